@@ -1,15 +1,29 @@
-use std::fs;
-use std::path::Path;
+use std::collections::HashMap;
 
 use serde_json as sj;
-use git2;
 
-use crate::error::{Result, Error, ErrorCategory};
+use crate::error::{Result, Error, ErrorCategory, ErrorKind};
 use super::{ 
     MM_GIT_KEY, 
     MM_GIT_USE_DEFAULT_KEY,
     MM_GIT_EMAIL_KEY,
     MM_GIT_NAME_KEY,
+    MM_TRASH_KEY,
+    MM_TRASH_PURGE_DAYS_KEY,
+    MM_JOURNAL_KEY,
+    MM_JOURNAL_FOLDER_KEY,
+    MM_JOURNAL_PATTERN_KEY,
+    MM_JOURNAL_TEMPLATE_KEY,
+    MM_JOURNAL_DEFAULT_FOLDER,
+    MM_JOURNAL_DEFAULT_PATTERN,
+    MM_EDITOR_KEY,
+    MM_EDITOR_COMMAND_KEY,
+    MM_GIT_OWN_SECTION,
+    MM_GIT_EDITOR_KEY,
+    MM_CRYPTO_KEY,
+    MM_CRYPTO_SALT_KEY,
+    MM_CRYPTO_ENCRYPT_ALL_KEY,
+    MM_CRYPTO_IDLE_TIMEOUT_KEY,
 };
 
 
@@ -18,8 +32,14 @@ pub(crate) struct Config {
     /// Internal JSON structure 
     internal: sj::Value,
 
-    /// Default git config
-    git_config: git2::Config,
+    /// Local parameters, that are not committed and hence never come
+    /// from other copies of a repository (e.g. an editor command)
+    local: sj::Value,
+
+    /// Needed parameters of default git config (empty for configurations
+    /// with explicit user). They are copied, so the configuration can be
+    /// shared between threads
+    git_config: HashMap<String, String>,
 }
 
 
@@ -41,39 +61,68 @@ impl Config {
         // ... and wrap it into an instance of `Config`
         //
 
-        Config::from_raw(default_config)
+        Config::from_raw(default_config, Config::read_git_config()?)
     }
 
 
-    /// Loads configuration from a file.
+    /// Creates a default configuration with specific git user instead
+    /// of system-default one. System and global git configurations are
+    /// not read at all.
     /// 
-    /// * `config_file` - path to a file to read configuration from
-    pub(crate) fn load(config_file: &Path) -> Result<Self> {
-        let file_content = fs::read(config_file)?;
+    /// * `name` - git user name
+    /// * `email` - git user email
+    pub(crate) fn with_user(name: &str, email: &str) -> Result<Self> {
+        let default_config = sj::json!({
+            MM_GIT_KEY: {
+                MM_GIT_USE_DEFAULT_KEY: false,
+                MM_GIT_NAME_KEY: name,
+                MM_GIT_EMAIL_KEY: email,
+            }
+        });
+
+        Config::from_raw(default_config, HashMap::new())
+    }
 
+
+    /// Loads configuration from a file's content.
+    /// 
+    /// * `file_content` - content of a file to read configuration from
+    pub(crate) fn from_slice(file_content: &[u8]) -> Result<Self> {
         //
         // Create a JSON instance and config from it
         //
 
-        Config::from_raw(sj::from_slice(file_content.as_slice())?)
+        Config::from_raw(sj::from_slice(file_content)?, Config::read_git_config()?)
     }
 
 
-    /// Saves a configuration into a file.
-    /// 
-    /// * `config_file` - path to a file to write config to
-    pub(crate) fn save(&self, config_file: &Path) -> Result<()> {
+    /// Serializes a configuration, so it can be saved into a file.
+    pub(crate) fn to_vec(&self) -> Result<Vec<u8>> {
         //
-        // Well... Just save it into a file!
+        // Well... Just make a pretty JSON!
         //
 
-        let json = sj::to_string_pretty(&self.internal)?;
-        fs::write(config_file, json)?;
+        sj::to_vec_pretty(&self.internal)
+            .map_err(Error::from)
+    }
+
 
+    /// Loads local parameters from a file's content.
+    /// 
+    /// * `file_content` - content of a file to read local parameters from
+    pub(crate) fn load_local(&mut self, file_content: &[u8]) -> Result<()> {
+        self.local = sj::from_slice(file_content)?;
         Ok(())
     }
 
 
+    /// Serializes local parameters, so they can be saved into a file.
+    pub(crate) fn local_to_vec(&self) -> Result<Vec<u8>> {
+        sj::to_vec_pretty(&self.local)
+            .map_err(Error::from)
+    }
+
+
     /// Query git user email
     pub(crate) fn query_email(&self) -> Result<&str> {
         self.query_git_parameter(MM_GIT_EMAIL_KEY)
@@ -86,6 +135,215 @@ impl Config {
     }
 
 
+    /// Sets git user, that is used instead of system-default one.
+    /// 
+    /// * `name` - git user name
+    /// * `email` - git user email
+    pub(crate) fn set_user(&mut self, name: &str, email: &str) {
+        self.set_parameter(MM_GIT_KEY, MM_GIT_USE_DEFAULT_KEY, Some(sj::Value::Bool(false)));
+        self.set_parameter(MM_GIT_KEY, MM_GIT_NAME_KEY, Some(sj::Value::from(name)));
+        self.set_parameter(MM_GIT_KEY, MM_GIT_EMAIL_KEY, Some(sj::Value::from(email)));
+    }
+
+
+    /// Query number of days after which notes are purged from trash.
+    /// `None` means, that notes are never purged automatically.
+    pub(crate) fn query_trash_purge_days(&self) -> Option<u64> {
+        self.query_parameter(MM_TRASH_KEY, MM_TRASH_PURGE_DAYS_KEY)
+            .and_then(sj::Value::as_u64)
+    }
+
+
+    /// Sets number of days after which notes are purged from trash.
+    /// 
+    /// * `days` - number of days (`None` disables automatic purge)
+    pub(crate) fn set_trash_purge_days(&mut self, days: Option<u64>) {
+        self.set_parameter(MM_TRASH_KEY, MM_TRASH_PURGE_DAYS_KEY, days.map(sj::Value::from))
+    }
+
+
+    /// Query a folder with journal entries (relative to working directory).
+    pub(crate) fn query_journal_folder(&self) -> &str {
+        self.query_parameter(MM_JOURNAL_KEY, MM_JOURNAL_FOLDER_KEY)
+            .and_then(sj::Value::as_str)
+            .unwrap_or(MM_JOURNAL_DEFAULT_FOLDER)
+    }
+
+
+    /// Query a pattern of journal entries' paths (in `strftime` format).
+    pub(crate) fn query_journal_pattern(&self) -> &str {
+        self.query_parameter(MM_JOURNAL_KEY, MM_JOURNAL_PATTERN_KEY)
+            .and_then(sj::Value::as_str)
+            .unwrap_or(MM_JOURNAL_DEFAULT_PATTERN)
+    }
+
+
+    /// Query a name of a template for new journal entries.
+    pub(crate) fn query_journal_template(&self) -> Option<&str> {
+        self.query_parameter(MM_JOURNAL_KEY, MM_JOURNAL_TEMPLATE_KEY)
+            .and_then(sj::Value::as_str)
+    }
+
+
+    /// Sets a folder with journal entries.
+    /// 
+    /// * `folder` - folder relative to working directory (`None` resets to default)
+    pub(crate) fn set_journal_folder(&mut self, folder: Option<&str>) {
+        self.set_parameter(MM_JOURNAL_KEY, MM_JOURNAL_FOLDER_KEY, folder.map(sj::Value::from))
+    }
+
+
+    /// Sets a pattern of journal entries' paths.
+    /// 
+    /// * `pattern` - pattern in `strftime` format (`None` resets to default)
+    pub(crate) fn set_journal_pattern(&mut self, pattern: Option<&str>) {
+        self.set_parameter(MM_JOURNAL_KEY, MM_JOURNAL_PATTERN_KEY, pattern.map(sj::Value::from))
+    }
+
+
+    /// Sets a template for new journal entries.
+    /// 
+    /// * `template` - name of a template (`None` means no template)
+    pub(crate) fn set_journal_template(&mut self, template: Option<&str>) {
+        self.set_parameter(MM_JOURNAL_KEY, MM_JOURNAL_TEMPLATE_KEY, template.map(sj::Value::from))
+    }
+
+
+    /// Query an editor command. It is a local parameter, because
+    /// a command from a shared configuration could run any program.
+    /// User's git configuration is checked if there is no local one.
+    pub(crate) fn query_editor(&self) -> Option<&str> {
+        Config::get_value(&self.local, MM_EDITOR_KEY, MM_EDITOR_COMMAND_KEY)
+            .and_then(sj::Value::as_str)
+            .or_else(|| self.git_config.get(MM_GIT_EDITOR_KEY).map(String::as_str))
+    }
+
+
+    /// Sets an editor command (as a local parameter).
+    /// 
+    /// * `editor` - editor command (`None` means system default one)
+    pub(crate) fn set_editor(&mut self, editor: Option<&str>) {
+        Config::set_value(&mut self.local, MM_EDITOR_KEY, MM_EDITOR_COMMAND_KEY, editor.map(sj::Value::from))
+    }
+
+
+    /// Query a salt for key derivation. Returns `None` if there is no 
+    /// salt or it is malformed.
+    pub(crate) fn query_crypto_salt(&self) -> Option<Vec<u8>> {
+        let salt = self.query_parameter(MM_CRYPTO_KEY, MM_CRYPTO_SALT_KEY)
+            .and_then(sj::Value::as_str)?;
+
+        (0..salt.len())
+            .step_by(2)
+            .map(|position| salt.get(position..position + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect()
+    }
+
+
+    /// Sets a salt for key derivation.
+    /// 
+    /// * `salt` - raw salt bytes
+    pub(crate) fn set_crypto_salt(&mut self, salt: &[u8]) {
+        let salt = salt
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        self.set_parameter(MM_CRYPTO_KEY, MM_CRYPTO_SALT_KEY, Some(sj::Value::from(salt)))
+    }
+
+
+    /// Query if all notes in a repository are encrypted.
+    pub(crate) fn query_encrypt_all(&self) -> bool {
+        self.query_parameter(MM_CRYPTO_KEY, MM_CRYPTO_ENCRYPT_ALL_KEY)
+            .and_then(sj::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+
+    /// Sets if all notes in a repository are encrypted.
+    /// 
+    /// * `encrypt_all` - `true` to encrypt all notes
+    pub(crate) fn set_encrypt_all(&mut self, encrypt_all: bool) {
+        self.set_parameter(MM_CRYPTO_KEY, MM_CRYPTO_ENCRYPT_ALL_KEY, Some(sj::Value::Bool(encrypt_all)))
+    }
+
+
+    /// Query a number of seconds after which an idle unlocked repository
+    /// is locked. Returns `None` if there is no timeout.
+    pub(crate) fn query_idle_timeout(&self) -> Option<u64> {
+        self.query_parameter(MM_CRYPTO_KEY, MM_CRYPTO_IDLE_TIMEOUT_KEY)
+            .and_then(sj::Value::as_u64)
+    }
+
+
+    /// Sets a number of seconds after which an idle unlocked repository
+    /// is locked.
+    /// 
+    /// * `seconds` - idle timeout (`None` disables timeout)
+    pub(crate) fn set_idle_timeout(&mut self, seconds: Option<u64>) {
+        self.set_parameter(MM_CRYPTO_KEY, MM_CRYPTO_IDLE_TIMEOUT_KEY, seconds.map(sj::Value::from))
+    }
+
+
+    /// Query a parameter from a specific section.
+    /// 
+    /// * `section` - name of a section
+    /// * `parameter` - name of a parameter inside of the section
+    fn query_parameter(&self, section: &str, parameter: &str) -> Option<&sj::Value> {
+        Config::get_value(&self.internal, section, parameter)
+    }
+
+
+    /// Sets or removes a parameter in a specific section. The section is
+    /// created if necessary.
+    /// 
+    /// * `section` - name of a section
+    /// * `parameter` - name of a parameter inside of the section
+    /// * `value` - new value of the parameter (`None` removes it)
+    fn set_parameter(&mut self, section: &str, parameter: &str, value: Option<sj::Value>) {
+        Config::set_value(&mut self.internal, section, parameter, value)
+    }
+
+
+    /// Query a parameter from a specific section of a JSON object.
+    /// 
+    /// * `config` - JSON object with sections
+    /// * `section` - name of a section
+    /// * `parameter` - name of a parameter inside of the section
+    fn get_value<'a>(config: &'a sj::Value, section: &str, parameter: &str) -> Option<&'a sj::Value> {
+        config
+            .get(section)
+            .and_then(|section| section.get(parameter))
+    }
+
+
+    /// Sets or removes a parameter in a specific section of a JSON object.
+    /// The section is created if necessary.
+    /// 
+    /// * `config` - JSON object with sections
+    /// * `section` - name of a section
+    /// * `parameter` - name of a parameter inside of the section
+    /// * `value` - new value of the parameter (`None` removes it)
+    fn set_value(config: &mut sj::Value, section: &str, parameter: &str, value: Option<sj::Value>) {
+        let config = match config.as_object_mut() {
+            Some(config) => config,
+            None => return
+        };
+
+        let section = config
+            .entry(section)
+            .or_insert_with(|| sj::Value::Object(sj::Map::new()));
+
+        if let Some(section) = section.as_object_mut() {
+            match value {
+                Some(value) => section.insert(parameter.to_owned(), value),
+                None => section.remove(parameter)
+            };
+        }
+    }
+
+
     /// Query git parameter by its name
     fn query_git_parameter(&self, parameter: &str) -> Result<&str> {
         let section = self.query_git_section()?;
@@ -100,8 +358,10 @@ impl Config {
 
         if let Some(true) = use_default.as_bool() {
             self.git_config
-                .get_str(parameter)
-                .map_err(Error::from)
+                .get(parameter)
+                .map(String::as_str)
+                .ok_or(Error::from_string(format!("missing {} parameter in default git config", parameter), ErrorCategory::Config)
+                    .with_kind(ErrorKind::NotFound))
         }
         else {
             section.get(parameter)
@@ -129,14 +389,43 @@ impl Config {
 
     /// Creates a config instance from parsed JSON, which is validated 
     /// against proper JSON schema.
-    fn from_raw(raw_config: sj::Value) -> Result<Self> {
+    /// 
+    /// * `raw_config` - parsed JSON
+    /// * `git_config` - parameters of default git config
+    fn from_raw(raw_config: sj::Value, git_config: HashMap<String, String>) -> Result<Self> {
         Config::validate(&raw_config)?;
 
         Ok(Config {
             internal: raw_config,
 
-            git_config: git2::Config::open_default()
-                .and_then(|mut config| config.snapshot())?
+            local: sj::Value::Object(sj::Map::new()),
+
+            git_config
         })
     }
+
+
+    /// Reads parameters of default git config. Only user identity and
+    /// `mm.*` parameters are copied, other ones (e.g. credential helpers)
+    /// are not needed by the library.
+    fn read_git_config() -> Result<HashMap<String, String>> {
+        let config = git2::Config::open_default()?.snapshot()?;
+        let mut parameters = HashMap::new();
+
+        let mut entries = config.entries(None)?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let is_needed = |name: &str| name == MM_GIT_NAME_KEY
+                || name == MM_GIT_EMAIL_KEY
+                || name.starts_with(MM_GIT_OWN_SECTION);
+
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                if is_needed(name) {
+                    parameters.insert(name.to_owned(), value.to_owned());
+                }
+            }
+        }
+
+        Ok(parameters)
+    }
 }
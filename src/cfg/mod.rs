@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod cfg;
 
 
@@ -16,3 +17,58 @@ const MM_GIT_EMAIL_KEY: &str = "user.email";
 
 /// A string configuration property, that contains a git user name
 const MM_GIT_NAME_KEY: &str = "user.name";
+
+/// A key in config, that is responsible for trash configuration items
+const MM_TRASH_KEY: &str = "trash";
+
+/// An integer configuration property, that contains a number of days
+/// after which notes are purged from trash
+const MM_TRASH_PURGE_DAYS_KEY: &str = "purge.days";
+
+/// A key in config, that is responsible for journal configuration items
+const MM_JOURNAL_KEY: &str = "journal";
+
+/// A string configuration property, that contains a folder with journal
+/// entries (relative to repository's working directory)
+const MM_JOURNAL_FOLDER_KEY: &str = "folder";
+
+/// A string configuration property, that contains a pattern of journal 
+/// entries' paths (in `strftime` format)
+const MM_JOURNAL_PATTERN_KEY: &str = "pattern";
+
+/// A string configuration property, that contains a name of a template
+/// for new journal entries
+const MM_JOURNAL_TEMPLATE_KEY: &str = "template";
+
+/// Default folder with journal entries
+const MM_JOURNAL_DEFAULT_FOLDER: &str = "journal";
+
+/// Default pattern of journal entries' paths
+const MM_JOURNAL_DEFAULT_PATTERN: &str = "%Y/%m/%Y-%m-%d.md";
+
+/// A key in config, that is responsible for editor configuration items
+const MM_EDITOR_KEY: &str = "editor";
+
+/// A string configuration property, that contains an editor command
+const MM_EDITOR_COMMAND_KEY: &str = "command";
+
+/// Prefix of library's own parameters in git config
+const MM_GIT_OWN_SECTION: &str = "mm.";
+
+/// A git config parameter, that contains a user-wide editor command
+const MM_GIT_EDITOR_KEY: &str = "mm.editor";
+
+/// A key in config, that is responsible for encryption configuration items
+const MM_CRYPTO_KEY: &str = "crypto";
+
+/// A string configuration property, that contains a hex-encoded salt
+/// for key derivation
+const MM_CRYPTO_SALT_KEY: &str = "salt";
+
+/// A boolean configuration property, that designates encryption of 
+/// all notes in a repository
+const MM_CRYPTO_ENCRYPT_ALL_KEY: &str = "encrypt.all";
+
+/// An integer configuration property, that contains a number of seconds
+/// after which an idle unlocked repository is locked
+const MM_CRYPTO_IDLE_TIMEOUT_KEY: &str = "idle.timeout";
@@ -0,0 +1,171 @@
+use argon2::Argon2;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, KeyInit, AeadCore};
+use chacha20poly1305::aead::{Aead, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result, ErrorCategory, ErrorKind};
+use super::{
+    MM_ENCRYPTED_MAGIC,
+    MM_KEY_SIZE,
+    MM_SALT_SIZE,
+    MM_NONCE_SIZE,
+    MM_KEY_CHECK_PLAINTEXT,
+};
+
+
+/// Symmetric key, that is derived from a passphrase. Key bytes are
+/// zeroized when the key is dropped.
+pub(crate) struct Key {
+    /// Raw key bytes
+    bytes: [u8; MM_KEY_SIZE],
+}
+
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+
+/// Generates a random salt for key derivation.
+pub(crate) fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; MM_SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+
+    salt
+}
+
+
+/// Derives a key from a passphrase using Argon2.
+/// 
+/// * `passphrase` - user's passphrase
+/// * `salt` - repository-specific salt
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut bytes = [0u8; MM_KEY_SIZE];
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut bytes)
+        .map_err(|err| Error::from_string(format!("cannot derive key: {}", err), ErrorCategory::Passphrase))?;
+
+    Ok(Key { bytes })
+}
+
+
+/// Checks if content is encrypted (i.e. starts with a special header).
+/// 
+/// * `content` - content to check
+pub(crate) fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(MM_ENCRYPTED_MAGIC)
+}
+
+
+/// Encrypts content with XChaCha20-Poly1305. Result contains a header 
+/// and a random nonce followed by ciphertext.
+/// 
+/// * `key` - key to encrypt with
+/// * `plaintext` - content to encrypt
+pub(crate) fn encrypt(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(&key.bytes.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| Error::from_string("cannot encrypt content", ErrorCategory::Generic))?;
+
+    let mut content = Vec::with_capacity(MM_ENCRYPTED_MAGIC.len() + MM_NONCE_SIZE + ciphertext.len());
+    content.extend_from_slice(MM_ENCRYPTED_MAGIC);
+    content.extend_from_slice(&nonce);
+    content.extend_from_slice(&ciphertext);
+
+    Ok(content)
+}
+
+
+/// Decrypts content produced by [`encrypt`]. Authentication failure 
+/// means, that the key is derived from a wrong passphrase (or content 
+/// is corrupted).
+/// 
+/// * `key` - key to decrypt with
+/// * `content` - encrypted content
+pub(crate) fn decrypt(key: &Key, content: &[u8]) -> Result<Vec<u8>> {
+    if !is_encrypted(content) || content.len() < MM_ENCRYPTED_MAGIC.len() + MM_NONCE_SIZE {
+        return Err(Error::from_string("content is not encrypted", ErrorCategory::Generic));
+    }
+
+    let (nonce, ciphertext) = content[MM_ENCRYPTED_MAGIC.len()..].split_at(MM_NONCE_SIZE);
+    let cipher = XChaCha20Poly1305::new(&key.bytes.into());
+
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::from_string("wrong passphrase or corrupted content", ErrorCategory::Passphrase).with_kind(ErrorKind::AuthFailed))
+}
+
+
+/// Creates a check value, that allows to verify a key without 
+/// decrypting any note.
+/// 
+/// * `key` - key to create a check value for
+pub(crate) fn create_check(key: &Key) -> Result<Vec<u8>> {
+    encrypt(key, MM_KEY_CHECK_PLAINTEXT)
+}
+
+
+/// Verifies a key against a check value produced by [`create_check`].
+/// 
+/// * `key` - key to verify
+/// * `check` - stored check value
+pub(crate) fn verify_check(key: &Key, check: &[u8]) -> Result<()> {
+    if decrypt(key, check)? != MM_KEY_CHECK_PLAINTEXT {
+        return Err(Error::from_string("wrong passphrase", ErrorCategory::Passphrase).with_kind(ErrorKind::AuthFailed));
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn content_survives_encryption() {
+        let key = derive_key("passphrase", &generate_salt()).unwrap();
+        let content = encrypt(&key, b"# Note").unwrap();
+
+        assert!(is_encrypted(&content));
+        assert!(!is_encrypted(b"# Note"));
+        assert_eq!(decrypt(&key, &content).unwrap(), b"# Note");
+    }
+
+
+    #[test]
+    fn wrong_key_is_rejected() {
+        let salt = generate_salt();
+        let key = derive_key("passphrase", &salt).unwrap();
+        let wrong_key = derive_key("wrong", &salt).unwrap();
+
+        let check = create_check(&key).unwrap();
+        assert!(verify_check(&key, &check).is_ok());
+        assert_eq!(verify_check(&wrong_key, &check).unwrap_err().get_kind(), ErrorKind::AuthFailed);
+
+        let content = encrypt(&key, b"# Note").unwrap();
+        assert_eq!(decrypt(&wrong_key, &content).unwrap_err().get_kind(), ErrorKind::AuthFailed);
+    }
+
+
+    #[test]
+    fn corrupted_content_is_rejected() {
+        let key = derive_key("passphrase", &generate_salt()).unwrap();
+
+        let mut content = encrypt(&key, b"# Note").unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 1;
+        assert!(decrypt(&key, &content).is_err());
+
+        assert!(decrypt(&key, MM_ENCRYPTED_MAGIC).is_err());
+        assert!(decrypt(&key, b"# Note").is_err());
+    }
+}
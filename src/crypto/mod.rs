@@ -0,0 +1,32 @@
+#[allow(clippy::module_inception)]
+mod crypto;
+mod session;
+
+
+pub(crate) use self::crypto::{
+    Key,
+    generate_salt,
+    derive_key,
+    is_encrypted,
+    encrypt,
+    decrypt,
+    create_check,
+    verify_check,
+};
+pub(crate) use self::session::{Session};
+
+
+/// Header, that marks encrypted content
+const MM_ENCRYPTED_MAGIC: &[u8] = b"MMCRYPT1";
+
+/// Size of a derived key in bytes
+const MM_KEY_SIZE: usize = 32;
+
+/// Size of a salt for key derivation in bytes
+const MM_SALT_SIZE: usize = 16;
+
+/// Size of a nonce (XChaCha20-Poly1305) in bytes
+const MM_NONCE_SIZE: usize = 24;
+
+/// Known plain text of a check value, that verifies a passphrase
+const MM_KEY_CHECK_PLAINTEXT: &[u8] = b"mm key check";
@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result, ErrorCategory, ErrorKind};
+use super::crypto::Key;
+
+
+/// An unlock session, that holds a key while a repository is unlocked.
+/// 
+/// The key is dropped (and hence zeroized) when the session is locked
+/// explicitly or it stays idle longer than a timeout.
+pub(crate) struct Session {
+    /// Key of the session. `None` if the session is locked
+    key: Option<Key>,
+
+    /// Time of the last use of the key
+    last_used: Instant,
+}
+
+
+impl Session {
+    /// Creates a locked session.
+    pub(crate) fn new() -> Self {
+        Session {
+            key: None,
+            last_used: Instant::now()
+        }
+    }
+
+
+    /// Unlocks the session with a key.
+    /// 
+    /// * `key` - verified key
+    pub(crate) fn unlock(&mut self, key: Key) {
+        self.key = Some(key);
+        self.last_used = Instant::now();
+    }
+
+
+    /// Locks the session. The key is zeroized.
+    pub(crate) fn lock(&mut self) {
+        self.key = None;
+    }
+
+
+    /// Checks if the session is unlocked. An expired session is locked here.
+    /// 
+    /// * `timeout` - idle timeout (`None` means no timeout)
+    pub(crate) fn is_unlocked(&mut self, timeout: Option<Duration>) -> bool {
+        self.expire(timeout);
+        self.key.is_some()
+    }
+
+
+    /// Obtains the key and marks the session as used.
+    /// 
+    /// * `timeout` - idle timeout (`None` means no timeout)
+    pub(crate) fn get_key(&mut self, timeout: Option<Duration>) -> Result<&Key> {
+        self.expire(timeout);
+        self.last_used = Instant::now();
+
+        self.key
+            .as_ref()
+            .ok_or(Error::from_string("repository is locked, passphrase is required", ErrorCategory::Passphrase).with_kind(ErrorKind::Locked))
+    }
+
+
+    /// Locks the session if it is idle longer than a timeout.
+    /// 
+    /// * `timeout` - idle timeout (`None` means no timeout)
+    fn expire(&mut self, timeout: Option<Duration>) {
+        if timeout.is_some_and(|timeout| self.last_used.elapsed() > timeout) {
+            self.lock();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{derive_key, generate_salt};
+
+
+    #[test]
+    fn session_is_locked_explicitly() {
+        let mut session = Session::new();
+        assert!(!session.is_unlocked(None));
+        assert_eq!(session.get_key(None).err().unwrap().get_kind(), ErrorKind::Locked);
+
+        session.unlock(derive_key("passphrase", &generate_salt()).unwrap());
+        assert!(session.is_unlocked(None));
+        assert!(session.get_key(None).is_ok());
+
+        session.lock();
+        assert!(!session.is_unlocked(None));
+    }
+
+
+    #[test]
+    fn idle_session_expires() {
+        let mut session = Session::new();
+        session.unlock(derive_key("passphrase", &generate_salt()).unwrap());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(session.is_unlocked(Some(Duration::from_secs(60))));
+        assert!(!session.is_unlocked(Some(Duration::from_millis(10))));
+        assert!(session.get_key(None).is_err());
+    }
+}
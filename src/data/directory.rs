@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::misc;
+use crate::error::{Result, Error};
+use super::storage::{Storage, Revision};
+
+
+/// Storage, that keeps files in a plain directory without versioning.
+pub struct DirectoryStorage {
+    /// Root directory
+    root: PathBuf,
+}
+
+
+impl DirectoryStorage {
+    /// Opens a directory as a storage. The directory is created if
+    /// necessary.
+    /// 
+    /// * `path` - path to the directory
+    pub fn open_or_create(path: &Path) -> Result<Self> {
+        misc::create_folder_recursive(path)?;
+
+        Ok(DirectoryStorage {
+            root: path.to_path_buf()
+        })
+    }
+}
+
+
+impl Storage for DirectoryStorage {
+    fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).exists()
+    }
+
+
+    fn is_folder(&self, path: &Path) -> bool {
+        self.root.join(path).is_dir()
+    }
+
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+            .map_err(Error::from)
+    }
+
+
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let full_path = self.root.join(path);
+        if let Some(parent) = full_path.parent() {
+            misc::create_folder_recursive(parent)?;
+        }
+
+        fs::write(full_path, content)
+            .map_err(Error::from)
+    }
+
+
+    fn create_folder(&self, path: &Path) -> Result<()> {
+        misc::create_folder_recursive(self.root.join(path))
+    }
+
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        let full_path = self.root.join(path);
+        if full_path.is_dir() {
+            fs::remove_dir_all(full_path)?;
+        }
+        else {
+            fs::remove_file(full_path)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let destination = self.root.join(to);
+        if let Some(parent) = destination.parent() {
+            misc::create_folder_recursive(parent)?;
+        }
+
+        fs::rename(self.root.join(from), destination)
+            .map_err(Error::from)
+    }
+
+
+    fn list(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        let full_path = self.root.join(folder);
+        if !full_path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let files = misc::list_files_recursive(&full_path, |_path| true)?;
+
+        Ok(files
+            .iter()
+            .filter_map(|file| file.strip_prefix(&self.root).ok())
+            .map(Path::to_path_buf)
+            .collect())
+    }
+
+
+    fn commit(&self, _author_name: &str, _author_email: &str, _changed: &[&Path], _removed: &[&Path], _message: &str, _time: Option<SystemTime>) -> Result<()> {
+        //
+        // No versioning, changes are already in place
+        //
+
+        Ok(())
+    }
+
+
+    fn history(&self, _path: Option<&Path>) -> Result<Vec<Revision>> {
+        Ok(Vec::new())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+
+    #[test]
+    fn files_are_kept_in_directory() {
+        let root = env::temp_dir().join(format!("mm-directory-test-{}", Uuid::new_v4()));
+        let storage = DirectoryStorage::open_or_create(&root).unwrap();
+
+        storage.write(Path::new("b/two.md"), b"2").unwrap();
+        storage.write(Path::new("a/one.md"), b"1").unwrap();
+        storage.create_folder(Path::new("empty")).unwrap();
+
+        assert_eq!(fs::read(root.join("a/one.md")).unwrap(), b"1");
+        assert!(storage.is_folder(Path::new("empty")));
+        assert_eq!(storage.list(Path::new("")).unwrap(), vec![PathBuf::from("a/one.md"), PathBuf::from("b/two.md")]);
+
+        storage.rename(Path::new("b"), Path::new("c/d")).unwrap();
+        assert!(!storage.exists(Path::new("b")));
+        assert_eq!(storage.read(Path::new("c/d/two.md")).unwrap(), b"2");
+
+        storage.delete(Path::new("c")).unwrap();
+        storage.delete(Path::new("a/one.md")).unwrap();
+        assert_eq!(storage.list(Path::new("")).unwrap(), Vec::<PathBuf>::new());
+        assert!(storage.delete(Path::new("missing")).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+
+    #[test]
+    fn directory_keeps_no_history() {
+        let root = env::temp_dir().join(format!("mm-directory-test-{}", Uuid::new_v4()));
+        let storage = DirectoryStorage::open_or_create(&root).unwrap();
+        let path = Path::new("note.md");
+
+        storage.write(path, b"content").unwrap();
+        storage.commit("Tester", "tester@example.com", &[path], &[], "chore: commit", None).unwrap();
+
+        assert!(storage.history(None).unwrap().is_empty());
+        assert!(storage.read_revision(path, "0").is_err());
+        assert!(storage.list_revision(path, "0").is_err());
+        assert!(storage.is_changed(path).unwrap());
+        assert!(storage.status().unwrap().is_empty());
+        assert!(storage.bundle_history().unwrap().is_none());
+        assert!(storage.unbundle_history(b"").is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
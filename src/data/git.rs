@@ -0,0 +1,651 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use git2;
+
+use crate::misc;
+use crate::error::{Result, Error, ErrorCategory};
+use super::storage::{Storage, Revision, StatusEntry, StatusKind};
+use super::{MM_GIT_HEAD_REF, MM_GIT_FOLDER, MM_GIT_EXCLUDE_FILE, MM_GIT_BUNDLE_HEADER, MM_GIT_BRANCH_PREFIX};
+
+
+/// Storage, that keeps files in a working directory of a git repository
+/// and records each change as a commit.
+/// 
+/// Git operations are serialized internally, so the storage may be
+/// shared between threads.
+pub struct GitStorage {
+    /// Internal git repository, that manages version control
+    internal_repo: Mutex<git2::Repository>,
+
+    /// Repository's working directory
+    workdir: PathBuf,
+
+    /// Optional list of remotes. `None` if repository has no remotes
+    #[allow(dead_code)]
+    remotes: Option<Vec<String>>,
+}
+
+
+impl GitStorage {
+    /// Opens or creates a git repository by its path.
+    /// 
+    /// * `path` - path to the repository's directory
+    pub fn open_or_create(path: &Path) -> Result<Self> {
+        let internal_repo = git2::Repository::open(path)
+            .or_else(|_error| git2::Repository::init(path))?;
+
+        let workdir = internal_repo
+            .workdir()
+            .ok_or(Error::from_string("cannot get working directory", ErrorCategory::Git))?
+            .to_path_buf();
+
+        let remotes = internal_repo
+            .remotes()
+            .ok()
+            .map(|remotes| remotes.iter().flatten().map(str::to_owned).collect());
+
+        Ok(GitStorage {
+            internal_repo: Mutex::new(internal_repo),
+            workdir,
+            remotes
+        })
+    }
+
+
+    /// Locks internal git repository.
+    fn lock(&self) -> Result<MutexGuard<'_, git2::Repository>> {
+        self.internal_repo
+            .lock()
+            .map_err(|_| Error::from_string("git repository is poisoned", ErrorCategory::Git))
+    }
+
+
+    /// Returns commit, that HEAD points to (if any).
+    /// 
+    /// * `internal_repo` - locked internal git repository
+    fn get_head_commit(internal_repo: &git2::Repository) -> Option<git2::Commit<'_>> {
+        internal_repo
+            .refname_to_id(MM_GIT_HEAD_REF)
+            .and_then(|head_oid| internal_repo.find_commit(head_oid))
+            .ok()
+    }
+
+
+    /// Returns identifier of an object at a path in a commit's tree (if any).
+    /// 
+    /// * `commit` - commit to look into
+    /// * `path` - relative path to an object
+    fn get_entry_id(commit: &git2::Commit, path: &Path) -> Option<git2::Oid> {
+        commit.tree()
+            .and_then(|tree| tree.get_path(path))
+            .map(|entry| entry.id())
+            .ok()
+    }
+
+
+    /// Checks if a commit changes a file compared to its first parent.
+    /// 
+    /// * `commit` - commit to check
+    /// * `path` - relative path to a file
+    fn touches(commit: &git2::Commit, path: &Path) -> bool {
+        let parent_entry = commit
+            .parent(0)
+            .ok()
+            .and_then(|parent| Self::get_entry_id(&parent, path));
+
+        Self::get_entry_id(commit, path) != parent_entry
+    }
+}
+
+
+impl Storage for GitStorage {
+    fn get_root(&self) -> &Path {
+        &self.workdir
+    }
+
+
+    fn exists(&self, path: &Path) -> bool {
+        self.workdir.join(path).exists()
+    }
+
+
+    fn is_folder(&self, path: &Path) -> bool {
+        self.workdir.join(path).is_dir()
+    }
+
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(self.workdir.join(path))
+            .map_err(Error::from)
+    }
+
+
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let full_path = self.workdir.join(path);
+        if let Some(parent) = full_path.parent() {
+            misc::create_folder_recursive(parent)?;
+        }
+
+        fs::write(full_path, content)
+            .map_err(Error::from)
+    }
+
+
+    fn create_folder(&self, path: &Path) -> Result<()> {
+        misc::create_folder_recursive(self.workdir.join(path))
+    }
+
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        let full_path = self.workdir.join(path);
+        if full_path.is_dir() {
+            fs::remove_dir_all(full_path)?;
+        }
+        else {
+            fs::remove_file(full_path)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let destination = self.workdir.join(to);
+        if let Some(parent) = destination.parent() {
+            misc::create_folder_recursive(parent)?;
+        }
+
+        fs::rename(self.workdir.join(from), destination)
+            .map_err(Error::from)
+    }
+
+
+    fn list(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        let full_path = self.workdir.join(folder);
+        if !full_path.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        //
+        // Git's internal folder is never listed
+        //
+
+        let git_folder = self.workdir.join(MM_GIT_FOLDER);
+        let files = misc::list_files_recursive(&full_path, |path| path != git_folder)?;
+
+        Ok(files
+            .iter()
+            .filter_map(|file| file.strip_prefix(&self.workdir).ok())
+            .map(Path::to_path_buf)
+            .collect())
+    }
+
+
+    fn commit(&self, author_name: &str, author_email: &str, changed: &[&Path], removed: &[&Path], message: &str, time: Option<SystemTime>) -> Result<()> {
+        //
+        // First of all, we need to stage all the changes.
+        // Empty pathspec matches everything in libgit2, hence
+        // empty lists are skipped explicitly.
+        // `git2::Index::add_all` is used in order to take `.gitignore`
+        // into account, because `git2::Index::add_path` forces files
+        // to be added.
+        //
+
+        let internal_repo = self.lock()?;
+        let mut index = internal_repo.index()?;
+
+        if !removed.is_empty() {
+            index.remove_all(removed.iter(), None)?;
+        }
+
+        if !changed.is_empty() {
+            index.add_all(changed.iter(), git2::IndexAddOption::DEFAULT, None)?;
+        }
+
+        index.write()?;
+
+        let tree_oid = index.write_tree()?;
+
+        //
+        // Now let's create a commit
+        //
+
+        let tree = internal_repo.find_tree(tree_oid)?;
+        let author = match time {
+            Some(time) => {
+                let seconds = time
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs() as i64);
+
+                git2::Signature::new(author_name, author_email, &git2::Time::new(seconds, 0))?
+            },
+            None => git2::Signature::now(author_name, author_email)?
+        };
+
+        //
+        // Well... Here I need a slice with references for parents container,
+        // hence I MUST do it in the following scary way :(
+        //
+
+        let head = Self::get_head_commit(&internal_repo);
+
+        let head_holder;
+        let parents = match head.as_ref() {
+            Some(head) => {
+                head_holder = [head];
+                &head_holder[..]
+            },
+            _ => &[]
+        };
+
+        internal_repo.commit(Some(MM_GIT_HEAD_REF), &author, &author, message, &tree, parents)?;
+
+        Ok(())
+    }
+
+
+    fn history(&self, path: Option<&Path>) -> Result<Vec<Revision>> {
+        let internal_repo = self.lock()?;
+        if Self::get_head_commit(&internal_repo).is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut revwalk = internal_repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        let mut revisions = Vec::new();
+        for oid in revwalk {
+            let commit = internal_repo.find_commit(oid?)?;
+
+            if path.is_some_and(|path| !Self::touches(&commit, path)) {
+                continue;
+            }
+
+            let time = UNIX_EPOCH + Duration::from_secs(commit.time().seconds().max(0) as u64);
+
+            let revision = Revision::new(
+                commit.id().to_string(),
+                commit.message().unwrap_or_default(),
+                commit.author().name().unwrap_or_default(),
+                time
+            );
+
+            revisions.push(match commit.parent_id(0) {
+                Ok(parent_id) => revision.with_parent_id(parent_id.to_string()),
+                Err(_) => revision
+            });
+        }
+
+        Ok(revisions)
+    }
+
+
+    fn read_revision(&self, path: &Path, revision_id: &str) -> Result<Option<Vec<u8>>> {
+        let internal_repo = self.lock()?;
+        let commit = internal_repo.find_commit(git2::Oid::from_str(revision_id)?)?;
+
+        match Self::get_entry_id(&commit, path) {
+            Some(entry_id) => Ok(Some(internal_repo.find_blob(entry_id)?.content().to_vec())),
+            None => Ok(None)
+        }
+    }
+
+
+    fn list_revision(&self, path: &Path, revision_id: &str) -> Result<Vec<PathBuf>> {
+        let internal_repo = self.lock()?;
+        let commit = internal_repo.find_commit(git2::Oid::from_str(revision_id)?)?;
+
+        let entry = match commit.tree()?.get_path(path) {
+            Ok(entry) => entry,
+            Err(_) => return Ok(Vec::new())
+        };
+
+        if entry.kind() != Some(git2::ObjectType::Tree) {
+            return Ok(vec![path.to_path_buf()]);
+        }
+
+        let mut files = Vec::new();
+        internal_repo.find_tree(entry.id())?.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                if let Some(name) = entry.name() {
+                    files.push(path.join(root).join(name));
+                }
+            }
+
+            git2::TreeWalkResult::Ok
+        })?;
+
+        Ok(files)
+    }
+
+
+    fn is_changed(&self, path: &Path) -> Result<bool> {
+        let internal_repo = self.lock()?;
+
+        let committed = Self::get_head_commit(&internal_repo)
+            .and_then(|head| Self::get_entry_id(&head, path));
+
+        //
+        // Untracked ignored files are never committed, so
+        // they are considered unchanged
+        //
+
+        if committed.is_none() && internal_repo.is_path_ignored(path)? {
+            return Ok(false);
+        }
+
+        let absolute_path = self.workdir.join(path);
+        let current = match absolute_path.is_file() {
+            true => Some(git2::Oid::hash_file(git2::ObjectType::Blob, &absolute_path)?),
+            false => None
+        };
+
+        Ok(committed != current)
+    }
+
+
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        let internal_repo = self.lock()?;
+
+        let mut options = git2::StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .renames_head_to_index(true)
+            .renames_index_to_workdir(true);
+
+        let mut entries = Vec::new();
+        for entry in internal_repo.statuses(Some(&mut options))?.iter() {
+            let status = entry.status();
+
+            //
+            // Renames are described by diffs, other changes
+            // are described by entry's path
+            //
+
+            let rename = entry
+                .index_to_workdir()
+                .filter(|_delta| status.is_wt_renamed())
+                .or(entry.head_to_index().filter(|_delta| status.is_index_renamed()))
+                .and_then(|delta| Some((delta.old_file().path()?.to_path_buf(), delta.new_file().path()?.to_path_buf())));
+
+            let path = match entry.path() {
+                Some(path) => PathBuf::from(path),
+                None => continue
+            };
+
+            let entry = if status.is_conflicted() {
+                StatusEntry::new(path, StatusKind::Conflicted)
+            }
+            else if let Some((from, to)) = rename {
+                StatusEntry::new(to, StatusKind::Renamed { from })
+            }
+            else if status.intersects(git2::Status::WT_NEW | git2::Status::INDEX_NEW) {
+                StatusEntry::new(path, StatusKind::Added)
+            }
+            else if status.intersects(git2::Status::WT_DELETED | git2::Status::INDEX_DELETED) {
+                StatusEntry::new(path, StatusKind::Deleted)
+            }
+            else if status.intersects(git2::Status::WT_MODIFIED | git2::Status::INDEX_MODIFIED | git2::Status::WT_TYPECHANGE | git2::Status::INDEX_TYPECHANGE) {
+                StatusEntry::new(path, StatusKind::Modified)
+            }
+            else {
+                continue;
+            };
+
+            entries.push(entry);
+        }
+
+        entries.sort_by(|left, right| left.get_path().cmp(right.get_path()));
+        Ok(entries)
+    }
+
+
+    fn exclude(&self, paths: &[&Path]) -> Result<()> {
+        let exclude_file = self.lock()?
+            .path()
+            .join(MM_GIT_EXCLUDE_FILE);
+
+        let mut content = match fs::read_to_string(&exclude_file) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(Error::from(error))
+        };
+
+        //
+        // Patterns are anchored to working directory, so
+        // files with the same names in subfolders are kept
+        //
+
+        let mut is_changed = false;
+        for path in paths {
+            let pattern = path
+                .components()
+                .fold(String::new(), |pattern, component| pattern + "/" + &component.as_os_str().to_string_lossy());
+
+            if content.lines().any(|line| line.trim() == pattern) {
+                continue;
+            }
+
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+
+            content.push_str(&pattern);
+            content.push('\n');
+            is_changed = true;
+        }
+
+        if !is_changed {
+            return Ok(());
+        }
+
+        if let Some(parent) = exclude_file.parent() {
+            misc::create_folder_recursive(parent)?;
+        }
+
+        fs::write(&exclude_file, content)
+            .map_err(Error::from)
+    }
+
+
+    fn get_history_folder(&self) -> Option<&Path> {
+        Some(Path::new(MM_GIT_FOLDER))
+    }
+
+
+    fn bundle_history(&self) -> Result<Option<Vec<u8>>> {
+        let internal_repo = self.lock()?;
+
+        let mut refs = Vec::new();
+        for branch in internal_repo.branches(Some(git2::BranchType::Local))? {
+            let reference = branch?.0.into_reference();
+            if let (Some(name), Some(oid)) = (reference.name(), reference.target()) {
+                refs.push((name.to_owned(), oid));
+            }
+        }
+
+        let mut walk = internal_repo.revwalk()?;
+        for (_name, oid) in &refs {
+            walk.push(*oid)?;
+        }
+
+        let mut pack = git2::Buf::new();
+        let mut builder = internal_repo.packbuilder()?;
+        builder.insert_walk(&mut walk)?;
+        builder.write_buf(&mut pack)?;
+
+        let mut bundle = MM_GIT_BUNDLE_HEADER.as_bytes().to_vec();
+        if let Some(head_oid) = internal_repo.head().ok().and_then(|head| head.target()) {
+            bundle.extend(format!("{} {}\n", head_oid, MM_GIT_HEAD_REF).as_bytes());
+        }
+
+        for (name, oid) in &refs {
+            bundle.extend(format!("{} {}\n", oid, name).as_bytes());
+        }
+
+        bundle.push(b'\n');
+        bundle.extend(pack.as_ref());
+
+        Ok(Some(bundle))
+    }
+
+
+    fn unbundle_history(&self, bundle: &[u8]) -> Result<()> {
+        let invalid = || Error::from_string("invalid history bundle", ErrorCategory::Git);
+
+        //
+        // Only branches are accepted. Prerequisites (lines
+        // starting with '-') are not, because the history
+        // MUST be complete
+        //
+
+        let mut rest = bundle
+            .strip_prefix(MM_GIT_BUNDLE_HEADER.as_bytes())
+            .ok_or_else(invalid)?;
+
+        let mut head = None;
+        let mut refs = Vec::new();
+        loop {
+            let end = rest
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .ok_or_else(invalid)?;
+
+            let line = std::str::from_utf8(&rest[..end]).map_err(|_| invalid())?;
+            rest = &rest[end + 1..];
+
+            if line.is_empty() {
+                break;
+            }
+
+            let (oid, name) = line
+                .split_once(' ')
+                .ok_or_else(invalid)?;
+
+            let oid = git2::Oid::from_str(oid).map_err(|_| invalid())?;
+            match name {
+                MM_GIT_HEAD_REF => head = Some(oid),
+                name if name.starts_with(MM_GIT_BRANCH_PREFIX) && git2::Reference::is_valid_name(name) => refs.push((name.to_owned(), oid)),
+                _ => return Err(invalid())
+            }
+        }
+
+        let (head_name, head_oid) = refs
+            .iter()
+            .find(|(_name, oid)| Some(*oid) == head)
+            .or(refs.first())
+            .cloned()
+            .ok_or_else(invalid)?;
+
+        let internal_repo = self.lock()?;
+        if GitStorage::get_head_commit(&internal_repo).is_some() {
+            return Err(Error::from_string("repository has history already", ErrorCategory::Git));
+        }
+
+        let odb = internal_repo.odb()?;
+        let mut writer = odb.packwriter()?;
+        writer.write_all(rest)?;
+        writer.commit()?;
+
+        for (name, oid) in &refs {
+            internal_repo.find_commit(*oid)?;
+            internal_repo.reference(name, *oid, false, "history imported")?;
+        }
+
+        //
+        // Index is reset to the restored HEAD, so working
+        // files are compared with the history as usual
+        //
+
+        internal_repo.set_head(&head_name)?;
+        internal_repo.reset(internal_repo.find_commit(head_oid)?.as_object(), git2::ResetType::Mixed, None)?;
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+
+    /// Creates a storage in a new temporary folder.
+    fn create_storage() -> (GitStorage, PathBuf) {
+        let path = env::temp_dir().join(format!("mm-git-test-{}", Uuid::new_v4()));
+        misc::create_folder_recursive(&path).unwrap();
+
+        (GitStorage::open_or_create(&path).unwrap(), path)
+    }
+
+
+    #[test]
+    fn history_survives_bundle() {
+        let (source, source_path) = create_storage();
+        let note = Path::new("note.md");
+        for content in ["first", "second"] {
+            source.write(note, content.as_bytes()).unwrap();
+            source.commit("Tester", "tester@example.com", &[note], &[], content, None).unwrap();
+        }
+
+        let bundle = source.bundle_history().unwrap().unwrap();
+
+        let (target, target_path) = create_storage();
+        target.unbundle_history(&bundle).unwrap();
+
+        let history = target.history(None).unwrap();
+        assert_eq!(history, source.history(None).unwrap());
+        assert_eq!(target.read_revision(note, history[1].get_id()).unwrap().unwrap(), b"first");
+        assert!(target.unbundle_history(&bundle).is_err());
+
+        fs::remove_dir_all(source_path).unwrap();
+        fs::remove_dir_all(target_path).unwrap();
+    }
+
+
+    #[test]
+    fn folder_is_listed_as_of_revision() {
+        let (storage, path) = create_storage();
+        let files = [Path::new("folder/note.md"), Path::new("folder/nested/note.md")];
+        for file in files {
+            storage.create_folder(file.parent().unwrap()).unwrap();
+            storage.write(file, b"content").unwrap();
+        }
+
+        storage.commit("Tester", "tester@example.com", &files, &[], "added", None).unwrap();
+        let revision_id = storage.history(None).unwrap()[0].get_id().to_owned();
+
+        let mut listed = storage.list_revision(Path::new("folder"), &revision_id).unwrap();
+        listed.sort();
+
+        assert_eq!(listed, [PathBuf::from("folder/nested/note.md"), PathBuf::from("folder/note.md")]);
+        assert_eq!(storage.list_revision(files[0], &revision_id).unwrap(), [files[0]]);
+        assert!(storage.list_revision(Path::new("absent"), &revision_id).unwrap().is_empty());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+
+    #[test]
+    fn incomplete_bundle_is_rejected() {
+        let (storage, path) = create_storage();
+
+        let bundle = format!("{}-{} \n\n", MM_GIT_BUNDLE_HEADER, git2::Oid::zero());
+        assert!(storage.unbundle_history(bundle.as_bytes()).is_err());
+
+        let bundle = format!("{}{} refs/tags/v1\n\n", MM_GIT_BUNDLE_HEADER, git2::Oid::zero());
+        assert!(storage.unbundle_history(bundle.as_bytes()).is_err());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
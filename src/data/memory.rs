@@ -0,0 +1,455 @@
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::error::{Result, Error, ErrorCategory, ErrorKind};
+use super::storage::{Storage, Revision};
+
+
+/// A revision with contents of files it touches.
+struct MemoryRevision {
+    /// Revision's description
+    revision: Revision,
+
+    /// Contents of files changed in the revision (`None` for removed files)
+    files: BTreeMap<PathBuf, Option<Vec<u8>>>,
+}
+
+
+/// Internal state of in-memory storage.
+#[derive(Default)]
+struct MemoryState {
+    /// Files' contents by their paths
+    files: BTreeMap<PathBuf, Vec<u8>>,
+
+    /// Explicitly created folders (folders with files are implied)
+    folders: BTreeSet<PathBuf>,
+
+    /// History of revisions (the oldest first)
+    revisions: Vec<MemoryRevision>,
+}
+
+
+impl MemoryState {
+    /// Checks if any file is located inside of a folder.
+    /// 
+    /// * `folder` - relative path to a folder
+    fn has_files_in(&self, folder: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|path| path != folder && path.starts_with(folder))
+    }
+
+
+    /// Registers a folder and all its parents.
+    /// 
+    /// * `folder` - relative path to a folder
+    fn add_folder(&mut self, folder: &Path) {
+        for ancestor in folder.ancestors() {
+            if !ancestor.as_os_str().is_empty() {
+                self.folders.insert(ancestor.to_path_buf());
+            }
+        }
+    }
+}
+
+
+/// Storage, that keeps all files in memory and records synthetic revisions.
+/// 
+/// Nothing is written to disk, so the storage is useful for tests. Its root
+/// is a virtual path, that only serves for composing absolute paths of notes.
+pub struct MemoryStorage {
+    /// Virtual root of the storage
+    root: PathBuf,
+
+    /// Files, folders and history
+    state: Mutex<MemoryState>,
+}
+
+
+impl MemoryStorage {
+    /// Creates an empty storage with a unique virtual root.
+    pub fn new() -> Self {
+        Self::with_root(&env::temp_dir().join(format!("mm-memory-{}", Uuid::new_v4())))
+    }
+
+
+    /// Creates an empty storage with a specific virtual root.
+    /// 
+    /// * `root` - absolute path, that is used as storage's root
+    pub fn with_root(root: &Path) -> Self {
+        MemoryStorage {
+            root: root.to_path_buf(),
+            state: Mutex::new(MemoryState::default())
+        }
+    }
+
+
+    /// Locks internal state.
+    fn lock(&self) -> Result<MutexGuard<'_, MemoryState>> {
+        self.state
+            .lock()
+            .map_err(|_| Error::from_string("in-memory storage is poisoned", ErrorCategory::Repo))
+    }
+}
+
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl Storage for MemoryStorage {
+    fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+
+    fn exists(&self, path: &Path) -> bool {
+        self.lock()
+            .is_ok_and(|state| state.files.contains_key(path))
+            || self.is_folder(path)
+    }
+
+
+    fn is_folder(&self, path: &Path) -> bool {
+        if path.as_os_str().is_empty() {
+            return true;
+        }
+
+        self.lock()
+            .is_ok_and(|state| state.folders.contains(path) || state.has_files_in(path))
+    }
+
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.lock()?
+            .files
+            .get(path)
+            .cloned()
+            .ok_or(Error::from_string("file not found", ErrorCategory::Os).with_kind(ErrorKind::NotFound).with_path(path))
+    }
+
+
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let mut state = self.lock()?;
+
+        if state.folders.contains(path) {
+            return Err(Error::from_string("path is a folder", ErrorCategory::Os).with_kind(ErrorKind::InvalidPath).with_path(path));
+        }
+
+        if let Some(parent) = path.parent() {
+            state.add_folder(parent);
+        }
+
+        state.files.insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+
+
+    fn create_folder(&self, path: &Path) -> Result<()> {
+        self.lock()?
+            .add_folder(path);
+
+        Ok(())
+    }
+
+
+    fn delete(&self, path: &Path) -> Result<()> {
+        let mut state = self.lock()?;
+
+        if state.files.remove(path).is_some() {
+            return Ok(());
+        }
+
+        if !state.folders.contains(path) && !state.has_files_in(path) {
+            return Err(Error::from_string("path not found", ErrorCategory::Os).with_kind(ErrorKind::NotFound).with_path(path));
+        }
+
+        state.files.retain(|file, _content| !file.starts_with(path));
+        state.folders.retain(|folder| !folder.starts_with(path));
+
+        Ok(())
+    }
+
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut state = self.lock()?;
+
+        if let Some(content) = state.files.remove(from) {
+            if let Some(parent) = to.parent() {
+                state.add_folder(parent);
+            }
+
+            state.files.insert(to.to_path_buf(), content);
+            return Ok(());
+        }
+
+        if !state.folders.contains(from) && !state.has_files_in(from) {
+            return Err(Error::from_string("path not found", ErrorCategory::Os).with_kind(ErrorKind::NotFound).with_path(from));
+        }
+
+        //
+        // A folder is moved together with its content
+        //
+
+        let moved_files: Vec<PathBuf> = state.files
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+
+        for path in moved_files {
+            if let (Some(content), Ok(relative_path)) = (state.files.remove(&path), path.strip_prefix(from)) {
+                state.files.insert(to.join(relative_path), content);
+            }
+        }
+
+        let moved_folders: Vec<PathBuf> = state.folders
+            .iter()
+            .filter(|folder| folder.starts_with(from))
+            .cloned()
+            .collect();
+
+        for folder in moved_folders {
+            state.folders.remove(&folder);
+            if let Ok(relative_path) = folder.strip_prefix(from) {
+                state.add_folder(&to.join(relative_path));
+            }
+        }
+
+        state.add_folder(to);
+        Ok(())
+    }
+
+
+    fn list(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self.lock()?
+            .files
+            .keys()
+            .filter(|path| path.starts_with(folder))
+            .cloned()
+            .collect())
+    }
+
+
+    fn commit(&self, author_name: &str, _author_email: &str, changed: &[&Path], removed: &[&Path], message: &str, time: Option<SystemTime>) -> Result<()> {
+        let mut state = self.lock()?;
+
+        let id = format!("{:040x}", state.revisions.len() + 1);
+        let revision = Revision::new(id, message, author_name, time.unwrap_or_else(SystemTime::now));
+        let revision = match state.revisions.last() {
+            Some(parent) => revision.with_parent_id(parent.revision.get_id()),
+            None => revision
+        };
+
+        let files = changed
+            .iter()
+            .map(|path| (path.to_path_buf(), state.files.get(*path).cloned()))
+            .chain(removed.iter().map(|path| (path.to_path_buf(), None)))
+            .collect();
+
+        state.revisions.push(MemoryRevision {
+            revision,
+            files
+        });
+
+        Ok(())
+    }
+
+
+    fn history(&self, path: Option<&Path>) -> Result<Vec<Revision>> {
+        Ok(self.lock()?
+            .revisions
+            .iter()
+            .rev()
+            .filter(|revision| path.is_none_or(|path| revision.files.contains_key(path)))
+            .map(|revision| revision.revision.clone())
+            .collect())
+    }
+
+
+    fn read_revision(&self, path: &Path, revision_id: &str) -> Result<Option<Vec<u8>>> {
+        let state = self.lock()?;
+
+        let position = state.revisions
+            .iter()
+            .position(|revision| revision.revision.get_id() == revision_id)
+            .ok_or(Error::from_string("revision not found", ErrorCategory::Os).with_kind(ErrorKind::NotFound))?;
+
+        //
+        // The latest revision, that touches the file,
+        // contains its content
+        //
+
+        Ok(state.revisions[..=position]
+            .iter()
+            .rev()
+            .find_map(|revision| revision.files.get(path))
+            .cloned()
+            .flatten())
+    }
+
+
+    fn list_revision(&self, path: &Path, revision_id: &str) -> Result<Vec<PathBuf>> {
+        let state = self.lock()?;
+
+        let position = state.revisions
+            .iter()
+            .position(|revision| revision.revision.get_id() == revision_id)
+            .ok_or(Error::from_string("revision not found", ErrorCategory::Os).with_kind(ErrorKind::NotFound))?;
+
+        //
+        // Revisions are replayed to know, which files existed
+        // in the folder at the moment. Removed folders are
+        // recorded by their paths
+        //
+
+        let mut files: BTreeMap<PathBuf, bool> = BTreeMap::new();
+        for revision in &state.revisions[..=position] {
+            for (file, content) in &revision.files {
+                if content.is_none() {
+                    for (known, exists) in files.iter_mut() {
+                        if known.starts_with(file) {
+                            *exists = false;
+                        }
+                    }
+                }
+
+                if file.starts_with(path) {
+                    files.insert(file.clone(), content.is_some());
+                }
+            }
+        }
+
+        Ok(files
+            .into_iter()
+            .filter_map(|(file, exists)| exists.then_some(file))
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Writes a file and commits it.
+    fn commit_file(storage: &MemoryStorage, path: &str, content: &[u8]) {
+        storage.write(Path::new(path), content).unwrap();
+        storage.commit("Tester", "tester@example.com", &[Path::new(path)], &[], "chore: write", None).unwrap();
+    }
+
+
+    /// Deletes a file or a folder and commits it.
+    fn commit_removal(storage: &MemoryStorage, path: &str) {
+        storage.delete(Path::new(path)).unwrap();
+        storage.commit("Tester", "tester@example.com", &[], &[Path::new(path)], "chore: remove", None).unwrap();
+    }
+
+
+    #[test]
+    fn files_and_folders_are_kept() {
+        let storage = MemoryStorage::new();
+        storage.write(Path::new("b/two.md"), b"2").unwrap();
+        storage.write(Path::new("a/one.md"), b"1").unwrap();
+
+        assert!(storage.is_folder(Path::new("")));
+        assert!(storage.is_folder(Path::new("a")));
+        assert!(!storage.is_folder(Path::new("a/one.md")));
+        assert!(storage.write(Path::new("a"), b"folder").is_err());
+        assert_eq!(storage.list(Path::new("")).unwrap(), vec![PathBuf::from("a/one.md"), PathBuf::from("b/two.md")]);
+
+        storage.delete(Path::new("a")).unwrap();
+        assert!(!storage.exists(Path::new("a/one.md")));
+        assert_eq!(storage.delete(Path::new("a")).unwrap_err().get_kind(), ErrorKind::NotFound);
+        assert_eq!(storage.read(Path::new("a/one.md")).unwrap_err().get_kind(), ErrorKind::NotFound);
+    }
+
+
+    #[test]
+    fn revisions_keep_contents() {
+        let storage = MemoryStorage::new();
+        commit_file(&storage, "note.md", b"1");
+        commit_file(&storage, "other.md", b"other");
+        commit_file(&storage, "note.md", b"2");
+        commit_removal(&storage, "note.md");
+
+        let history = storage.history(None).unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0].get_parent_id(), Some(history[1].get_id()));
+        assert_eq!(history[3].get_parent_id(), None);
+        assert_eq!(storage.history(Some(Path::new("note.md"))).unwrap().len(), 3);
+
+        assert_eq!(storage.read_revision(Path::new("note.md"), history[3].get_id()).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.read_revision(Path::new("note.md"), history[2].get_id()).unwrap(), Some(b"1".to_vec()));
+        assert_eq!(storage.read_revision(Path::new("note.md"), history[1].get_id()).unwrap(), Some(b"2".to_vec()));
+        assert_eq!(storage.read_revision(Path::new("note.md"), history[0].get_id()).unwrap(), None);
+        assert_eq!(storage.read_revision(Path::new("note.md"), "missing").unwrap_err().get_kind(), ErrorKind::NotFound);
+    }
+
+
+    #[test]
+    fn folder_is_listed_as_of_revision() {
+        let storage = MemoryStorage::new();
+        commit_file(&storage, "folder/a.md", b"a");
+        commit_file(&storage, "folder/sub/b.md", b"b");
+        commit_file(&storage, "outside.md", b"c");
+        commit_removal(&storage, "folder");
+        commit_file(&storage, "folder/c.md", b"c");
+
+        let history = storage.history(None).unwrap();
+        let list = |revision: &Revision| storage.list_revision(Path::new("folder"), revision.get_id()).unwrap();
+
+        assert_eq!(list(&history[2]), vec![PathBuf::from("folder/a.md"), PathBuf::from("folder/sub/b.md")]);
+        assert!(list(&history[1]).is_empty());
+        assert_eq!(list(&history[0]), vec![PathBuf::from("folder/c.md")]);
+        assert_eq!(storage.list_revision(Path::new("outside.md"), history[0].get_id()).unwrap(), vec![PathBuf::from("outside.md")]);
+    }
+
+
+    #[test]
+    fn rename_moves_folder_with_content() {
+        let storage = MemoryStorage::new();
+        storage.write(Path::new("a/one.md"), b"1").unwrap();
+        storage.write(Path::new("a/b/two.md"), b"2").unwrap();
+        storage.create_folder(Path::new("a/empty")).unwrap();
+
+        storage.rename(Path::new("a"), Path::new("c/d")).unwrap();
+
+        assert!(!storage.exists(Path::new("a")));
+        assert_eq!(storage.read(Path::new("c/d/one.md")).unwrap(), b"1");
+        assert_eq!(storage.read(Path::new("c/d/b/two.md")).unwrap(), b"2");
+        assert!(storage.is_folder(Path::new("c/d/empty")));
+    }
+
+
+    #[test]
+    fn renamed_folder_is_listed_as_of_revision() {
+        let storage = MemoryStorage::new();
+        commit_file(&storage, "a/one.md", b"1");
+
+        storage.rename(Path::new("a"), Path::new("b")).unwrap();
+        storage.commit("Tester", "tester@example.com", &[Path::new("b/one.md")], &[Path::new("a")], "chore: rename", None).unwrap();
+
+        let history = storage.history(None).unwrap();
+        assert_eq!(storage.list_revision(Path::new("a"), history[1].get_id()).unwrap(), vec![PathBuf::from("a/one.md")]);
+        assert!(storage.list_revision(Path::new("a"), history[0].get_id()).unwrap().is_empty());
+        assert_eq!(storage.list_revision(Path::new("b"), history[0].get_id()).unwrap(), vec![PathBuf::from("b/one.md")]);
+        assert_eq!(storage.read_revision(Path::new("b/one.md"), history[0].get_id()).unwrap(), Some(b"1".to_vec()));
+    }
+
+
+    #[test]
+    fn rename_fails_for_missing_path() {
+        let storage = MemoryStorage::new();
+        assert!(storage.rename(Path::new("missing"), Path::new("other")).is_err());
+    }
+}
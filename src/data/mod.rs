@@ -1,8 +1,32 @@
+#[allow(clippy::module_inception)]
 mod data;
+mod storage;
+mod git;
+mod directory;
+mod memory;
 
 
 pub(crate) use self::data::{get_mm_folder};
+pub use self::storage::{Storage, Revision, StatusEntry, StatusKind};
+pub use self::git::{GitStorage};
+pub use self::directory::{DirectoryStorage};
+pub use self::memory::{MemoryStorage};
 
 
 /// Path to repositories relative to user's home folder
 const MM_DATA_FOLDER: &str = ".mm/";
+
+/// Reference being updated in git repositories
+const MM_GIT_HEAD_REF: &str = "HEAD";
+
+/// Name of git's internal folder inside of working directory
+const MM_GIT_FOLDER: &str = ".git";
+
+/// Path to a file with local exclusion patterns inside of git's internal folder
+const MM_GIT_EXCLUDE_FILE: &str = "info/exclude";
+
+/// Header of git bundles (version 2)
+const MM_GIT_BUNDLE_HEADER: &str = "# v2 git bundle\n";
+
+/// Prefix of branch references in git repositories
+const MM_GIT_BRANCH_PREFIX: &str = "refs/heads/";
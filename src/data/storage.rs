@@ -0,0 +1,297 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::{Result, Error, ErrorCategory};
+
+
+/// Describes a single revision (commit) in storage's history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Revision {
+    /// Identifier of the revision (e.g. commit hash)
+    id: String,
+
+    /// Revision's message
+    message: String,
+
+    /// Name of revision's author
+    author: String,
+
+    /// Time of the revision
+    time: SystemTime,
+
+    /// Identifier of the first parent revision (`None` for the first one)
+    parent_id: Option<String>,
+}
+
+
+impl Revision {
+    /// Constructs a revision description.
+    /// 
+    /// * `id` - identifier of the revision
+    /// * `message` - revision's message
+    /// * `author` - name of revision's author
+    /// * `time` - time of the revision
+    pub fn new<S, M, A>(id: S, message: M, author: A, time: SystemTime) -> Self
+    where
+        S: Into<String>,
+        M: Into<String>,
+        A: Into<String>
+    {
+        Revision {
+            id: id.into(),
+            message: message.into(),
+            author: author.into(),
+            time,
+            parent_id: None
+        }
+    }
+
+
+    /// Sets an identifier of the first parent revision.
+    /// 
+    /// * `parent_id` - identifier of the parent revision
+    pub fn with_parent_id<P: Into<String>>(mut self, parent_id: P) -> Self {
+        self.parent_id = Some(parent_id.into());
+        self
+    }
+
+
+    /// Obtains an identifier of the revision.
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+
+    /// Obtains a message of the revision.
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+
+    /// Obtains a name of revision's author.
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+
+    /// Obtains a time of the revision.
+    pub fn get_time(&self) -> SystemTime {
+        self.time
+    }
+
+
+    /// Obtains an identifier of the first parent revision, i.e. the revision
+    /// the changes were made on top of (`None` for the first revision).
+    pub fn get_parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+}
+
+
+/// Kind of an uncommitted change of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StatusKind {
+    /// A new file
+    Added,
+
+    /// Content of a file was changed
+    Modified,
+
+    /// A file was removed
+    Deleted,
+
+    /// A file was moved from another path
+    Renamed {
+        /// Previous path of the file
+        from: PathBuf,
+    },
+
+    /// A file has unresolved merge conflicts
+    Conflicted,
+}
+
+
+/// Describes an uncommitted change of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusEntry {
+    /// Path to the file
+    path: PathBuf,
+
+    /// Kind of the change
+    kind: StatusKind,
+}
+
+
+impl StatusEntry {
+    /// Constructs a change description.
+    /// 
+    /// * `path` - path to the file
+    /// * `kind` - kind of the change
+    pub fn new<P>(path: P, kind: StatusKind) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        StatusEntry {
+            path: path.into(),
+            kind
+        }
+    }
+
+
+    /// Obtains a path to the file.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+
+    /// Obtains a kind of the change.
+    pub fn get_kind(&self) -> &StatusKind {
+        &self.kind
+    }
+}
+
+
+/// Data container abstraction, that stores repository's files.
+/// 
+/// All paths passed to storage methods are relative to storage's root.
+/// Implementations decide how (and if) changes are versioned. Storages
+/// MUST be safe to share between threads (see [`crate::repo::SharedRepository`]).
+pub trait Storage: Send + Sync {
+    /// Obtains a root of the storage. Absolute paths used by
+    /// [`crate::repo::Repository`] are resolved against it.
+    fn get_root(&self) -> &Path;
+
+    /// Checks if a file or a folder exists.
+    /// 
+    /// * `path` - relative path to check
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Checks if a path is an existing folder.
+    /// 
+    /// * `path` - relative path to check
+    fn is_folder(&self, path: &Path) -> bool;
+
+    /// Reads whole content of a file.
+    /// 
+    /// * `path` - relative path to a file
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Writes content into a file. The file is created or truncated,
+    /// missing parent folders are created.
+    /// 
+    /// * `path` - relative path to a file
+    /// * `content` - new content of the file
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()>;
+
+    /// Creates a folder and its parents.
+    /// 
+    /// * `path` - relative path to a folder
+    fn create_folder(&self, path: &Path) -> Result<()>;
+
+    /// Deletes a file or a folder with all its content.
+    /// 
+    /// * `path` - relative path to delete
+    fn delete(&self, path: &Path) -> Result<()>;
+
+    /// Moves a file or a folder with all its content. Missing parent
+    /// folders of destination are created.
+    /// 
+    /// * `from` - relative path to a file or a folder to move
+    /// * `to` - relative destination path
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Recursively lists all files inside of a folder. Returned paths
+    /// are relative to storage's root and sorted.
+    /// 
+    /// * `folder` - relative path to a folder (empty path means root)
+    fn list(&self, folder: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Records changes of files as a new revision. Storages without
+    /// versioning may do nothing here.
+    /// 
+    /// * `author_name` - name of revision's author
+    /// * `author_email` - email of revision's author
+    /// * `changed` - list of added or modified files
+    /// * `removed` - list of removed files
+    /// * `message` - revision's message
+    /// * `time` - time of the revision (`None` means current time)
+    fn commit(&self, author_name: &str, author_email: &str, changed: &[&Path], removed: &[&Path], message: &str, time: Option<SystemTime>) -> Result<()>;
+
+    /// Returns history of revisions (the newest first).
+    /// 
+    /// * `path` - relative path to a file to get history of (`None`
+    ///   means history of the whole storage)
+    fn history(&self, path: Option<&Path>) -> Result<Vec<Revision>>;
+
+    /// Reads content of a file as of a revision. Returns `None` if the
+    /// file was absent in the revision. Storages without versioning
+    /// cannot read old contents.
+    /// 
+    /// * `path` - relative path to a file
+    /// * `revision_id` - identifier of a revision (see [`Revision::get_id`])
+    fn read_revision(&self, path: &Path, _revision_id: &str) -> Result<Option<Vec<u8>>> {
+        Err(Error::from_string("storage does not keep contents of revisions", ErrorCategory::Os).with_path(path))
+    }
+
+    /// Lists files at a path as of a revision: the file itself or all
+    /// files inside of a folder. The list is empty if there was nothing
+    /// at the path.
+    /// 
+    /// * `path` - relative path to a file or a folder
+    /// * `revision_id` - identifier of a revision (see [`Revision::get_id`])
+    fn list_revision(&self, path: &Path, revision_id: &str) -> Result<Vec<PathBuf>> {
+        Ok(self.read_revision(path, revision_id)?
+            .map(|_content| vec![path.to_path_buf()])
+            .unwrap_or_default())
+    }
+
+    /// Checks if a file differs from its state in the last revision. Added
+    /// and removed files differ too. Storages without versioning consider
+    /// all files changed.
+    /// 
+    /// * `path` - relative path to a file
+    fn is_changed(&self, _path: &Path) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// Returns uncommitted changes of files sorted by paths. Ignored files
+    /// are skipped. Storages without versioning have no uncommitted changes.
+    fn status(&self) -> Result<Vec<StatusEntry>> {
+        Ok(Vec::new())
+    }
+
+    /// Excludes files from version control without recording anything in
+    /// the storage itself, so exclusions are not shared with other copies
+    /// of the storage. Folders are excluded with all their content.
+    /// Storages without versioning do nothing here.
+    /// 
+    /// * `paths` - relative paths to exclude
+    fn exclude(&self, _paths: &[&Path]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns a folder with internal version control data relative to
+    /// storage's root (e.g. `.git`). The folder is not listed by 
+    /// [`Storage::list`] for root, but may be listed explicitly. `None`
+    /// means, that history cannot be copied as files.
+    fn get_history_folder(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Packs full history into a git bundle: references followed by a pack
+    /// with all objects they reach. `None` means, that the storage keeps
+    /// no history.
+    fn bundle_history(&self) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Restores history from a bundle produced by [`Storage::bundle_history`].
+    /// Only objects and branches are taken from the bundle, working files
+    /// are not touched. The storage MUST have no history yet.
+    /// 
+    /// * `bundle` - content of the bundle
+    fn unbundle_history(&self, _bundle: &[u8]) -> Result<()> {
+        Err(Error::from_string("storage cannot keep history", ErrorCategory::Repo))
+    }
+}
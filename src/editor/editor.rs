@@ -0,0 +1,53 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{Error, Result, ErrorCategory};
+use super::{MM_VISUAL_ENV, MM_EDITOR_ENV};
+
+
+/// Resolves an editor command.
+/// 
+/// Configured editor has the highest priority, then `$VISUAL` and 
+/// `$EDITOR` environment variables are checked. Empty commands are
+/// skipped.
+/// 
+/// * `configured` - editor command from repository's local configuration
+///   (if any)
+pub(crate) fn resolve_editor(configured: Option<&str>) -> Result<String> {
+    let is_set = |editor: &String| !editor.trim().is_empty();
+
+    configured
+        .map(str::to_owned)
+        .filter(is_set)
+        .or_else(|| env::var(MM_VISUAL_ENV).ok().filter(is_set))
+        .or_else(|| env::var(MM_EDITOR_ENV).ok().filter(is_set))
+        .ok_or(Error::from_string("no editor configured", ErrorCategory::Editor))
+}
+
+
+/// Launches an editor for a file and waits for it to exit.
+/// 
+/// Editor command may contain arguments separated by whitespaces 
+/// (e.g. `code --wait`). Path to the file is appended as the last one.
+/// 
+/// * `editor` - editor command
+/// * `file_path` - path to a file to edit
+pub(crate) fn launch_editor(editor: &str, file_path: &Path) -> Result<()> {
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or(Error::from_string("empty editor command", ErrorCategory::Editor))?;
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(file_path)
+        .status()
+        .map_err(|err| Error::from_string(format!("cannot launch editor '{}': {}", program, err), ErrorCategory::Editor))?;
+
+    if !status.success() {
+        return Err(Error::from_string(format!("editor '{}' exited with {}", program, status), ErrorCategory::Editor));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,14 @@
+#[allow(clippy::module_inception)]
+mod editor;
+mod temp;
+
+
+pub(crate) use self::editor::{resolve_editor, launch_editor};
+pub(crate) use self::temp::TempCopy;
+
+
+/// Environment variable with a preferred visual editor
+const MM_VISUAL_ENV: &str = "VISUAL";
+
+/// Environment variable with a preferred editor
+const MM_EDITOR_ENV: &str = "EDITOR";
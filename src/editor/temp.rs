@@ -0,0 +1,101 @@
+use std::fs::{self, OpenOptions, DirBuilder};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result, ErrorCategory, ErrorKind};
+
+
+/// A temporary copy of a note, that is edited by an external editor.
+/// 
+/// The copy is placed into a folder, that only the owner can access
+/// (`0700` folder and `0600` file on unix), because it may contain
+/// decrypted content. When the copy is dropped, the file is overwritten
+/// with zeros and removed together with its folder, so it is cleaned up
+/// on every exit path.
+pub(crate) struct TempCopy {
+    /// Path to the temporary folder
+    folder: PathBuf,
+
+    /// Path to the copy inside of the temporary folder
+    path: PathBuf,
+}
+
+
+impl TempCopy {
+    /// Creates a temporary copy of a note.
+    /// 
+    /// * `parent` - folder, where the temporary folder is created
+    /// * `file_name` - name of the copy (editors usually rely on extension)
+    /// * `content` - content of the copy
+    pub(crate) fn create(parent: &Path, file_name: &Path, content: &[u8]) -> Result<Self> {
+        if file_name.file_name() != Some(file_name.as_os_str()) {
+            return Err(Error::from_string("invalid note name", ErrorCategory::Editor)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(file_name));
+        }
+
+        let folder = parent.join(format!("mm-{}", Uuid::new_v4()));
+
+        let mut builder = DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&folder)
+            .map_err(Error::from)?;
+
+        //
+        // From now on the folder is removed on drop
+        //
+
+        let copy = TempCopy { path: folder.join(file_name), folder };
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&copy.path)
+            .and_then(|mut file| file.write_all(content))
+            .map_err(Error::from)?;
+
+        Ok(copy)
+    }
+
+
+    /// Obtains path to the copy.
+    pub(crate) fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+
+    /// Reads current content of the copy.
+    pub(crate) fn read(&self) -> Result<Vec<u8>> {
+        fs::read(&self.path)
+            .map_err(Error::from)
+    }
+}
+
+
+impl Drop for TempCopy {
+    fn drop(&mut self) {
+        //
+        // Editor may replace the file instead of writing it,
+        // so current length is used. Errors are ignored
+        // intentionally, there is nothing to do with them
+        //
+
+        if let Ok(metadata) = fs::metadata(&self.path) {
+            let _ = OpenOptions::new()
+                .write(true)
+                .open(&self.path)
+                .and_then(|mut file| {
+                    file.write_all(&vec![0; metadata.len() as usize])?;
+                    file.sync_all()
+                });
+        }
+
+        let _ = fs::remove_dir_all(&self.folder);
+    }
+}
@@ -1,4 +1,5 @@
 use std::{io, fmt, path, result};
+use std::path::{Path, PathBuf};
 
 use git2;
 use serde_json;
@@ -6,6 +7,7 @@ use serde_json;
 
 /// Enumeration with error categories
 #[derive(PartialEq, Eq, Clone, Debug, Copy)]
+#[non_exhaustive]
 pub enum ErrorCategory {
     /// Unspecified error
     Generic,
@@ -27,14 +29,48 @@ pub enum ErrorCategory {
 
     /// Configuration
     Config,
+
+    /// Wrong or missing passphrase for encrypted content
+    Passphrase,
+}
+
+
+/// Enumeration with error kinds, that describe what exactly went wrong
+/// regardless of a subsystem, that reported an error
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// No specific kind
+    Other,
+
+    /// A file, a note, a repository or another entity does not exist
+    NotFound,
+
+    /// An entity already exists
+    AlreadyExists,
+
+    /// A path is malformed or points to an inappropriate location
+    InvalidPath,
+
+    /// Changes conflict with other ones
+    Conflict,
+
+    /// A resource is locked (e.g. encrypted repository without a passphrase)
+    Locked,
+
+    /// Authentication failed (e.g. wrong passphrase)
+    AuthFailed,
 }
 
 
 /// Structure, that describes all errors in mm
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Error {
     msg: String,
     category: ErrorCategory,
+    kind: ErrorKind,
+    path: Option<PathBuf>,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
 }
 
 
@@ -49,30 +85,108 @@ impl Error {
     {
         Error {
             msg: s.into(),
-            category: category
+            category,
+            kind: ErrorKind::Other,
+            path: None,
+            source: None
         }
     }
 
 
-    /// Constructs an error from an error object.
+    /// Sets a kind of the error.
+    /// 
+    /// * `kind` - error kind (see [`crate::error::ErrorKind`])
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+
+    /// Sets a path, that the error originates from.
+    /// 
+    /// * `path` - path to a file or a folder
+    pub fn with_path<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>
+    {
+        self.path = Some(path.into());
+        self
+    }
+
+
+    /// Obtains a description of the error.
+    pub fn get_message(&self) -> &str {
+        &self.msg
+    }
+
+
+    /// Obtains a category of the error.
+    pub fn get_category(&self) -> ErrorCategory {
+        self.category
+    }
+
+
+    /// Obtains a kind of the error.
+    pub fn get_kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+
+    /// Obtains a path, that the error originates from (if any).
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+
+    /// Constructs an error from an error object. The object is kept as
+    /// a source of the error.
     /// 
     /// * `err` - generic error instance (needs to implement [`std::error::Error`] trait)
-    fn from_error<E: std::error::Error>(err: E) -> Self {
+    /// * `category` - error category (see [`crate::error::ErrorCategory`])
+    /// * `kind` - error kind (see [`crate::error::ErrorKind`])
+    fn from_error<E>(err: E, category: ErrorCategory, kind: ErrorKind) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static
+    {
         Error {
             msg: err.to_string(),
-            category: ErrorCategory::Generic
+            category,
+            kind,
+            path: None,
+            source: Some(Box::new(err))
         }
     }
 }
 
 
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        //
+        // Sources are not comparable, but their
+        // descriptions are kept in messages
+        //
+
+        self.msg == other.msg &&
+            self.category == other.category &&
+            self.kind == other.kind &&
+            self.path == other.path
+    }
+}
+
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         //
         // intentionally ignore category (maybe for now)
         //
 
-        write!(f, "Description: {} (category: {:?})", self.msg, self.category)
+        write!(f, "Description: {} (category: {:?}, kind: {:?})", self.msg, self.category, self.kind)?;
+
+        if let Some(path) = &self.path {
+            write!(f, " (path: {:?})", path)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -81,41 +195,86 @@ impl std::error::Error for Error {
     fn description(&self) -> &str {
         &self.msg
     }
+
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
 }
 
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        let mut res = Error::from_error(err);
-        res.category = ErrorCategory::Os;
-        res
+        let kind = match err.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            io::ErrorKind::InvalidInput => ErrorKind::InvalidPath,
+            _ => ErrorKind::Other
+        };
+
+        Error::from_error(err, ErrorCategory::Os, kind)
     }
 }
 
 
 impl From<path::StripPrefixError> for Error {
     fn from(err: path::StripPrefixError) -> Self {
-        let mut res = Error::from_error(err);
-        res.category = ErrorCategory::Os;
-        res
+        Error::from_error(err, ErrorCategory::Os, ErrorKind::InvalidPath)
     }
 }
 
 
 impl From<git2::Error> for Error {
     fn from(err: git2::Error) -> Self {
-        let mut res = Error::from_error(err);
-        res.category = ErrorCategory::Git;
-        res
+        let kind = match err.code() {
+            git2::ErrorCode::NotFound => ErrorKind::NotFound,
+            git2::ErrorCode::Exists => ErrorKind::AlreadyExists,
+            git2::ErrorCode::Conflict | git2::ErrorCode::MergeConflict | git2::ErrorCode::Modified => ErrorKind::Conflict,
+            git2::ErrorCode::Locked => ErrorKind::Locked,
+            git2::ErrorCode::Auth | git2::ErrorCode::Certificate => ErrorKind::AuthFailed,
+            _ => ErrorKind::Other
+        };
+
+        Error::from_error(err, ErrorCategory::Git, kind)
     }
 }
 
 
 impl From<serde_json::Error> for Error {
     fn from(err: serde_json::Error) -> Self {
-        let mut res = Error::from_error(err);
-        res.category = ErrorCategory::IO;
-        res
+        Error::from_error(err, ErrorCategory::IO, ErrorKind::Other)
+    }
+}
+
+
+impl From<quick_xml::Error> for Error {
+    fn from(err: quick_xml::Error) -> Self {
+        Error::from_error(err, ErrorCategory::IO, ErrorKind::Other)
+    }
+}
+
+
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        match err {
+            zip::result::ZipError::Io(err) => Error::from(err),
+            err => Error::from_error(err, ErrorCategory::IO, ErrorKind::Other)
+        }
+    }
+}
+
+
+#[cfg(feature = "watcher")]
+impl From<notify_debouncer_mini::notify::Error> for Error {
+    fn from(err: notify_debouncer_mini::notify::Error) -> Self {
+        let kind = match &err.kind {
+            notify_debouncer_mini::notify::ErrorKind::PathNotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Other
+        };
+
+        Error::from_error(err, ErrorCategory::Os, kind)
     }
 }
 
@@ -123,3 +282,78 @@ impl From<serde_json::Error> for Error {
 /// Crate-specific alias for [`std::result::Result`] instantiated 
 /// with [`crate::error::Error`]
 pub type Result<T> = result::Result<T, Error>;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as StdError;
+
+
+    #[test]
+    fn source_is_kept() {
+        let err = Error::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+
+        assert_eq!(err.get_message(), "denied");
+        assert_eq!(err.get_category(), ErrorCategory::Os);
+
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), "denied");
+        assert_eq!(source.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::PermissionDenied);
+
+        assert!(Error::from_string("no source", ErrorCategory::Repo).source().is_none());
+    }
+
+
+    #[test]
+    fn kind_is_mapped_from_io_errors() {
+        let kinds = [
+            (io::ErrorKind::NotFound, ErrorKind::NotFound),
+            (io::ErrorKind::AlreadyExists, ErrorKind::AlreadyExists),
+            (io::ErrorKind::InvalidInput, ErrorKind::InvalidPath),
+            (io::ErrorKind::PermissionDenied, ErrorKind::Other),
+        ];
+
+        for (io_kind, kind) in kinds {
+            assert_eq!(Error::from(io::Error::from(io_kind)).get_kind(), kind, "{:?}", io_kind);
+        }
+    }
+
+
+    #[test]
+    fn kind_is_mapped_from_git_codes() {
+        let kinds = [
+            (git2::ErrorCode::NotFound, ErrorKind::NotFound),
+            (git2::ErrorCode::Exists, ErrorKind::AlreadyExists),
+            (git2::ErrorCode::Conflict, ErrorKind::Conflict),
+            (git2::ErrorCode::Modified, ErrorKind::Conflict),
+            (git2::ErrorCode::Locked, ErrorKind::Locked),
+            (git2::ErrorCode::Auth, ErrorKind::AuthFailed),
+            (git2::ErrorCode::Certificate, ErrorKind::AuthFailed),
+            (git2::ErrorCode::GenericError, ErrorKind::Other),
+        ];
+
+        for (code, kind) in kinds {
+            let err = Error::from(git2::Error::new(code, git2::ErrorClass::None, "git"));
+
+            assert_eq!(err.get_category(), ErrorCategory::Git);
+            assert_eq!(err.get_kind(), kind, "{:?}", code);
+        }
+    }
+
+
+    #[test]
+    fn path_is_filled_in() {
+        let err = Error::from(io::Error::from(io::ErrorKind::NotFound))
+            .with_path("folder/note.md");
+
+        assert_eq!(err.get_path(), Some(Path::new("folder/note.md")));
+        assert_eq!(err.get_kind(), ErrorKind::NotFound);
+        assert!(err.source().is_some());
+        assert!(err.to_string().ends_with("(path: \"folder/note.md\")"), "{}", err);
+
+        let err = Error::from_string("invalid path", ErrorCategory::Repo);
+        assert_eq!(err.get_path(), None);
+        assert!(!err.to_string().contains("path:"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf, Component};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::error::{Error, Result, ErrorCategory, ErrorKind};
+use super::{MM_ZIP_MAGIC, MM_GZIP_MAGIC};
+
+
+/// Format of an archive with repository's files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Zip archive with deflate compression
+    Zip,
+
+    /// Tar archive compressed with gzip
+    TarGz,
+}
+
+
+/// Writer of an archive, that adds files one by one.
+pub(crate) enum ArchiveWriter {
+    /// Zip archive
+    Zip(zip::ZipWriter<File>),
+
+    /// Gzip-compressed tar archive
+    TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+
+impl ArchiveWriter {
+    /// Creates an archive file.
+    /// 
+    /// * `path` - path to an archive to create
+    /// * `format` - format of the archive
+    pub(crate) fn create(path: &Path, format: ArchiveFormat) -> Result<Self> {
+        let file = File::create(path)?;
+
+        Ok(match format {
+            ArchiveFormat::Zip => ArchiveWriter::Zip(zip::ZipWriter::new(file)),
+            ArchiveFormat::TarGz => ArchiveWriter::TarGz(tar::Builder::new(GzEncoder::new(file, Compression::default())))
+        })
+    }
+
+
+    /// Adds a file into the archive.
+    /// 
+    /// * `relative_path` - path to the file inside of the archive
+    /// * `content` - content of the file
+    pub(crate) fn add_file(&mut self, relative_path: &Path, content: &[u8]) -> Result<()> {
+        let name = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        match self {
+            ArchiveWriter::Zip(writer) => {
+                let options = zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+
+                writer.start_file(name, options)?;
+
+                writer.write_all(content)?;
+            },
+            ArchiveWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+
+                builder.append_data(&mut header, name, content)?;
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Completes the archive. Nothing is guaranteed to be written
+    /// until the archive is completed.
+    pub(crate) fn finish(self) -> Result<()> {
+        match self {
+            ArchiveWriter::Zip(mut writer) => {
+                writer.finish()?;
+            },
+            ArchiveWriter::TarGz(builder) => {
+                builder.into_inner()?
+                    .finish()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Reads all files from an archive. Format is detected by archive's
+/// content. Folders are skipped, paths are relative.
+/// 
+/// Archives with paths leading outside (absolute ones or with `..`)
+/// are rejected.
+/// 
+/// * `path` - path to an archive to read
+pub(crate) fn read_archive(path: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut magic = [0u8; 4];
+    let read = File::open(path)?.read(&mut magic)?;
+
+    let magic = &magic[..read];
+    let mut files = Vec::new();
+
+    if magic.starts_with(MM_ZIP_MAGIC) {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+
+            if file.is_dir() {
+                continue;
+            }
+
+            let relative_path = validate_path(Path::new(file.name()))?;
+
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+
+            files.push((relative_path, content));
+        }
+    }
+    else if magic.starts_with(MM_GZIP_MAGIC) {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let relative_path = validate_path(&entry.path()?)?;
+
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+
+            files.push((relative_path, content));
+        }
+    }
+    else {
+        return Err(Error::from_string("unknown archive format", ErrorCategory::IO));
+    }
+
+    Ok(files)
+}
+
+
+/// Ensures, that a path from an archive stays inside of a destination.
+/// 
+/// * `path` - path from an archive
+fn validate_path(path: &Path) -> Result<PathBuf> {
+    let valid = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+    if !valid {
+        return Err(Error::from_string("invalid path in archive", ErrorCategory::IO)
+            .with_kind(ErrorKind::InvalidPath)
+            .with_path(path));
+    }
+
+    Ok(path.to_path_buf())
+}
+
@@ -0,0 +1,457 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{html, Event, Options, Parser, Tag, CowStr};
+use sha2::{Digest, Sha256};
+
+use crate::wiki::{self, LinkResolver, normalize, get_relative_link};
+use super::{
+    MM_EXPORT_INDEX_PAGE,
+    MM_EXPORT_TAGS_FOLDER,
+    MM_EXPORT_PAGE_EXTENSION,
+    MM_EXPORT_TAG_NAME_LENGTH,
+    MM_EXPORT_RESERVED_SUFFIX,
+    MM_MARKDOWN_EXTENSIONS,
+    MM_RESERVED_NAMES,
+};
+
+
+/// A rendered note.
+pub(crate) struct Page {
+    /// Path to the page relative to site's root
+    path: PathBuf,
+
+    /// Title of the page
+    title: String,
+
+    /// Complete HTML document
+    html: String,
+
+    /// Tags found in the note (sorted, without duplicates)
+    tags: Vec<String>,
+}
+
+
+impl Page {
+    /// Obtains a path to the page relative to site's root.
+    pub(crate) fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+
+    /// Obtains a complete HTML document of the page.
+    pub(crate) fn get_html(&self) -> &str {
+        &self.html
+    }
+
+
+    /// Obtains tags found in the note.
+    pub(crate) fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+
+/// Checks if a note should be rendered from Markdown by its extension.
+/// 
+/// * `relative_path` - path to a note
+pub(crate) fn is_markdown(relative_path: &Path) -> bool {
+    relative_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MM_MARKDOWN_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+
+/// Returns a path to an exported note relative to site's root. Folder
+/// structure is preserved, Markdown notes get pages with the same names.
+/// 
+/// Index page and tags folder are generated, so a note or a folder, that
+/// would clash with them (regardless of case), gets a suffix.
+/// 
+/// * `relative_path` - path to a note relative to repository's root
+pub(crate) fn get_exported_path(relative_path: &Path) -> PathBuf {
+    let exported_path = match is_markdown(relative_path) {
+        true => relative_path.with_extension(MM_EXPORT_PAGE_EXTENSION),
+        false => relative_path.to_path_buf()
+    };
+
+    let mut components = exported_path.components();
+    let first = match components.next() {
+        Some(first) => Path::new(first.as_os_str()),
+        None => return exported_path
+    };
+
+    let is_reserved = |name: &str| first.as_os_str().eq_ignore_ascii_case(name);
+    let is_generated = is_reserved(MM_EXPORT_TAGS_FOLDER)
+        || (is_reserved(MM_EXPORT_INDEX_PAGE) && components.as_path().as_os_str().is_empty());
+
+    if !is_generated {
+        return exported_path;
+    }
+
+    let mut name = first
+        .file_stem()
+        .unwrap_or(first.as_os_str())
+        .to_owned();
+
+    name.push(MM_EXPORT_RESERVED_SUFFIX);
+    if let Some(extension) = first.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+
+    let mut exported_path = PathBuf::from(name);
+    exported_path.extend(components);
+
+    exported_path
+}
+
+
+/// Returns a path to a page of a tag relative to site's root.
+/// 
+/// Page's name is safe on any filesystem: it is lowercase and consists
+/// of letters, digits, `-` and `_` only, doesn't start with a dot and isn't
+/// a reserved name. Names, that differ from their tags (e.g. `a/b` or
+/// `Todo`), get a hash of the tag as a suffix, so different tags never
+/// share a page.
+/// 
+/// * `tag` - tag without leading `#`
+pub(crate) fn get_tag_page_path(tag: &str) -> PathBuf {
+    let mut name: String = tag
+        .chars()
+        .take(MM_EXPORT_TAG_NAME_LENGTH)
+        .map(|ch| match ch.is_alphanumeric() || ch == '-' || ch == '_' {
+            true => ch,
+            false => '-'
+        })
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    let is_reserved = name.is_empty()
+        || name.chars().all(|ch| ch == '-')
+        || MM_RESERVED_NAMES.contains(&name.as_str());
+
+    if is_reserved || name != tag {
+        let hash: String = Sha256::digest(tag.as_bytes())
+            .iter()
+            .take(4)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        if !name.is_empty() {
+            name.push('-');
+        }
+
+        name.push_str(&hash);
+    }
+
+    Path::new(MM_EXPORT_TAGS_FOLDER)
+        .join(name)
+        .with_extension(MM_EXPORT_PAGE_EXTENSION)
+}
+
+
+/// Renders a Markdown note into a page. Wiki links are converted into
+/// relative links to pages (embedded files are rendered as images), links
+/// to other notes are redirected to their pages. Tags (e.g. `#project`) are
+/// collected from note's text and front matter, which is not rendered.
+/// 
+/// * `relative_path` - path to a note relative to repository's root
+/// * `markdown` - content of the note
+/// * `resolver` - resolver of wiki links
+pub(crate) fn render_note(relative_path: &Path, markdown: &str, resolver: &LinkResolver) -> Page {
+    let tags = wiki::collect_note_tags(markdown);
+    let (_front_matter, markdown) = wiki::split_front_matter(markdown);
+
+    let path = get_exported_path(relative_path);
+    let markdown = wiki::replace_wiki_links(markdown, |link| {
+        match resolver.resolve(link.get_target()) {
+            Some(target) if link.is_embed() && !is_markdown(target) => {
+                format!("![{}](<{}>)", link.get_title(), get_relative_link(&path, &get_exported_path(target)))
+            },
+            Some(target) => {
+                format!("[{}](<{}>)", link.get_title(), get_relative_link(&path, &get_exported_path(target)))
+            },
+            None => link.get_title().to_owned()
+        }
+    });
+
+    let events = Parser::new_ext(&markdown, Options::all())
+        .map(|event| match event {
+            Event::Start(Tag::Link(kind, destination, title)) => {
+                Event::Start(Tag::Link(kind, redirect_note_link(relative_path, destination), title))
+            },
+            Event::End(Tag::Link(kind, destination, title)) => {
+                Event::End(Tag::Link(kind, redirect_note_link(relative_path, destination), title))
+            },
+            event => event
+        });
+
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+
+    let title = relative_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let html = wrap_page(&title, &body, &path);
+
+    Page {
+        path,
+        title,
+        html,
+        tags
+    }
+}
+
+
+/// Renders an index page with a tree of folders and a list of tags.
+/// 
+/// * `pages` - all rendered pages
+pub(crate) fn render_index(pages: &[Page]) -> String {
+    #[derive(Default)]
+    struct Folder<'a> {
+        folders: BTreeMap<String, Folder<'a>>,
+        pages: Vec<&'a Page>,
+    }
+
+    fn render_folder(folder: &Folder, body: &mut String) {
+        body.push_str("<ul>\n");
+
+        for (name, subfolder) in &folder.folders {
+            body.push_str(&format!("<li class=\"folder\">{}\n", escape(name)));
+            render_folder(subfolder, body);
+            body.push_str("</li>\n");
+        }
+
+        for page in &folder.pages {
+            body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", escape(&normalize(&page.path)), escape(&page.title)));
+        }
+
+        body.push_str("</ul>\n");
+    }
+
+    let mut root = Folder::default();
+    for page in pages {
+        let mut folder = &mut root;
+        if let Some(parent) = page.path.parent() {
+            for component in parent.components() {
+                folder = folder.folders
+                    .entry(component.as_os_str().to_string_lossy().into_owned())
+                    .or_default();
+            }
+        }
+
+        folder.pages.push(page);
+    }
+
+    let mut body = String::from("<h1>Notes</h1>\n");
+    render_folder(&root, &mut body);
+
+    let tags = collect_tag_index(pages);
+    if !tags.is_empty() {
+        body.push_str("<h2>Tags</h2>\n<ul>\n");
+
+        for (tag, tagged) in &tags {
+            body.push_str(&format!("<li><a href=\"{}\">#{}</a> ({})</li>\n",
+                escape(&normalize(&get_tag_page_path(tag))), escape(tag), tagged.len()));
+        }
+
+        body.push_str("</ul>\n");
+    }
+
+    wrap_page("Notes", &body, Path::new(MM_EXPORT_INDEX_PAGE))
+}
+
+
+/// Renders a page with all notes marked by a tag.
+/// 
+/// * `tag` - tag without leading `#`
+/// * `pages` - all rendered pages
+pub(crate) fn render_tag_page(tag: &str, pages: &[Page]) -> String {
+    let tag_page_path = get_tag_page_path(tag);
+
+    let mut body = format!("<h1>#{}</h1>\n<ul>\n", escape(tag));
+    for page in pages.iter().filter(|page| page.tags.iter().any(|page_tag| page_tag == tag)) {
+        body.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n",
+            escape(&get_relative_link(&tag_page_path, &page.path)), escape(&page.title)));
+    }
+
+    body.push_str("</ul>\n");
+
+    wrap_page(&format!("#{}", tag), &body, &tag_page_path)
+}
+
+
+/// Groups pages by tags.
+/// 
+/// * `pages` - all rendered pages
+fn collect_tag_index(pages: &[Page]) -> BTreeMap<&str, Vec<&Page>> {
+    let mut tags: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in pages {
+        for tag in &page.tags {
+            tags.entry(tag).or_default().push(page);
+        }
+    }
+
+    tags
+}
+
+
+/// Wraps body of a page into a complete HTML document with a link
+/// to the index page.
+/// 
+/// * `title` - title of the page
+/// * `body` - HTML body of the page
+/// * `page_path` - path to the page relative to site's root
+fn wrap_page(title: &str, body: &str, page_path: &Path) -> String {
+    let index_link = get_relative_link(page_path, Path::new(MM_EXPORT_INDEX_PAGE));
+
+    format!(concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n",
+        "<head>\n",
+        "<meta charset=\"utf-8\">\n",
+        "<title>{}</title>\n",
+        "</head>\n",
+        "<body>\n",
+        "<nav><a href=\"{}\">Index</a></nav>\n",
+        "<main>\n{}</main>\n",
+        "</body>\n",
+        "</html>\n"),
+        escape(title), escape(&index_link), body)
+}
+
+
+/// Redirects a relative link to a Markdown note into a link to its page.
+/// Links to files, that are exported under other names, are redirected
+/// too. Other links are returned unchanged.
+/// 
+/// * `relative_path` - path to a note with the link relative to repository's root
+/// * `destination` - destination of a link
+fn redirect_note_link<'a>(relative_path: &Path, destination: CowStr<'a>) -> CowStr<'a> {
+    //
+    // The target is resolved against repository's root, because
+    // it may be exported differently (see `get_exported_path`)
+    //
+
+    let (target, fragment) = match wiki::resolve_relative_link(relative_path, &destination) {
+        Some((target, fragment)) => (target, fragment),
+        None => return destination
+    };
+
+    let exported_path = get_exported_path(&target);
+    if !is_markdown(&target) && exported_path == target {
+        return destination;
+    }
+
+    let page_path = get_exported_path(relative_path);
+    CowStr::from(format!("{}{}", get_relative_link(&page_path, &exported_path), fragment))
+}
+
+
+/// Escapes special HTML characters.
+/// 
+/// * `text` - text to escape
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Obtains a name of a tag's page.
+    fn get_tag_page_name(tag: &str) -> String {
+        let path = get_tag_page_path(tag);
+        assert_eq!(path.parent(), Some(Path::new(MM_EXPORT_TAGS_FOLDER)));
+
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+
+    #[test]
+    fn generated_pages_are_not_overwritten() {
+        assert_eq!(get_exported_path(Path::new("index.md")), Path::new("index-note.html"));
+        assert_eq!(get_exported_path(Path::new("Index.markdown")), Path::new("Index-note.html"));
+        assert_eq!(get_exported_path(Path::new("INDEX.html")), Path::new("INDEX-note.html"));
+        assert_eq!(get_exported_path(Path::new("_tags/todo.md")), Path::new("_tags-note/todo.html"));
+        assert_eq!(get_exported_path(Path::new("_Tags/image.png")), Path::new("_Tags-note/image.png"));
+
+        assert_eq!(get_exported_path(Path::new("folder/index.md")), Path::new("folder/index.html"));
+        assert_eq!(get_exported_path(Path::new("folder/_tags/todo.md")), Path::new("folder/_tags/todo.html"));
+        assert_eq!(get_exported_path(Path::new("index.html/note.md")), Path::new("index.html/note.html"));
+        assert_eq!(get_exported_path(Path::new("image.png")), Path::new("image.png"));
+    }
+
+
+    #[test]
+    fn links_lead_to_exported_paths() {
+        let files = vec![PathBuf::from("index.md"), PathBuf::from("_tags/image.png"), PathBuf::from("folder/note.md")];
+        let resolver = LinkResolver::new(&files, is_markdown);
+
+        let page = render_note(Path::new("folder/note.md"), "[[index]] ![[image.png]] [home](../index.md) [pic](../_tags/image.png) [site](https://example.com)", &resolver);
+        let html = page.get_html();
+
+        assert_eq!(page.get_path(), Path::new("folder/note.html"));
+        assert!(html.contains("href=\"../index-note.html\""), "{}", html);
+        assert!(html.contains("src=\"../_tags-note/image.png\""), "{}", html);
+        assert!(html.contains("href=\"../_tags-note/image.png\""), "{}", html);
+        assert!(html.contains("href=\"https://example.com\""), "{}", html);
+        assert_eq!(html.matches("../index-note.html").count(), 2, "{}", html);
+    }
+
+
+    #[test]
+    fn plain_tag_keeps_its_name() {
+        assert_eq!(get_tag_page_name("todo"), "todo.html");
+        assert_eq!(get_tag_page_name("a-b_c1"), "a-b_c1.html");
+    }
+
+
+    #[test]
+    fn unsafe_tags_are_sanitized() {
+        for tag in ["..", ".", "a/b", "a\\b", "c:d", "a/../b", "con", "...", "*?<>|\""] {
+            let name = get_tag_page_name(tag);
+            let stem = name.strip_suffix(".html").unwrap();
+
+            assert!(!stem.is_empty() && !stem.starts_with('.'), "{}", name);
+            assert!(stem.chars().all(|ch| ch.is_alphanumeric() || ch == '-' || ch == '_'), "{}", name);
+            assert!(!MM_RESERVED_NAMES.contains(&stem), "{}", name);
+        }
+    }
+
+
+    #[test]
+    fn colliding_tags_get_different_pages() {
+        let tags = ["a/b", "a-b", "a:b", "A-b", "a\\b"];
+        let mut names: Vec<String> = tags.iter().map(|tag| get_tag_page_name(tag)).collect();
+
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), tags.len());
+    }
+
+
+    #[test]
+    fn long_tag_is_shortened() {
+        let name = get_tag_page_name(&"x".repeat(1000));
+        assert!(name.len() < MM_EXPORT_TAG_NAME_LENGTH + 16);
+    }
+}
@@ -0,0 +1,47 @@
+mod html;
+mod archive;
+
+
+pub(crate) use self::html::{
+    is_markdown,
+    render_note,
+    render_index,
+    render_tag_page,
+    get_tag_page_path,
+    get_exported_path,
+};
+pub use self::archive::{ArchiveFormat};
+pub(crate) use self::archive::{ArchiveWriter, read_archive};
+
+
+/// Name of generated index page
+pub(crate) const MM_EXPORT_INDEX_PAGE: &str = "index.html";
+
+/// Name of a folder with generated tag pages
+const MM_EXPORT_TAGS_FOLDER: &str = "_tags";
+
+/// Suffix of exported notes and folders, whose names are taken by
+/// generated pages
+const MM_EXPORT_RESERVED_SUFFIX: &str = "-note";
+
+/// Extension of exported pages
+const MM_EXPORT_PAGE_EXTENSION: &str = "html";
+
+/// Maximum length of a tag page's name (in characters) before a hash suffix
+const MM_EXPORT_TAG_NAME_LENGTH: usize = 64;
+
+/// Names, that are reserved by Windows regardless of extension
+const MM_RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Extensions of notes, that are rendered from Markdown
+const MM_MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Header of zip archives
+const MM_ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Header of gzip-compressed archives
+const MM_GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
@@ -0,0 +1,276 @@
+use std::time::SystemTime;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::NaiveDateTime;
+use md5::{Md5, Digest};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use crate::error::{Result, Error, ErrorCategory};
+use super::{MM_ENEX_DATE_FORMAT};
+
+
+/// Extensions of files by their MIME types
+const MIME_EXTENSIONS: [(&str, &str); 10] = [
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/svg+xml", "svg"),
+    ("image/webp", "webp"),
+    ("image/bmp", "bmp"),
+    ("application/pdf", "pdf"),
+    ("text/plain", "txt"),
+    ("audio/mpeg", "mp3"),
+    ("audio/wav", "wav"),
+];
+
+
+/// A resource (e.g. an image) embedded into an Evernote note.
+pub(crate) struct EnexResource {
+    /// Decoded content of the resource
+    data: Vec<u8>,
+
+    /// MD5 hash of the content in hex, ENML refers resources by it
+    hash: String,
+
+    /// MIME type of the resource
+    mime: String,
+
+    /// Original name of the resource's file (if any)
+    file_name: Option<String>,
+}
+
+
+impl EnexResource {
+    /// Obtains decoded content of the resource.
+    pub(crate) fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+
+    /// Obtains MD5 hash of the content in hex.
+    pub(crate) fn get_hash(&self) -> &str {
+        &self.hash
+    }
+
+
+    /// Obtains a name of the resource's file. Resources without original
+    /// names are named after their hashes with an extension guessed from
+    /// MIME type.
+    pub(crate) fn get_file_name(&self) -> String {
+        if let Some(file_name) = &self.file_name {
+            return file_name.clone();
+        }
+
+        match MIME_EXTENSIONS.iter().find(|(mime, _extension)| *mime == self.mime) {
+            Some((_mime, extension)) => format!("{}.{}", self.hash, extension),
+            None => self.hash.clone()
+        }
+    }
+}
+
+
+/// A note from an Evernote export file.
+#[derive(Default)]
+pub(crate) struct EnexNote {
+    /// Title of the note
+    title: String,
+
+    /// Content of the note in ENML
+    content: String,
+
+    /// Time of note's creation (if known)
+    created: Option<SystemTime>,
+
+    /// Time of note's last modification (if known)
+    updated: Option<SystemTime>,
+
+    /// Tags of the note
+    tags: Vec<String>,
+
+    /// Resources embedded into the note
+    resources: Vec<EnexResource>,
+}
+
+
+impl EnexNote {
+    /// Obtains a title of the note.
+    pub(crate) fn get_title(&self) -> &str {
+        &self.title
+    }
+
+
+    /// Obtains content of the note in ENML.
+    pub(crate) fn get_content(&self) -> &str {
+        &self.content
+    }
+
+
+    /// Obtains a time of note's creation (if known).
+    pub(crate) fn get_created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+
+    /// Obtains a time of note's last modification (if known).
+    pub(crate) fn get_updated(&self) -> Option<SystemTime> {
+        self.updated
+    }
+
+
+    /// Obtains tags of the note.
+    pub(crate) fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+
+    /// Obtains resources embedded into the note.
+    pub(crate) fn get_resources(&self) -> &[EnexResource] {
+        &self.resources
+    }
+}
+
+
+/// Parses an Evernote export file (`.enex`). Resources, that cannot be
+/// decoded, and malformed dates are reported and ignored.
+/// 
+/// * `xml` - content of the file
+/// * `warn` - function, that reports a problem with a note (by its title)
+pub(crate) fn parse_enex<W>(xml: &str, mut warn: W) -> Result<Vec<EnexNote>>
+where
+    W: FnMut(&str, String)
+{
+    let mut reader = Reader::from_str(xml);
+
+    let mut notes = Vec::new();
+    let mut note: Option<EnexNote> = None;
+    let mut resource: Option<(String, String, Option<String>)> = None;
+
+    //
+    // Path of currently opened elements and text of the innermost one
+    //
+
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+
+                match (path.last().map(String::as_str), name.as_str()) {
+                    (Some("en-export"), "note") => note = Some(EnexNote::default()),
+                    (Some("note"), "resource") => resource = Some((String::new(), String::new(), None)),
+                    _ => {}
+                }
+
+                path.push(name);
+                text.clear();
+            },
+            Event::Text(content) => text.push_str(&content.unescape()?),
+            Event::CData(content) => text.push_str(&String::from_utf8_lossy(&content.into_inner())),
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let parent = path.last().map(String::as_str);
+
+                match (parent, name.as_str()) {
+                    (Some("en-export"), "note") => notes.extend(note.take()),
+                    (Some("note"), field) => {
+                        let note = match note.as_mut() {
+                            Some(note) => note,
+                            None => continue
+                        };
+
+                        match field {
+                            "title" => note.title = text.trim().to_owned(),
+                            "content" => note.content = std::mem::take(&mut text),
+                            "created" => note.created = parse_date(&text, &note.title, &mut warn),
+                            "updated" => note.updated = parse_date(&text, &note.title, &mut warn),
+                            "tag" => note.tags.push(text.trim().to_owned()),
+                            "resource" => {
+                                let (data, mime, file_name) = resource.take().unwrap_or_default();
+                                match decode_resource(&data, mime, file_name) {
+                                    Some(resource) => note.resources.push(resource),
+                                    None => warn(&note.title, "cannot decode embedded resource".to_owned())
+                                }
+                            },
+                            _ => {}
+                        }
+                    },
+                    (Some("resource"), "data") => {
+                        if let Some((data, _, _)) = resource.as_mut() {
+                            *data = std::mem::take(&mut text);
+                        }
+                    },
+                    (Some("resource"), "mime") => {
+                        if let Some((_, mime, _)) = resource.as_mut() {
+                            *mime = text.trim().to_owned();
+                        }
+                    },
+                    (Some("resource-attributes"), "file-name") => {
+                        if let Some((_, _, file_name)) = resource.as_mut() {
+                            *file_name = Some(text.trim().to_owned()).filter(|name| !name.is_empty());
+                        }
+                    },
+                    _ => {}
+                }
+
+                text.clear();
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !path.is_empty() {
+        return Err(Error::from_string("unexpected end of export file", ErrorCategory::IO));
+    }
+
+    Ok(notes)
+}
+
+
+/// Decodes a base64-encoded resource and computes its hash.
+/// 
+/// * `data` - base64-encoded content (may contain line breaks)
+/// * `mime` - MIME type of the resource
+/// * `file_name` - original name of the resource's file
+fn decode_resource(data: &str, mime: String, file_name: Option<String>) -> Option<EnexResource> {
+    let encoded: String = data
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+
+    let data = BASE64.decode(encoded).ok()?;
+    let hash = Md5::digest(&data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    Some(EnexResource {
+        data,
+        hash,
+        mime,
+        file_name
+    })
+}
+
+
+/// Parses a date in Evernote format (e.g. `20230131T120000Z`).
+/// 
+/// * `text` - text to parse
+/// * `title` - title of a note with the date
+/// * `warn` - function, that reports a problem with a note
+fn parse_date<W>(text: &str, title: &str, warn: &mut W) -> Option<SystemTime>
+where
+    W: FnMut(&str, String)
+{
+    match NaiveDateTime::parse_from_str(text.trim(), MM_ENEX_DATE_FORMAT) {
+        Ok(date) => Some(SystemTime::from(date.and_utc())),
+        Err(_) => {
+            warn(title, format!("malformed date '{}'", text.trim()));
+            None
+        }
+    }
+}
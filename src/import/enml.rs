@@ -0,0 +1,583 @@
+use quick_xml::Reader;
+use quick_xml::events::{Event, BytesStart};
+
+use crate::error::Result;
+
+
+/// Elements, whose content is not converted
+const SKIPPED_ELEMENTS: [&str; 5] = ["en-crypt", "head", "title", "style", "script"];
+
+/// Marker of Evernote's code blocks in `style` attribute
+const CODE_BLOCK_STYLE: &str = "-en-codeblock:true";
+
+
+/// Converts a note in ENML (Evernote's XHTML dialect) into Markdown.
+/// 
+/// Formatting, lists (including checkboxes), links, quotes, code blocks
+/// and tables are converted, other elements are reduced to their text.
+/// Embedded resources (`en-media`) are replaced with results of `media`.
+/// 
+/// * `enml` - content of a note
+/// * `media` - function, that composes a link to a resource by its hash
+///   and MIME type (`None` if the resource is absent)
+/// * `warn` - function, that reports a problem with the note
+pub(crate) fn convert_enml<M, W>(enml: &str, mut media: M, mut warn: W) -> Result<String>
+where
+    M: FnMut(&str, &str) -> Option<String>,
+    W: FnMut(String)
+{
+    let mut reader = Reader::from_str(enml);
+    let mut writer = MarkdownWriter {
+        at_line_start: true,
+        blank: true,
+        ..Default::default()
+    };
+
+    //
+    // Closing part of each opened element (if any)
+    //
+
+    let mut closings: Vec<Closing> = Vec::new();
+    let mut skipped = 0;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = get_name(&element);
+                if skipped > 0 || SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    if skipped == 0 && name == "en-crypt" {
+                        warn("encrypted content is not imported".to_owned());
+                    }
+
+                    skipped += 1;
+                    continue;
+                }
+
+                let closing = writer.open(&name, &element, &mut media, &mut warn)?;
+                closings.push(closing);
+            },
+            Event::Empty(element) => {
+                let name = get_name(&element);
+                if skipped > 0 || SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    continue;
+                }
+
+                let closing = writer.open(&name, &element, &mut media, &mut warn)?;
+                writer.close(closing);
+            },
+            Event::End(_) => {
+                if skipped > 0 {
+                    skipped -= 1;
+                    continue;
+                }
+
+                if let Some(closing) = closings.pop() {
+                    writer.close(closing);
+                }
+            },
+            Event::Text(text) if skipped == 0 => writer.text(&text.unescape()?),
+            Event::CData(text) if skipped == 0 => writer.text(&String::from_utf8_lossy(&text.into_inner())),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(writer.finish())
+}
+
+
+/// Describes what to do when an element is closed.
+enum Closing {
+    /// Nothing special
+    Nothing,
+
+    /// Write a text
+    Text(String),
+
+    /// End a block (with a blank line if `true`)
+    Block(bool),
+
+    /// End a list item
+    Item,
+
+    /// End a list
+    List,
+
+    /// End a quote
+    Quote,
+
+    /// End a code block
+    Code,
+
+    /// End a table's row
+    Row,
+
+    /// End a table's cell
+    Cell,
+
+    /// End a table
+    Table,
+}
+
+
+/// A table being converted.
+#[derive(Default)]
+struct Table {
+    /// Number of completed rows
+    rows: usize,
+
+    /// Number of cells in current row
+    cells: usize,
+}
+
+
+/// Composes Markdown text line by line.
+#[derive(Default)]
+struct MarkdownWriter {
+    /// Composed text
+    output: String,
+
+    /// Prefixes of lines (quotes and indentation of list items)
+    prefixes: Vec<String>,
+
+    /// Marker of a list item, that is written instead of the last prefix
+    /// on the next line
+    marker: Option<String>,
+
+    /// Counters of opened lists (`None` for bullet lists)
+    lists: Vec<Option<usize>>,
+
+    /// Opened tables
+    tables: Vec<Table>,
+
+    /// Depth of code blocks
+    code: usize,
+
+    /// Nothing is written on current line yet
+    at_line_start: bool,
+
+    /// The last completed line is blank
+    blank: bool,
+}
+
+
+impl MarkdownWriter {
+    /// Handles an opening element. Returns what to do when the element
+    /// is closed.
+    fn open<M, W>(&mut self, name: &str, element: &BytesStart, media: &mut M, warn: &mut W) -> Result<Closing>
+    where
+        M: FnMut(&str, &str) -> Option<String>,
+        W: FnMut(String)
+    {
+        let closing = match name {
+            "p" => {
+                self.block(true);
+                Closing::Block(true)
+            },
+            "div" if get_attribute(element, "style")?.is_some_and(|style| style.replace(' ', "").contains(CODE_BLOCK_STYLE)) => {
+                self.open_code()
+            },
+            "div" | "en-note" => {
+                self.block(false);
+                Closing::Block(false)
+            },
+            "pre" => self.open_code(),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block(true);
+
+                let level = name[1..].parse().unwrap_or(1);
+                self.write(&format!("{} ", "#".repeat(level)));
+
+                Closing::Block(true)
+            },
+            "br" => {
+                self.newline();
+                Closing::Nothing
+            },
+            "hr" => {
+                self.block(true);
+                self.write("---");
+                self.block(true);
+                Closing::Nothing
+            },
+            "blockquote" => {
+                self.block(true);
+                self.prefixes.push("> ".to_owned());
+                Closing::Quote
+            },
+            "ul" | "ol" => {
+                self.block(self.lists.is_empty());
+                self.lists.push(if name == "ol" { Some(0) } else { None });
+                Closing::List
+            },
+            "li" => {
+                self.block(false);
+
+                let marker = match self.lists.last_mut() {
+                    Some(Some(counter)) => {
+                        *counter += 1;
+                        format!("{}. ", counter)
+                    },
+                    _ => "- ".to_owned()
+                };
+
+                self.prefixes.push(" ".repeat(marker.len()));
+                self.marker = Some(marker);
+                Closing::Item
+            },
+            "en-todo" => {
+                let checked = get_attribute(element, "checked")?.is_some_and(|checked| checked == "true");
+                self.write(if checked { "[x] " } else { "[ ] " });
+                Closing::Nothing
+            },
+            "b" | "strong" => self.open_inline("**"),
+            "i" | "em" => self.open_inline("*"),
+            "s" | "strike" | "del" => self.open_inline("~~"),
+            "code" if self.code == 0 => self.open_inline("`"),
+            "a" => match get_attribute(element, "href")? {
+                Some(href) if self.code == 0 => {
+                    self.write("[");
+                    Closing::Text(format!("](<{}>)", href))
+                },
+                _ => Closing::Nothing
+            },
+            "img" => {
+                if let Some(source) = get_attribute(element, "src")? {
+                    self.write(&format!("![](<{}>)", source));
+                }
+
+                Closing::Nothing
+            },
+            "en-media" => {
+                let hash = get_attribute(element, "hash")?.unwrap_or_default();
+                let mime = get_attribute(element, "type")?.unwrap_or_default();
+
+                match media(&hash, &mime) {
+                    Some(link) => self.write(&link),
+                    None => warn(format!("embedded resource {} not found", hash))
+                }
+
+                Closing::Nothing
+            },
+            "table" => {
+                self.block(true);
+                self.tables.push(Table::default());
+                Closing::Table
+            },
+            "tr" => {
+                self.block(false);
+                Closing::Row
+            },
+            "td" | "th" => {
+                self.write("| ");
+                Closing::Cell
+            },
+            _ => Closing::Nothing
+        };
+
+        Ok(closing)
+    }
+
+
+    /// Handles a closing element.
+    fn close(&mut self, closing: Closing) {
+        match closing {
+            Closing::Nothing => {},
+            Closing::Text(text) => self.write(&text),
+            Closing::Block(blank) => self.block(blank),
+            Closing::Item => {
+                self.block(false);
+                self.prefixes.pop();
+                self.marker = None;
+            },
+            Closing::List => {
+                self.lists.pop();
+                self.block(self.lists.is_empty());
+            },
+            Closing::Quote => {
+                self.block(false);
+                self.prefixes.pop();
+                self.block(true);
+            },
+            Closing::Code => {
+                self.code -= 1;
+                self.block(false);
+                self.write("```");
+                self.block(true);
+            },
+            Closing::Cell => {
+                if !self.output.ends_with(' ') {
+                    self.write(" ");
+                }
+
+                if let Some(table) = self.tables.last_mut() {
+                    table.cells += 1;
+                }
+            },
+            Closing::Row => {
+                self.write("|");
+
+                let separator = match self.tables.last_mut() {
+                    Some(table) => {
+                        table.rows += 1;
+                        (table.rows == 1).then(|| format!("|{}", " --- |".repeat(table.cells)))
+                    },
+                    None => None
+                };
+
+                if let Some(separator) = separator {
+                    self.newline();
+                    self.write(&separator);
+                }
+
+                self.newline();
+
+                if let Some(table) = self.tables.last_mut() {
+                    table.cells = 0;
+                }
+            },
+            Closing::Table => {
+                self.tables.pop();
+                self.block(true);
+            }
+        }
+    }
+
+
+    /// Writes text of an element. Whitespaces are collapsed and special
+    /// characters are escaped outside of code blocks.
+    fn text(&mut self, text: &str) {
+        if self.code > 0 {
+            for (index, line) in text.split('\n').enumerate() {
+                if index > 0 {
+                    self.newline();
+                }
+
+                self.write(line);
+            }
+
+            return;
+        }
+
+        let mut collapsed = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                c if c.is_whitespace() => {
+                    let is_redundant = match collapsed.is_empty() {
+                        true => self.at_line_start || self.output.ends_with(' '),
+                        false => collapsed.ends_with(' ')
+                    };
+
+                    if !is_redundant {
+                        collapsed.push(' ');
+                    }
+                },
+                '\\' | '*' | '_' | '`' | '[' | ']' => {
+                    collapsed.push('\\');
+                    collapsed.push(c);
+                },
+                c => collapsed.push(c)
+            }
+        }
+
+        self.write(&collapsed);
+    }
+
+
+    /// Returns composed text.
+    fn finish(mut self) -> String {
+        self.block(false);
+
+        let trimmed = self.output.trim_end().len();
+        self.output.truncate(trimmed);
+        self.output.push('\n');
+
+        self.output
+    }
+
+
+    /// Starts a code block.
+    fn open_code(&mut self) -> Closing {
+        self.block(true);
+        self.write("```");
+        self.newline();
+        self.code += 1;
+
+        Closing::Code
+    }
+
+
+    /// Starts inline formatting.
+    /// 
+    /// * `mark` - formatting mark (e.g. `**`)
+    fn open_inline(&mut self, mark: &str) -> Closing {
+        if self.code > 0 {
+            return Closing::Nothing;
+        }
+
+        self.write(mark);
+        Closing::Text(mark.to_owned())
+    }
+
+
+    /// Writes a text on current line.
+    fn write(&mut self, text: &str) {
+        if text.is_empty() && !(self.at_line_start && self.code > 0) {
+            return;
+        }
+
+        if self.at_line_start {
+            self.write_prefix(false);
+            self.at_line_start = false;
+        }
+
+        self.output.push_str(text);
+    }
+
+
+    /// Ends current line.
+    fn newline(&mut self) {
+        if self.at_line_start || self.output.is_empty() {
+            self.blank = self.marker.is_none();
+            self.write_prefix(true);
+        }
+        else {
+            self.blank = false;
+        }
+
+        self.output.push('\n');
+        self.at_line_start = true;
+    }
+
+
+    /// Ends current block. Inside of tables' cells blocks are separated
+    /// with spaces.
+    /// 
+    /// * `blank` - separate the block with a blank line
+    fn block(&mut self, blank: bool) {
+        if self.tables.last().is_some_and(|table| table.cells > 0 || !self.at_line_start) {
+            if !self.output.ends_with(' ') {
+                self.write(" ");
+            }
+
+            return;
+        }
+
+        if self.output.is_empty() {
+            return;
+        }
+
+        if !self.at_line_start {
+            self.newline();
+        }
+
+        if blank && !self.blank {
+            self.newline();
+        }
+    }
+
+
+    /// Writes prefixes of a line.
+    /// 
+    /// * `trim` - remove trailing whitespaces (for blank lines)
+    fn write_prefix(&mut self, trim: bool) {
+        let mut prefix = self.prefixes.concat();
+        if let Some(marker) = self.marker.take() {
+            prefix.truncate(prefix.len() - marker.len());
+            prefix.push_str(&marker);
+        }
+
+        if trim {
+            prefix.truncate(prefix.trim_end().len());
+        }
+
+        self.output.push_str(&prefix);
+    }
+}
+
+
+/// Obtains a lowercase local name of an element.
+/// 
+/// * `element` - element to get name of
+fn get_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_lowercase()
+}
+
+
+/// Obtains an unescaped value of element's attribute.
+/// 
+/// * `element` - element to get attribute of
+/// * `name` - name of the attribute
+fn get_attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    Ok(match element.try_get_attribute(name)? {
+        Some(attribute) => Some(attribute.unescape_value()?.into_owned()),
+        None => None
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Converts a note without resources and warnings.
+    fn convert(body: &str) -> String {
+        let enml = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?><en-note>{}</en-note>", body);
+        convert_enml(&enml, |_, _| None, |_| {}).unwrap()
+    }
+
+
+    #[test]
+    fn formatting_is_converted() {
+        assert_eq!(
+            convert("<h2>Title</h2><p>Some <b>bold</b>, <i>italic</i> and <a href=\"https://example.com\">link</a></p><p>a*b_c</p>"),
+            "## Title\n\nSome **bold**, *italic* and [link](<https://example.com>)\n\na\\*b\\_c\n"
+        );
+    }
+
+
+    #[test]
+    fn lists_and_checkboxes_are_converted() {
+        assert_eq!(
+            convert("<ul><li>one<ol><li>nested</li><li>second</li></ol></li><li><en-todo checked=\"true\"/>done</li></ul>"),
+            "- one\n  1. nested\n  2. second\n- [x] done\n"
+        );
+    }
+
+
+    #[test]
+    fn quotes_and_code_blocks_are_converted() {
+        assert_eq!(
+            convert("<blockquote><div>quoted</div></blockquote><div style=\"-en-codeblock: true\">let *a* = 1;<br/>done</div>"),
+            "> quoted\n\n```\nlet *a* = 1;\ndone\n```\n"
+        );
+    }
+
+
+    #[test]
+    fn tables_are_converted() {
+        assert_eq!(
+            convert("<table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td><div>2</div></td></tr></table>"),
+            "| A | B |\n| --- | --- |\n| 1 | 2 |\n"
+        );
+    }
+
+
+    #[test]
+    fn resources_and_problems_are_reported() {
+        let enml = "<en-note><en-media hash=\"abc\" type=\"image/png\"/><en-media hash=\"def\" type=\"image/png\"/><en-crypt>secret</en-crypt></en-note>";
+
+        let mut warnings = Vec::new();
+        let markdown = convert_enml(enml, |hash, mime| (hash == "abc").then(|| format!("![{}](<{}.png>)", mime, hash)), |problem| warnings.push(problem)).unwrap();
+
+        assert_eq!(markdown, "![image/png](<abc.png>)\n");
+        assert_eq!(warnings, vec!["embedded resource def not found", "encrypted content is not imported"]);
+    }
+
+
+    #[test]
+    fn malformed_note_is_rejected() {
+        assert!(convert_enml("<en-note><p>text</en-note>", |_, _| None, |_| {}).is_err());
+    }
+}
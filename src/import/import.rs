@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::wiki::{self, LinkResolver, WikiLink, get_relative_link};
+use crate::repo::NoteMetadata;
+use super::{MM_UNTITLED_NOTE_NAME};
+
+
+/// A note prepared for import.
+pub(crate) struct ImportedNote {
+    /// Path to the note relative to repository's root
+    relative_path: PathBuf,
+
+    /// Content of the note
+    content: Vec<u8>,
+
+    /// Metadata of the note
+    metadata: NoteMetadata,
+}
+
+
+impl ImportedNote {
+    /// Constructs a note for import.
+    /// 
+    /// * `relative_path` - path to the note relative to repository's root
+    /// * `content` - content of the note
+    /// * `metadata` - metadata of the note
+    pub(crate) fn new(relative_path: PathBuf, content: Vec<u8>, metadata: NoteMetadata) -> Self {
+        ImportedNote {
+            relative_path,
+            content,
+            metadata
+        }
+    }
+
+
+    /// Obtains a path to the note relative to repository's root.
+    pub(crate) fn get_relative_path(&self) -> &Path {
+        &self.relative_path
+    }
+
+
+    /// Obtains content of the note.
+    pub(crate) fn get_content(&self) -> &[u8] {
+        &self.content
+    }
+
+
+    /// Obtains metadata of the note.
+    pub(crate) fn get_metadata(&self) -> &NoteMetadata {
+        &self.metadata
+    }
+}
+
+
+/// Checks if a file or a folder is hidden (its name starts with a dot).
+/// 
+/// * `path` - path to check
+pub(crate) fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+
+/// Composes a name of a note's file from its title. Characters, that are
+/// not allowed in file names, are replaced with underscores.
+/// 
+/// * `title` - title of a note
+pub(crate) fn get_note_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c
+        })
+        .collect();
+
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() {
+        return MM_UNTITLED_NOTE_NAME.to_owned();
+    }
+
+    name.to_owned()
+}
+
+
+/// Rewrites links of an imported note. Wiki links are converted into
+/// regular Markdown links, links to files, that became attachments, are
+/// redirected to attachments folder.
+/// 
+/// * `relative_path` - path to the note relative to source's root
+/// * `destination` - folder relative to repository's root, that source's
+///   root is imported into
+/// * `markdown` - content of the note
+/// * `resolver` - resolver of wiki links over source's files
+/// * `attachments` - paths to attachments relative to repository's root
+///   by source paths of files
+/// * `warn` - function, that reports a problem with the note
+pub(crate) fn rewrite_links<W>(relative_path: &Path, destination: &Path, markdown: &str, resolver: &LinkResolver, attachments: &HashMap<PathBuf, PathBuf>, mut warn: W) -> String
+where
+    W: FnMut(String)
+{
+    let note_path = destination.join(relative_path);
+
+    //
+    // Regular links are rewritten first. Their positions are
+    // known only in original text
+    //
+
+    let mut replacements = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, Options::all()).into_offset_iter() {
+        let destination = match event {
+            Event::Start(Tag::Link(_, destination, _)) | Event::Start(Tag::Image(_, destination, _)) => destination,
+            _ => continue
+        };
+
+        let attachment = wiki::resolve_relative_link(relative_path, &destination)
+            .and_then(|(target, _fragment)| attachments.get(&target));
+
+        let attachment = match attachment {
+            Some(attachment) => attachment,
+            None => continue
+        };
+
+        //
+        // Destination may be written with encoded spaces
+        //
+
+        let text = &markdown[range.clone()];
+        let encoded = destination.replace(' ', "%20");
+
+        let found = [destination.as_ref(), encoded.as_str()]
+            .into_iter()
+            .find_map(|raw| text.rfind(raw).map(|position| (range.start + position, raw.len())));
+
+        match found {
+            Some((start, length)) => {
+                replacements.push((start..start + length, format!("<{}>", get_relative_link(&note_path, attachment))));
+            },
+            None => warn(format!("cannot redirect link to {:?}", destination.as_ref()))
+        }
+    }
+
+    let mut rewritten = String::with_capacity(markdown.len());
+    let mut position = 0;
+    for (range, replacement) in replacements {
+        rewritten.push_str(&markdown[position..range.start]);
+        rewritten.push_str(&replacement);
+        position = range.end;
+    }
+
+    rewritten.push_str(&markdown[position..]);
+
+    //
+    // And now wiki links
+    //
+
+    wiki::replace_wiki_links(&rewritten, |link| {
+        match resolver.resolve(link.get_target()) {
+            Some(target) => match attachments.get(target) {
+                Some(attachment) => {
+                    let embed = if link.is_embed() { "!" } else { "" };
+                    format!("{}[{}](<{}>)", embed, get_attachment_title(link, target), get_relative_link(&note_path, attachment))
+                },
+                None => {
+                    let fragment = link
+                        .get_heading()
+                        .map(|heading| format!("#{}", slugify(heading)))
+                        .unwrap_or_default();
+
+                    format!("[{}](<{}{}>)", link.get_title(), get_relative_link(&note_path, &destination.join(target)), fragment)
+                }
+            },
+            None => {
+                warn(format!("unresolved link {}", link.get_text()));
+                link.get_text().to_owned()
+            }
+        }
+    })
+}
+
+
+/// Returns a title of a link to an attachment. Obsidian uses title of
+/// embedded images as their size (e.g. `![[image.png|300]]`), so such
+/// titles are replaced with file's name.
+/// 
+/// * `link` - wiki link to the attachment
+/// * `target` - source path of the attachment
+fn get_attachment_title(link: &WikiLink, target: &Path) -> String {
+    let title = link.get_title();
+    let is_size = title
+        .split('x')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+
+    if title == link.get_target() || is_size {
+        return target
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+
+    title.to_owned()
+}
+
+
+/// Converts a heading into an anchor, like most Markdown renderers do.
+/// 
+/// * `heading` - text of a heading
+fn slugify(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None
+        })
+        .collect()
+}
@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::DateTime;
+
+use crate::error::Result;
+use super::import::get_note_file_name;
+use super::{MM_JOPLIN_RESOURCES_FOLDER, MM_JOPLIN_ITEM_EXTENSION};
+
+
+/// Type of Joplin's notes
+const NOTE_TYPE: &str = "1";
+
+/// Type of Joplin's notebooks
+const FOLDER_TYPE: &str = "2";
+
+/// Type of Joplin's resources (attachments)
+const RESOURCE_TYPE: &str = "4";
+
+/// Type of Joplin's tags
+const TAG_TYPE: &str = "5";
+
+/// Type of Joplin's bindings of tags to notes
+const NOTE_TAG_TYPE: &str = "6";
+
+/// Length of Joplin's identifiers
+const ID_LENGTH: usize = 32;
+
+
+/// A note from a Joplin export archive.
+pub(crate) struct JexNote {
+    /// Joplin's identifier of the note
+    id: String,
+
+    /// Identifier of note's notebook
+    parent_id: String,
+
+    /// Title of the note
+    title: String,
+
+    /// Content of the note in Markdown
+    body: String,
+
+    /// Time of note's creation (if known)
+    created: Option<SystemTime>,
+
+    /// Time of note's last modification (if known)
+    updated: Option<SystemTime>,
+
+    /// Tags of the note
+    tags: Vec<String>,
+}
+
+
+impl JexNote {
+    /// Obtains Joplin's identifier of the note.
+    pub(crate) fn get_id(&self) -> &str {
+        &self.id
+    }
+
+
+    /// Obtains a title of the note.
+    pub(crate) fn get_title(&self) -> &str {
+        &self.title
+    }
+
+
+    /// Obtains content of the note in Markdown.
+    pub(crate) fn get_body(&self) -> &str {
+        &self.body
+    }
+
+
+    /// Obtains a time of note's creation (if known).
+    pub(crate) fn get_created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+
+    /// Obtains a time of note's last modification (if known).
+    pub(crate) fn get_updated(&self) -> Option<SystemTime> {
+        self.updated
+    }
+
+
+    /// Obtains tags of the note.
+    pub(crate) fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+
+/// A resource (attachment) from a Joplin export archive.
+pub(crate) struct JexResource {
+    /// Name of resource's file
+    file_name: String,
+
+    /// Content of the resource
+    data: Vec<u8>,
+}
+
+
+impl JexResource {
+    /// Obtains a name of resource's file.
+    pub(crate) fn get_file_name(&self) -> &str {
+        &self.file_name
+    }
+
+
+    /// Obtains content of the resource.
+    pub(crate) fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+
+/// Content of a Joplin export archive (`.jex`).
+pub(crate) struct JexExport {
+    /// Titles and parents of notebooks by their identifiers
+    folders: HashMap<String, (String, String)>,
+
+    /// Notes
+    notes: Vec<JexNote>,
+
+    /// Resources by their identifiers
+    resources: HashMap<String, JexResource>,
+}
+
+
+impl JexExport {
+    /// Reads an export archive. Items, that cannot be parsed, and resources
+    /// without content are reported and ignored.
+    /// 
+    /// * `path` - path to the archive
+    /// * `warn` - function, that reports a problem with the archive
+    pub(crate) fn read<W>(path: &Path, mut warn: W) -> Result<Self>
+    where
+        W: FnMut(String)
+    {
+        let mut items = Vec::new();
+        let mut resource_files = HashMap::new();
+
+        let mut archive = tar::Archive::new(File::open(path)?);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+
+            if entry_path.starts_with(MM_JOPLIN_RESOURCES_FOLDER) {
+                if let Some(id) = entry_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()) {
+                    resource_files.insert(id, content);
+                }
+            }
+            else if entry_path.extension().is_some_and(|extension| extension == MM_JOPLIN_ITEM_EXTENSION) {
+                match String::from_utf8(content) {
+                    Ok(content) => items.push(parse_item(&content)),
+                    Err(_) => warn(format!("item {:?} is not a valid UTF-8 text", entry_path))
+                }
+            }
+        }
+
+        //
+        // Items are linked by identifiers, so they are
+        // collected first and bound together afterwards
+        //
+
+        let mut export = JexExport {
+            folders: HashMap::new(),
+            notes: Vec::new(),
+            resources: HashMap::new()
+        };
+
+        let mut tags = HashMap::new();
+        let mut note_tags = Vec::new();
+
+        for (title, body, mut properties) in items {
+            let mut take = |key: &str| properties.remove(key).unwrap_or_default();
+
+            match take("type_").as_str() {
+                NOTE_TYPE => {
+                    let created = parse_time(&take("user_created_time")).or(parse_time(&take("created_time")));
+                    let updated = parse_time(&take("user_updated_time")).or(parse_time(&take("updated_time")));
+
+                    export.notes.push(JexNote {
+                        id: take("id"),
+                        parent_id: take("parent_id"),
+                        title,
+                        body,
+                        created,
+                        updated,
+                        tags: Vec::new()
+                    });
+                },
+                FOLDER_TYPE => {
+                    export.folders.insert(take("id"), (title, take("parent_id")));
+                },
+                RESOURCE_TYPE => {
+                    let id = take("id");
+                    let data = match resource_files.remove(&id) {
+                        Some(data) => data,
+                        None => {
+                            warn(format!("content of resource '{}' not found", title));
+                            continue;
+                        }
+                    };
+
+                    let extension = take("file_extension");
+                    let file_name = match take("filename") {
+                        file_name if !file_name.is_empty() => file_name,
+                        _ if !extension.is_empty() => format!("{}.{}", id, extension),
+                        _ => id.clone()
+                    };
+
+                    export.resources.insert(id, JexResource {
+                        file_name,
+                        data
+                    });
+                },
+                TAG_TYPE => {
+                    tags.insert(take("id"), title);
+                },
+                NOTE_TAG_TYPE => {
+                    note_tags.push((take("note_id"), take("tag_id")));
+                },
+                _ => {}
+            }
+        }
+
+        for (note_id, tag_id) in note_tags {
+            let note = export.notes.iter_mut().find(|note| note.id == note_id);
+            if let (Some(note), Some(tag)) = (note, tags.get(&tag_id)) {
+                note.tags.push(tag.clone());
+            }
+        }
+
+        Ok(export)
+    }
+
+
+    /// Obtains notes from the archive.
+    pub(crate) fn get_notes(&self) -> &[JexNote] {
+        &self.notes
+    }
+
+
+    /// Obtains resources by their identifiers.
+    pub(crate) fn get_resources(&self) -> &HashMap<String, JexResource> {
+        &self.resources
+    }
+
+
+    /// Composes a path to note's folder from titles of notebooks, that
+    /// contain the note.
+    /// 
+    /// * `note` - note to compose path for
+    pub(crate) fn get_folder_path(&self, note: &JexNote) -> PathBuf {
+        let mut names = Vec::new();
+        let mut parent_id = &note.parent_id;
+
+        //
+        // Malformed archives may contain cycles of notebooks
+        //
+
+        while let Some((title, next_parent_id)) = self.folders.get(parent_id) {
+            if names.len() == self.folders.len() {
+                break;
+            }
+
+            names.push(get_note_file_name(title));
+            parent_id = next_parent_id;
+        }
+
+        names
+            .iter()
+            .rev()
+            .collect()
+    }
+}
+
+
+/// Replaces Joplin's links to notes and resources (`:/<id>`) in a note.
+/// Links, that cannot be resolved, are kept and reported.
+/// 
+/// * `markdown` - content of a note
+/// * `resolve` - function, that returns a relative link to a note or
+///   a resource by its identifier
+/// * `warn` - function, that reports a problem with the note
+pub(crate) fn rewrite_joplin_links<R, W>(markdown: &str, mut resolve: R, mut warn: W) -> String
+where
+    R: FnMut(&str) -> Option<String>,
+    W: FnMut(String)
+{
+    let mut rewritten = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(position) = rest.find(":/") {
+        let id = rest[position + 2..]
+            .get(..ID_LENGTH)
+            .filter(|id| id.chars().all(|c| c.is_ascii_hexdigit()));
+
+        //
+        // Links are always placed into parentheses in Markdown
+        // or into quotes in HTML
+        //
+
+        let is_link = rest[..position].ends_with(['(', '"']);
+
+        match id.filter(|_id| is_link) {
+            Some(id) => {
+                rewritten.push_str(&rest[..position]);
+
+                match resolve(id) {
+                    Some(link) => rewritten.push_str(&link.replace(' ', "%20")),
+                    None => {
+                        warn(format!("unresolved link :/{}", id));
+                        rewritten.push_str(&rest[position..position + 2 + ID_LENGTH]);
+                    }
+                }
+
+                rest = &rest[position + 2 + ID_LENGTH..];
+            },
+            None => {
+                rewritten.push_str(&rest[..position + 2]);
+                rest = &rest[position + 2..];
+            }
+        }
+    }
+
+    rewritten.push_str(rest);
+    rewritten
+}
+
+
+/// Parses a Joplin item. Items consist of a title, a body and properties
+/// (`key: value` lines) separated with blank lines.
+/// 
+/// * `content` - serialized item
+fn parse_item(content: &str) -> (String, String, HashMap<String, String>) {
+    let lines: Vec<&str> = content.trim_end().split('\n').collect();
+
+    //
+    // Properties are read from the end up to the first blank line
+    //
+
+    let mut properties = HashMap::new();
+    let mut end = lines.len();
+    while end > 0 {
+        let line = lines[end - 1].trim();
+        end -= 1;
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            properties.insert(key.trim().to_owned(), value.trim().to_owned());
+        }
+    }
+
+    let title = lines
+        .first()
+        .filter(|_line| end > 0)
+        .map(|line| line.trim().to_owned())
+        .unwrap_or_default();
+
+    let body = lines
+        .get(2..end)
+        .map(|body| body.join("\n"))
+        .unwrap_or_default();
+
+    (title, body, properties)
+}
+
+
+/// Parses a time in Joplin format (ISO 8601, e.g. `2023-01-31T12:00:00.000Z`).
+/// 
+/// * `text` - text to parse
+fn parse_time(text: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(SystemTime::from)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+    use std::time::{Duration, UNIX_EPOCH};
+
+
+    /// Identifier of a notebook
+    const WORK_ID: &str = "00000000000000000000000000000001";
+
+    /// Identifier of a notebook inside of [`WORK_ID`]
+    const PROJECTS_ID: &str = "00000000000000000000000000000002";
+
+    /// Identifiers of notebooks, that contain each other
+    const CYCLE_IDS: [&str; 2] = ["0000000000000000000000000000000a", "0000000000000000000000000000000b"];
+
+    /// Identifier of a note
+    const NOTE_ID: &str = "00000000000000000000000000000010";
+
+    /// Identifier of a note inside of [`CYCLE_IDS`]
+    const LOST_NOTE_ID: &str = "00000000000000000000000000000011";
+
+    /// Identifier of a resource
+    const RESOURCE_ID: &str = "00000000000000000000000000000020";
+
+    /// Identifier of a resource without content
+    const LOST_RESOURCE_ID: &str = "00000000000000000000000000000021";
+
+
+    /// Serializes a Joplin item.
+    /// 
+    /// * `title` - title of the item
+    /// * `body` - body of the item
+    /// * `properties` - properties of the item
+    fn create_item(title: &str, body: &str, properties: &[(&str, &str)]) -> String {
+        let properties: Vec<String> = properties
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect();
+
+        format!("{}\n\n{}\n\n{}", title, body, properties.join("\n"))
+    }
+
+
+    /// Writes an export archive with files. Returns a path to the archive.
+    /// 
+    /// * `files` - paths inside of the archive and contents of files
+    fn create_archive(files: &[(String, Vec<u8>)]) -> PathBuf {
+        let path = env::temp_dir().join(format!("mm-jex-test-{}.jex", uuid::Uuid::new_v4()));
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+
+        for (file_path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            builder.append_data(&mut header, file_path, content.as_slice()).unwrap();
+        }
+
+        builder.finish().unwrap();
+        path
+    }
+
+
+    #[test]
+    fn item_is_parsed() {
+        let (title, body, properties) = parse_item(&create_item("Title", "First line\n\nsecond: line", &[("id", NOTE_ID), ("type_", NOTE_TYPE)]));
+
+        assert_eq!(title, "Title");
+        assert_eq!(body, "First line\n\nsecond: line");
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties["id"], NOTE_ID);
+        assert_eq!(properties["type_"], NOTE_TYPE);
+
+        let (title, body, properties) = parse_item("id: 1\ntype_: 5\n");
+
+        assert_eq!(title, "");
+        assert_eq!(body, "");
+        assert_eq!(properties["type_"], TAG_TYPE);
+    }
+
+
+    #[test]
+    fn joplin_links_are_rewritten() {
+        let markdown = format!("[note](:/{}) ![image](:/{}) <img src=\":/{}\"/> [lost](:/{}) see :/{} or (:/abc)",
+            NOTE_ID, RESOURCE_ID, RESOURCE_ID, WORK_ID, NOTE_ID);
+
+        let mut problems = Vec::new();
+        let rewritten = rewrite_joplin_links(&markdown,
+            |id| match id {
+                NOTE_ID => Some("folder/my note.md".to_owned()),
+                RESOURCE_ID => Some("attachments/image.png".to_owned()),
+                _ => None
+            },
+            |problem| problems.push(problem));
+
+        assert_eq!(rewritten, format!("[note](folder/my%20note.md) ![image](attachments/image.png) <img src=\"attachments/image.png\"/> [lost](:/{}) see :/{} or (:/abc)",
+            WORK_ID, NOTE_ID));
+        assert_eq!(problems, vec![format!("unresolved link :/{}", WORK_ID)]);
+    }
+
+
+    #[test]
+    fn folder_cycle_is_broken() {
+        let export = JexExport {
+            folders: HashMap::from([
+                (CYCLE_IDS[0].to_owned(), ("First".to_owned(), CYCLE_IDS[1].to_owned())),
+                (CYCLE_IDS[1].to_owned(), ("Second".to_owned(), CYCLE_IDS[0].to_owned())),
+            ]),
+            notes: Vec::new(),
+            resources: HashMap::new()
+        };
+
+        let note = JexNote {
+            id: NOTE_ID.to_owned(),
+            parent_id: CYCLE_IDS[0].to_owned(),
+            title: "Note".to_owned(),
+            body: String::new(),
+            created: None,
+            updated: None,
+            tags: Vec::new()
+        };
+
+        assert_eq!(export.get_folder_path(&note), Path::new("Second/First"));
+    }
+
+
+    #[test]
+    fn archive_is_read() {
+        let archive_path = create_archive(&[
+            (format!("{}.md", WORK_ID), create_item("Work", "", &[("id", WORK_ID), ("type_", FOLDER_TYPE)]).into_bytes()),
+            (format!("{}.md", PROJECTS_ID), create_item("Projects", "", &[("id", PROJECTS_ID), ("parent_id", WORK_ID), ("type_", FOLDER_TYPE)]).into_bytes()),
+            (format!("{}.md", NOTE_ID), create_item("Plan", &format!("![scheme](:/{})", RESOURCE_ID), &[
+                ("id", NOTE_ID),
+                ("parent_id", PROJECTS_ID),
+                ("created_time", "2023-01-31T12:00:00.000Z"),
+                ("user_updated_time", "2023-02-01T00:00:00.000Z"),
+                ("type_", NOTE_TYPE),
+            ]).into_bytes()),
+            (format!("{}.md", RESOURCE_ID), create_item("scheme.png", "", &[("id", RESOURCE_ID), ("file_extension", "png"), ("type_", RESOURCE_TYPE)]).into_bytes()),
+            (format!("resources/{}.png", RESOURCE_ID), b"image".to_vec()),
+            (format!("{}.md", LOST_RESOURCE_ID), create_item("Lost", "", &[("id", LOST_RESOURCE_ID), ("type_", RESOURCE_TYPE)]).into_bytes()),
+            (format!("{}.md", CYCLE_IDS[0]), create_item("First", "", &[("id", CYCLE_IDS[0]), ("parent_id", CYCLE_IDS[1]), ("type_", FOLDER_TYPE)]).into_bytes()),
+            (format!("{}.md", CYCLE_IDS[1]), create_item("Second", "", &[("id", CYCLE_IDS[1]), ("parent_id", CYCLE_IDS[0]), ("type_", FOLDER_TYPE)]).into_bytes()),
+            (format!("{}.md", LOST_NOTE_ID), create_item("Lost", "", &[("id", LOST_NOTE_ID), ("parent_id", CYCLE_IDS[0]), ("type_", NOTE_TYPE)]).into_bytes()),
+            ("tag.md".to_owned(), create_item("plans", "", &[("id", "t"), ("type_", TAG_TYPE)]).into_bytes()),
+            ("note_tag.md".to_owned(), create_item("", "", &[("note_id", NOTE_ID), ("tag_id", "t"), ("type_", NOTE_TAG_TYPE)]).into_bytes()),
+            ("broken.md".to_owned(), vec![0xff, 0xfe]),
+        ]);
+
+        let mut problems = Vec::new();
+        let export = JexExport::read(&archive_path, |problem| problems.push(problem)).unwrap();
+
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert_eq!(problems[0], "item \"broken.md\" is not a valid UTF-8 text");
+        assert_eq!(problems[1], "content of resource 'Lost' not found");
+
+        let note = &export.get_notes()[0];
+        assert_eq!(export.get_notes().len(), 2);
+        assert_eq!(note.get_title(), "Plan");
+        assert_eq!(note.get_body(), format!("![scheme](:/{})", RESOURCE_ID));
+        assert_eq!(note.get_tags(), ["plans"]);
+        assert_eq!(note.get_created(), Some(UNIX_EPOCH + Duration::from_secs(1675166400)));
+        assert_eq!(note.get_updated(), Some(UNIX_EPOCH + Duration::from_secs(1675209600)));
+        assert_eq!(export.get_folder_path(note), Path::new("Work/Projects"));
+
+        let lost_note = &export.get_notes()[1];
+        assert_eq!(lost_note.get_created(), None);
+        assert_eq!(export.get_folder_path(lost_note).components().count(), 4);
+
+        let resource = &export.get_resources()[RESOURCE_ID];
+        assert_eq!(resource.get_file_name(), format!("{}.png", RESOURCE_ID));
+        assert_eq!(resource.get_data(), b"image");
+
+        fs::remove_file(archive_path).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+#[allow(clippy::module_inception)]
+mod import;
+mod report;
+mod obsidian;
+mod enex;
+mod enml;
+mod jex;
+
+
+pub use self::report::{ImportReport};
+pub(crate) use self::import::{ImportedNote, rewrite_links, is_hidden, get_note_file_name};
+pub(crate) use self::obsidian::{ObsidianSettings};
+pub(crate) use self::enex::{parse_enex};
+pub(crate) use self::enml::{convert_enml};
+pub(crate) use self::jex::{JexExport, rewrite_joplin_links};
+
+
+/// Name of Obsidian's configuration folder inside of a vault
+const MM_OBSIDIAN_FOLDER: &str = ".obsidian";
+
+/// Name of Obsidian's daily notes plugin configuration file
+const MM_OBSIDIAN_DAILY_NOTES_FILE: &str = "daily-notes.json";
+
+/// Name of Obsidian's templates plugin configuration file
+const MM_OBSIDIAN_TEMPLATES_FILE: &str = "templates.json";
+
+/// Default format of Obsidian's daily notes
+const MM_OBSIDIAN_DEFAULT_DAILY_FORMAT: &str = "YYYY-MM-DD";
+
+/// Extension of imported daily notes
+const MM_DAILY_NOTE_EXTENSION: &str = ".md";
+
+/// Format of dates in Evernote export files
+const MM_ENEX_DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Name of imported notes without titles
+const MM_UNTITLED_NOTE_NAME: &str = "Untitled";
+
+/// Folder with resources' content in Joplin export archives
+const MM_JOPLIN_RESOURCES_FOLDER: &str = "resources";
+
+/// Extension of items in Joplin export archives
+const MM_JOPLIN_ITEM_EXTENSION: &str = "md";
@@ -0,0 +1,184 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json as sj;
+
+use super::ImportReport;
+use super::{
+    MM_OBSIDIAN_FOLDER,
+    MM_OBSIDIAN_DAILY_NOTES_FILE,
+    MM_OBSIDIAN_TEMPLATES_FILE,
+    MM_OBSIDIAN_DEFAULT_DAILY_FORMAT,
+    MM_DAILY_NOTE_EXTENSION,
+};
+
+
+/// Settings of an Obsidian vault, that have analogues in mm.
+#[derive(Default)]
+pub(crate) struct ObsidianSettings {
+    /// Folder with daily notes relative to vault's root
+    daily_folder: Option<String>,
+
+    /// Pattern of daily notes' paths in `strftime` format
+    daily_pattern: Option<String>,
+
+    /// Path to a template of daily notes relative to vault's root
+    daily_template: Option<PathBuf>,
+
+    /// Folder with templates relative to vault's root
+    templates_folder: Option<PathBuf>,
+}
+
+
+impl ObsidianSettings {
+    /// Loads settings from vault's configuration folder. Absent or malformed
+    /// configuration files are treated as default settings, the latter are
+    /// reported.
+    /// 
+    /// * `vault` - path to vault's root
+    /// * `report` - report to record problems into
+    pub(crate) fn load(vault: &Path, report: &mut ImportReport) -> Self {
+        let obsidian_folder = vault.join(MM_OBSIDIAN_FOLDER);
+        let mut settings = ObsidianSettings::default();
+
+        if let Some(daily_notes) = read_settings(&obsidian_folder.join(MM_OBSIDIAN_DAILY_NOTES_FILE), report) {
+            settings.daily_folder = get_string(&daily_notes, "folder")
+                .map(str::to_owned);
+
+            settings.daily_template = get_string(&daily_notes, "template")
+                .map(PathBuf::from);
+
+            let format = get_string(&daily_notes, "format")
+                .unwrap_or(MM_OBSIDIAN_DEFAULT_DAILY_FORMAT);
+
+            settings.daily_pattern = convert_moment_format(format)
+                .map(|pattern| pattern + MM_DAILY_NOTE_EXTENSION);
+
+            if settings.daily_pattern.is_none() {
+                report.warn(&obsidian_folder.join(MM_OBSIDIAN_DAILY_NOTES_FILE),
+                    format!("daily notes format '{}' is not supported", format));
+            }
+        }
+
+        if let Some(templates) = read_settings(&obsidian_folder.join(MM_OBSIDIAN_TEMPLATES_FILE), report) {
+            settings.templates_folder = get_string(&templates, "folder")
+                .map(PathBuf::from);
+        }
+
+        settings
+    }
+
+
+    /// Obtains a folder with daily notes relative to vault's root.
+    pub(crate) fn get_daily_folder(&self) -> Option<&str> {
+        self.daily_folder.as_deref()
+    }
+
+
+    /// Obtains a pattern of daily notes' paths in `strftime` format.
+    pub(crate) fn get_daily_pattern(&self) -> Option<&str> {
+        self.daily_pattern.as_deref()
+    }
+
+
+    /// Obtains a path to a template of daily notes relative to vault's root.
+    pub(crate) fn get_daily_template(&self) -> Option<&Path> {
+        self.daily_template.as_deref()
+    }
+
+
+    /// Obtains a folder with templates relative to vault's root.
+    pub(crate) fn get_templates_folder(&self) -> Option<&Path> {
+        self.templates_folder.as_deref()
+    }
+}
+
+
+/// Reads a JSON settings file. Returns `None` if the file is absent
+/// or malformed.
+/// 
+/// * `path` - path to the file
+/// * `report` - report to record problems into
+fn read_settings(path: &Path, report: &mut ImportReport) -> Option<sj::Value> {
+    let content = fs::read(path).ok()?;
+
+    match sj::from_slice(&content) {
+        Ok(settings) => Some(settings),
+        Err(err) => {
+            report.warn(path, format!("settings are ignored: {}", err));
+            None
+        }
+    }
+}
+
+
+/// Obtains a non-empty string value from settings.
+/// 
+/// * `settings` - settings object
+/// * `key` - key of the value
+fn get_string<'a>(settings: &'a sj::Value, key: &str) -> Option<&'a str> {
+    settings
+        .get(key)
+        .and_then(sj::Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+
+/// Converts a date format of Moment.js (used by Obsidian) into `strftime`
+/// format. Returns `None` if the format contains unsupported tokens.
+/// 
+/// * `format` - Moment.js format (e.g. `YYYY-MM-DD`)
+fn convert_moment_format(format: &str) -> Option<String> {
+    const TOKENS: [(&str, &str); 13] = [
+        ("YYYY", "%Y"),
+        ("YY", "%y"),
+        ("MMMM", "%B"),
+        ("MMM", "%b"),
+        ("MM", "%m"),
+        ("M", "%-m"),
+        ("DDDD", "%j"),
+        ("DD", "%d"),
+        ("D", "%-d"),
+        ("dddd", "%A"),
+        ("ddd", "%a"),
+        ("ww", "%V"),
+        ("E", "%u"),
+    ];
+
+    let mut pattern = String::with_capacity(format.len() * 2);
+    let mut rest = format;
+
+    'tokens: while let Some(c) = rest.chars().next() {
+        //
+        // Text in square brackets is escaped in Moment.js
+        //
+
+        if c == '[' {
+            let end = rest.find(']')?;
+            pattern.push_str(&rest[1..end].replace('%', "%%"));
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        for (token, replacement) in TOKENS {
+            if let Some(tail) = rest.strip_prefix(token) {
+                pattern.push_str(replacement);
+                rest = tail;
+                continue 'tokens;
+            }
+        }
+
+        if c.is_ascii_alphabetic() {
+            return None;
+        }
+
+        if c == '%' {
+            pattern.push('%');
+        }
+
+        pattern.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Some(pattern)
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+
+
+/// Describes a result of an import: imported notes and files, that were
+/// skipped or imported with problems.
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    /// Absolute paths of imported notes
+    imported: Vec<PathBuf>,
+
+    /// Number of imported attachments
+    attachments: usize,
+
+    /// Source files, that were not imported, with reasons
+    skipped: Vec<(PathBuf, String)>,
+
+    /// Problems, that did not prevent files from being imported
+    warnings: Vec<(PathBuf, String)>,
+}
+
+
+impl ImportReport {
+    /// Obtains absolute paths of imported notes.
+    pub fn get_imported(&self) -> &[PathBuf] {
+        &self.imported
+    }
+
+
+    /// Obtains number of imported attachments.
+    pub fn get_attachments_count(&self) -> usize {
+        self.attachments
+    }
+
+
+    /// Obtains source files, that were not imported, with reasons.
+    pub fn get_skipped(&self) -> &[(PathBuf, String)] {
+        &self.skipped
+    }
+
+
+    /// Obtains problems, that did not prevent files from being imported.
+    pub fn get_warnings(&self) -> &[(PathBuf, String)] {
+        &self.warnings
+    }
+
+
+    /// Records an imported note.
+    /// 
+    /// * `note_path` - absolute path to the note
+    pub(crate) fn add_imported(&mut self, note_path: PathBuf) {
+        self.imported.push(note_path);
+    }
+
+
+    /// Records an imported attachment.
+    pub(crate) fn add_attachment(&mut self) {
+        self.attachments += 1;
+    }
+
+
+    /// Records a skipped file.
+    /// 
+    /// * `path` - path to the file
+    /// * `reason` - reason of skipping
+    pub(crate) fn skip<S: Into<String>>(&mut self, path: &Path, reason: S) {
+        self.skipped.push((path.to_path_buf(), reason.into()));
+    }
+
+
+    /// Records a problem with a file.
+    /// 
+    /// * `path` - path to the file
+    /// * `problem` - description of the problem
+    pub(crate) fn warn<S: Into<String>>(&mut self, path: &Path, problem: S) {
+        self.warnings.push((path.to_path_buf(), problem.into()));
+    }
+
+
+    /// Composes a commit message with a summary of the import.
+    /// 
+    /// * `title` - first line of the message
+    pub(crate) fn to_commit_message(&self, title: &str) -> String {
+        let mut message = format!("{}\n\n{} notes and {} attachments imported", title, self.imported.len(), self.attachments);
+
+        for (header, entries) in [("Skipped", &self.skipped), ("Warnings", &self.warnings)] {
+            if entries.is_empty() {
+                continue;
+            }
+
+            message.push_str(&format!("\n\n{}:", header));
+            for (path, description) in entries {
+                message.push_str(&format!("\n- {:?}: {}", path, description));
+            }
+        }
+
+        message
+    }
+}
@@ -11,11 +11,16 @@ extern crate serde_json;
 // List of private modules
 //
 mod misc;
-mod data;
 mod cfg;
+mod editor;
+mod crypto;
+mod export;
+mod wiki;
+mod import;
 
 //
 // List of public modules
 //
 pub mod repo;
+pub mod data;
 pub mod error;
\ No newline at end of file
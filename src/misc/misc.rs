@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::error::{Error, Result};
 
@@ -16,28 +16,37 @@ pub(crate) fn create_folder_recursive<P: AsRef<Path>>(path: P) -> Result<()> {
 }
 
 
-/// Creates a directory (fails if one of its parents is absent)
+/// Recursively lists all files inside of a directory.
 /// 
-/// Actually just calls [`std::fs::create_dir`] and then converts
-/// an error to [`crate::error::Error`] if necessary.
+/// Each entry (file or directory) is passed to a filter first. Rejected 
+/// directories are not traversed and rejected files are omitted.
 /// 
-/// * `path` - path to create
-pub(crate) fn create_folder<P: AsRef<Path>>(path: P) -> Result<()> {
-    fs::create_dir(path)
-        .map_err(Error::from)
-}
+/// * `path` - path to a directory to list
+/// * `filter` - function, that decides if an entry should be processed
+pub(crate) fn list_files_recursive<P, F>(path: P, filter: F) -> Result<Vec<PathBuf>> 
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool
+{
+    let mut files = Vec::new();
+    let mut folders = vec![path.as_ref().to_path_buf()];
 
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(folder)? {
+            let entry_path = entry?.path();
+            if !filter(&entry_path) {
+                continue;
+            }
 
-/// Creates a new file in filesystem. Fails if the file already exists.
-/// 
-/// Technically it is similar to `touch` command in *nix systems. 
-/// 
-/// * `path` - path to a file to create
-pub(crate) fn touch_new_file<P: AsRef<Path>>(path: P) -> Result<()> {
-    fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(path)
-        .map_err(Error::from)
-        .map(|_file| ())
+            if entry_path.is_dir() {
+                folders.push(entry_path);
+            }
+            else {
+                files.push(entry_path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
 }
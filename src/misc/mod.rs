@@ -1,7 +1,7 @@
+#[allow(clippy::module_inception)]
 mod misc;
 
 pub(crate) use self::misc::{ 
-    create_folder, 
     create_folder_recursive, 
-    touch_new_file 
+    list_files_recursive,
 };
@@ -0,0 +1,418 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::task;
+
+use crate::data::Revision;
+use crate::import::ImportReport;
+use crate::error::{Result, Error, ErrorCategory};
+use super::repository::Repository;
+use super::shared::SharedRepository;
+use super::view::RepositoryView;
+use super::ArchiveFormat;
+
+
+/// Asynchronous interface of a repository for tokio-based applications.
+/// 
+/// Repository operations block on disk and git, so they are offloaded to
+/// tokio's blocking pool. Therefore methods MUST be called from a tokio
+/// runtime. Cloning is cheap: all clones refer to the same repository.
+/// 
+/// Operations, that are waiting for other ones to finish, are cancelled
+/// if their futures are dropped. Long operations (exports and imports)
+/// check for cancellation between files or notes and stop early. Other
+/// operations, that are already running, are completed anyway.
+/// 
+/// Repositories have no search and synchronization operations, so there
+/// are no asynchronous versions of them.
+#[derive(Clone)]
+pub struct AsyncRepository {
+    /// Repository, that is shared with the blocking pool
+    shared: SharedRepository,
+}
+
+
+impl AsyncRepository {
+    /// Wraps a repository into an asynchronous interface.
+    /// 
+    /// * `repo` - repository to wrap
+    pub fn new(repo: Repository) -> Self {
+        AsyncRepository {
+            shared: SharedRepository::new(repo)
+        }
+    }
+
+
+    /// Obtains a thread-safe handle of the repository. It may be used
+    /// for operations, that have no asynchronous versions.
+    pub fn get_shared(&self) -> &SharedRepository {
+        &self.shared
+    }
+
+
+    /// Runs a read operation in the blocking pool
+    /// (see [`SharedRepository::read`]).
+    /// 
+    /// * `operation` - function, that reads from the repository
+    pub async fn read<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(RepositoryView<'_>) -> Result<T> + Send + 'static,
+        T: Send + 'static
+    {
+        self.read_cancellable(move |repo, _check_cancelled| operation(repo)).await
+    }
+
+
+    /// Runs an operation, that changes the repository, in the blocking
+    /// pool (see [`SharedRepository::write`]).
+    /// 
+    /// * `operation` - function, that changes the repository
+    pub async fn write<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(&mut Repository) -> Result<T> + Send + 'static,
+        T: Send + 'static
+    {
+        self.write_cancellable(move |repo, _check_cancelled| operation(repo)).await
+    }
+
+
+    /// Adds a note to repository (see [`Repository::add_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to add
+    pub async fn add_note(&self, note_path: PathBuf) -> Result<()> {
+        self.write(move |repo| repo.add_note(&note_path)).await
+    }
+
+
+    /// Reads content of a note (see [`Repository::read_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to read
+    pub async fn read_note(&self, note_path: PathBuf) -> Result<Vec<u8>> {
+        self.read(move |repo| repo.read_note(&note_path)).await
+    }
+
+
+    /// Writes content of a note and adds it to repository
+    /// (see [`Repository::write_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to write
+    /// * `content` - new content of the note
+    pub async fn write_note(&self, note_path: PathBuf, content: Vec<u8>) -> Result<()> {
+        self.write(move |repo| repo.write_note(&note_path, &content)).await
+    }
+
+
+    /// Adds a folder to repository (see [`Repository::add_folder`]).
+    /// 
+    /// * `folder_path` - absolute path to a folder to add
+    pub async fn add_folder(&self, folder_path: PathBuf) -> Result<()> {
+        self.write(move |repo| repo.add_folder(&folder_path)).await
+    }
+
+
+    /// Removes a note from repository (see [`Repository::remove_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to remove
+    pub async fn remove_note(&self, note_path: PathBuf) -> Result<()> {
+        self.write(move |repo| repo.remove_note(&note_path)).await
+    }
+
+
+    /// Moves (or renames) a note inside of repository
+    /// (see [`Repository::move_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to move
+    /// * `new_note_path` - absolute destination path (MUST NOT exist)
+    pub async fn move_note(&self, note_path: PathBuf, new_note_path: PathBuf) -> Result<()> {
+        self.write(move |repo| repo.move_note(&note_path, &new_note_path)).await
+    }
+
+
+    /// Looks for a note by its stable identifier
+    /// (see [`Repository::find_by_id`]).
+    /// 
+    /// * `id` - identifier of a note
+    pub async fn find_by_id(&self, id: String) -> Result<Option<PathBuf>> {
+        self.read(move |repo| repo.find_by_id(&id)).await
+    }
+
+
+    /// Obtains a history of changes (see [`Repository::get_history`]).
+    /// 
+    /// * `note_path` - absolute path to a note (pass `None` to obtain
+    ///   history of the whole repository)
+    pub async fn get_history(&self, note_path: Option<PathBuf>) -> Result<Vec<Revision>> {
+        self.read(move |repo| repo.get_history(note_path.as_deref())).await
+    }
+
+
+    /// Exports the repository into a static HTML site
+    /// (see [`Repository::export_html`]).
+    /// 
+    /// * `destination` - path to a folder for the site
+    pub async fn export_html(&self, destination: PathBuf) -> Result<()> {
+        self.read_cancellable(move |repo, check_cancelled| repo.export_html_with(&destination, check_cancelled)).await
+    }
+
+
+    /// Exports the repository into an archive
+    /// (see [`Repository::export_archive`]).
+    /// 
+    /// * `archive_path` - path to an archive to create
+    /// * `format` - format of the archive
+    /// * `with_history` - `true` to include full history
+    pub async fn export_archive(&self, archive_path: PathBuf, format: ArchiveFormat, with_history: bool) -> Result<()> {
+        self.read_cancellable(move |repo, check_cancelled| repo.export_archive_with(&archive_path, format, with_history, check_cancelled)).await
+    }
+
+
+    /// Imports notes from an Evernote export file
+    /// (see [`Repository::import_enex`]). Nothing is imported if the
+    /// import is cancelled.
+    /// 
+    /// * `enex_path` - path to an export file
+    /// * `folder_path` - absolute path to a folder to import notes into
+    pub async fn import_enex(&self, enex_path: PathBuf, folder_path: PathBuf) -> Result<ImportReport> {
+        self.write_cancellable(move |repo, check_cancelled| repo.import_enex_with(&enex_path, &folder_path, check_cancelled)).await
+    }
+
+
+    /// Imports notes from a Joplin export archive
+    /// (see [`Repository::import_jex`]). Notes imported before cancellation
+    /// remain in the repository.
+    /// 
+    /// * `jex_path` - path to an export archive
+    /// * `folder_path` - absolute path to a folder to import notes into
+    pub async fn import_jex(&self, jex_path: PathBuf, folder_path: PathBuf) -> Result<ImportReport> {
+        self.write_cancellable(move |repo, check_cancelled| repo.import_jex_with(&jex_path, &folder_path, check_cancelled)).await
+    }
+
+
+    /// Runs a read operation in the blocking pool. The operation receives
+    /// a function, that fails if the operation is cancelled.
+    /// 
+    /// * `operation` - function, that reads from the repository
+    async fn read_cancellable<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(RepositoryView<'_>, &dyn Fn() -> Result<()>) -> Result<T> + Send + 'static,
+        T: Send + 'static
+    {
+        let shared = self.shared.clone();
+        self.spawn(move |cancelled| shared.read(|repo| {
+            let check = || check_cancelled(&cancelled);
+
+            check()?;
+            operation(repo, &check)
+        })).await
+    }
+
+
+    /// Runs an operation, that changes the repository, in the blocking
+    /// pool. The operation receives a function, that fails if the
+    /// operation is cancelled.
+    /// 
+    /// * `operation` - function, that changes the repository
+    async fn write_cancellable<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(&mut Repository, &dyn Fn() -> Result<()>) -> Result<T> + Send + 'static,
+        T: Send + 'static
+    {
+        let shared = self.shared.clone();
+        self.spawn(move |cancelled| shared.write(|repo| {
+            let check = || check_cancelled(&cancelled);
+
+            check()?;
+            operation(repo, &check)
+        })).await
+    }
+
+
+    /// Runs a blocking function in tokio's blocking pool. The function
+    /// receives a flag, that is set if the returned future is dropped.
+    /// 
+    /// * `function` - function to run
+    async fn spawn<F, T>(&self, function: F) -> Result<T>
+    where
+        F: FnOnce(Arc<AtomicBool>) -> Result<T> + Send + 'static,
+        T: Send + 'static
+    {
+        let guard = CancelOnDrop {
+            cancelled: Arc::new(AtomicBool::new(false))
+        };
+
+        let cancelled = guard.cancelled.clone();
+        task::spawn_blocking(move || function(cancelled))
+            .await
+            .map_err(|error| Error::from_string(format!("repository operation failed: {}", error), ErrorCategory::Repo))?
+    }
+}
+
+
+impl From<Repository> for AsyncRepository {
+    fn from(repo: Repository) -> Self {
+        AsyncRepository::new(repo)
+    }
+}
+
+
+impl From<SharedRepository> for AsyncRepository {
+    fn from(shared: SharedRepository) -> Self {
+        AsyncRepository {
+            shared
+        }
+    }
+}
+
+
+/// Sets a cancellation flag when dropped. The flag is set after an
+/// operation completes as well, but it is not checked anymore then.
+struct CancelOnDrop {
+    /// Flag to set
+    cancelled: Arc<AtomicBool>,
+}
+
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+
+/// Fails if an operation was cancelled.
+/// 
+/// * `cancelled` - cancellation flag of the operation
+fn check_cancelled(cancelled: &AtomicBool) -> Result<()> {
+    match cancelled.load(Ordering::SeqCst) {
+        true => Err(Error::from_string("repository operation cancelled", ErrorCategory::Repo)),
+        false => Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, thread};
+    use std::sync::mpsc;
+
+
+    /// Evernote export file with two notes
+    const ENEX: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <en-export>\n\
+        <note><title>First</title><content><![CDATA[<en-note><div>One</div></en-note>]]></content></note>\n\
+        <note><title>Second</title><content><![CDATA[<en-note><div>Two</div></en-note>]]></content></note>\n\
+        </en-export>\n";
+
+
+    /// Writes an Evernote export file. Returns a path to the file.
+    fn create_enex() -> PathBuf {
+        let enex_path = env::temp_dir().join(format!("mm-enex-test-{}.enex", uuid::Uuid::new_v4()));
+        fs::write(&enex_path, ENEX).unwrap();
+
+        enex_path
+    }
+
+
+    #[tokio::test]
+    async fn operations_run_in_blocking_pool() {
+        let repo = AsyncRepository::new(Repository::in_memory("async", "Tester", "tester@example.com").unwrap());
+        let workdir = repo.read(|view| view.get_workdir().map(PathBuf::from)).await.unwrap();
+        let note_path = workdir.join("note.md");
+        let moved_path = workdir.join("moved.md");
+
+        repo.write_note(note_path.clone(), b"# Note".to_vec()).await.unwrap();
+        assert_eq!(repo.read_note(note_path.clone()).await.unwrap(), b"# Note");
+
+        //
+        // Clones refer to the same repository
+        //
+
+        let clone = repo.clone();
+        let id = clone.read(move |view| view.get_note_id(&note_path)).await.unwrap().unwrap();
+        clone.move_note(workdir.join("note.md"), moved_path.clone()).await.unwrap();
+
+        assert_eq!(repo.find_by_id(id).await.unwrap(), Some(moved_path.clone()));
+        assert_eq!(repo.get_history(None).await.unwrap().len(), 3);
+
+        let enex_path = create_enex();
+        let report = repo.import_enex(enex_path.clone(), workdir.join("evernote")).await.unwrap();
+        assert_eq!(report.get_imported().len(), 2);
+
+        repo.remove_note(moved_path.clone()).await.unwrap();
+        assert!(repo.read_note(moved_path).await.is_err());
+
+        fs::remove_file(enex_path).unwrap();
+    }
+
+
+    #[test]
+    fn dropped_guard_cancels_operation() {
+        let guard = CancelOnDrop {
+            cancelled: Arc::new(AtomicBool::new(false))
+        };
+
+        let cancelled = guard.cancelled.clone();
+        assert!(check_cancelled(&cancelled).is_ok());
+
+        drop(guard);
+        assert!(check_cancelled(&cancelled).is_err());
+    }
+
+
+    #[tokio::test]
+    async fn dropped_import_is_cancelled() {
+        let repo = AsyncRepository::new(Repository::in_memory("async", "Tester", "tester@example.com").unwrap());
+        let workdir = repo.read(|view| view.get_workdir().map(PathBuf::from)).await.unwrap();
+        let revisions = repo.get_history(None).await.unwrap().len();
+
+        //
+        // Another thread holds the repository, so the import
+        // waits for it in the blocking pool
+        //
+
+        let (locked_sender, locked) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        let shared = repo.get_shared().clone();
+        let holder = thread::spawn(move || shared.write(|_repo| {
+            locked_sender.send(()).unwrap();
+            released.recv().unwrap();
+            Ok(())
+        }));
+
+        locked.recv().unwrap();
+
+        //
+        // The result is sent only if the import is run, so the
+        // receiver is disconnected if it is cancelled
+        //
+
+        let enex_path = create_enex();
+        let (result_sender, result) = mpsc::channel();
+        let import = {
+            let repo = repo.clone();
+            let enex_path = enex_path.clone();
+
+            task::spawn(async move {
+                repo.write_cancellable(move |repo, check_cancelled| {
+                    let report = repo.import_enex_with(&enex_path, &workdir.join("evernote"), check_cancelled);
+                    result_sender.send(report.is_ok()).unwrap();
+                    Ok(())
+                }).await
+            })
+        };
+
+        task::yield_now().await;
+        import.abort();
+        assert!(import.await.unwrap_err().is_cancelled());
+
+        release.send(()).unwrap();
+        holder.join().unwrap().unwrap();
+
+        assert!(result.recv().is_err());
+        assert_eq!(repo.get_history(None).await.unwrap().len(), revisions);
+
+        fs::remove_file(enex_path).unwrap();
+    }
+}
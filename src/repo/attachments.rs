@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use sha2::{Sha256, Digest};
+
+use crate::data::Storage;
+use crate::error::Result;
+use super::helpers;
+use super::{MM_ATTACHMENTS_FOLDER};
+
+
+/// Extensions of attachments, that are rendered as images
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+
+/// Computes a content-addressed name of an attachment: SHA-256 hash of
+/// the file's content with original extension (if any).
+/// 
+/// * `content` - content of a file
+/// * `file_path` - path to a file to compute name for
+pub(super) fn get_attachment_name(content: &[u8], file_path: &Path) -> String {
+    let hash = Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some(extension) => format!("{}.{}", hash, extension.to_lowercase()),
+        None => hash
+    }
+}
+
+
+/// Composes a Markdown link to an attachment, that is relative to a note.
+/// Images are composed as embedded images.
+/// 
+/// * `note_relative_path` - relative to working directory path to a note
+/// * `attachment_name` - name of an attachment inside of attachments folder
+/// * `title` - text of the link
+pub(super) fn compose_link(note_relative_path: &Path, attachment_name: &str, title: &str) -> String {
+    //
+    // Each folder between working directory and a note
+    // requires to go one level up
+    //
+
+    let depth = note_relative_path
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+
+    let target = format!("{}{}/{}", "../".repeat(depth), MM_ATTACHMENTS_FOLDER, attachment_name);
+
+    if is_image(attachment_name) {
+        format!("![{}]({})", title, target)
+    }
+    else {
+        format!("[{}]({})", title, target)
+    }
+}
+
+
+/// Collects notes, that reference each attachment. Attachments without
+/// references are present in the result with empty list of notes.
+/// 
+/// Since attachments are content-addressed, a note is considered to
+/// reference an attachment if its text contains attachment's name.
+/// All paths are relative to storage's root.
+/// 
+/// * `storage` - storage with repository's files
+/// * `read_note` - function, that reads (and decrypts if necessary) a note
+pub(super) fn collect_references<F>(storage: &dyn Storage, read_note: F) -> Result<HashMap<PathBuf, Vec<PathBuf>>>
+where
+    F: Fn(&Path) -> Result<Vec<u8>>
+{
+    let mut references: HashMap<PathBuf, Vec<PathBuf>> = storage.list(Path::new(MM_ATTACHMENTS_FOLDER))?
+        .into_iter()
+        .map(|attachment| (attachment, Vec::new()))
+        .collect();
+
+    if references.is_empty() {
+        return Ok(references);
+    }
+
+    //
+    // Now scan all notes. Binary files are skipped, because
+    // they cannot reference anything. Reading errors are not
+    // skipped though: an unreadable (e.g. encrypted) note may
+    // reference an attachment
+    //
+
+    let notes = storage.list(Path::new(""))?
+        .into_iter()
+        .filter(|path| helpers::is_note_path(path));
+
+    for note in notes {
+        let content = match String::from_utf8(read_note(&note)?) {
+            Ok(content) => content,
+            _ => continue
+        };
+
+        for (attachment, notes) in references.iter_mut() {
+            let referenced = attachment
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| content.contains(name));
+
+            if referenced {
+                notes.push(note.clone());
+            }
+        }
+    }
+
+    Ok(references)
+}
+
+
+/// Checks if an attachment is an image by its extension.
+/// 
+/// * `attachment_name` - name of an attachment
+fn is_image(attachment_name: &str) -> bool {
+    Path::new(attachment_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::MemoryStorage;
+
+
+    #[test]
+    fn name_depends_on_content_only() {
+        let name = get_attachment_name(b"content", Path::new("folder/Image.PNG"));
+
+        assert_eq!(name, "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73.png");
+        assert_eq!(get_attachment_name(b"content", Path::new("other.png")), name);
+        assert_eq!(get_attachment_name(b"content", Path::new("file")), name.trim_end_matches(".png"));
+    }
+
+
+    #[test]
+    fn links_are_relative_to_note() {
+        assert_eq!(compose_link(Path::new("note.md"), "hash.png", "image"), "![image](attachments/hash.png)");
+        assert_eq!(compose_link(Path::new("a/b/note.md"), "hash.pdf", "doc"), "[doc](../../attachments/hash.pdf)");
+    }
+
+
+    #[test]
+    fn references_are_collected() {
+        let storage = MemoryStorage::new();
+        let (used, unused) = (Path::new("attachments/used.png"), Path::new("attachments/unused.pdf"));
+        storage.write(used, b"image").unwrap();
+        storage.write(unused, b"document").unwrap();
+        storage.write(Path::new("note.md"), b"![image](attachments/used.png)").unwrap();
+        storage.write(Path::new("binary.bin"), &[0xff, 0xfe, 0x00]).unwrap();
+        storage.write(Path::new(".mm/mm_ids.json"), b"used.png").unwrap();
+
+        let references = collect_references(&storage, |note| storage.read(note)).unwrap();
+
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[used], vec![PathBuf::from("note.md")]);
+        assert!(references[unused].is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+
+
+/// Change of a repository, that is made through the library.
+/// 
+/// Paths are absolute. Repositories have no synchronization operations
+/// yet, so there is no event for them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RepositoryEvent {
+    /// A note was created (or added to repository for the first time)
+    NoteCreated(PathBuf),
+
+    /// Content or metadata of a note was changed
+    NoteUpdated(PathBuf),
+
+    /// A note was removed (moved to trash)
+    NoteDeleted(PathBuf),
+
+    /// A note was moved or renamed
+    NoteMoved {
+        /// Previous path of the note
+        from: PathBuf,
+
+        /// New path of the note
+        to: PathBuf,
+    },
+
+    /// A folder was added
+    FolderAdded(PathBuf),
+
+    /// Repository's configuration was changed
+    ConfigChanged,
+}
+
+
+/// Identifier of a subscription to repository's events
+/// (see [`crate::repo::Repository::subscribe`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+
+/// Receiver of events.
+#[derive(Clone)]
+pub(super) enum Observer {
+    /// Function, that is called for each event
+    Callback(Arc<dyn Fn(&RepositoryEvent) + Send + Sync>),
+
+    /// Sending end of a channel
+    Channel(Sender<RepositoryEvent>),
+}
+
+
+/// Collection of subscribers to repository's events.
+#[derive(Default)]
+pub(super) struct Observers {
+    /// Identifier of the next subscription
+    next_id: u64,
+
+    /// Subscribers by their subscription identifiers
+    observers: Vec<(SubscriptionId, Observer)>,
+}
+
+
+impl Observers {
+    /// Subscribes a callback. Returns an identifier of the subscription.
+    /// 
+    /// * `callback` - function, that is called for each event
+    pub(super) fn add_callback(&mut self, callback: Arc<dyn Fn(&RepositoryEvent) + Send + Sync>) -> SubscriptionId {
+        self.add(Observer::Callback(callback))
+    }
+
+
+    /// Subscribes a channel. Returns an identifier of the subscription.
+    /// 
+    /// * `sender` - sending end of a channel
+    pub(super) fn add_channel(&mut self, sender: Sender<RepositoryEvent>) -> SubscriptionId {
+        self.add(Observer::Channel(sender))
+    }
+
+
+    /// Removes a subscription. Returns `true` if it existed.
+    /// 
+    /// * `id` - identifier of the subscription
+    pub(super) fn remove(&mut self, id: SubscriptionId) -> bool {
+        let count = self.observers.len();
+        self.observers.retain(|(observer_id, _observer)| *observer_id != id);
+
+        self.observers.len() != count
+    }
+
+
+    /// Copies current subscribers, so events may be delivered without
+    /// holding the collection (see [`deliver`]).
+    pub(super) fn snapshot(&self) -> Vec<(SubscriptionId, Observer)> {
+        self.observers.clone()
+    }
+
+
+    /// Adds a subscriber.
+    /// 
+    /// * `observer` - subscriber to add
+    fn add(&mut self, observer: Observer) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+
+        self.observers.push((id, observer));
+        id
+    }
+}
+
+
+/// Delivers an event to subscribers. Returns identifiers of channels,
+/// whose receiving ends are dropped, so they may be unsubscribed.
+/// 
+/// * `observers` - subscribers (see [`Observers::snapshot`])
+/// * `event` - event to deliver
+pub(super) fn deliver(observers: &[(SubscriptionId, Observer)], event: &RepositoryEvent) -> Vec<SubscriptionId> {
+    let mut closed = Vec::new();
+    for (id, observer) in observers {
+        match observer {
+            Observer::Callback(callback) => callback(event),
+            Observer::Channel(sender) => {
+                if sender.send(event.clone()).is_err() {
+                    closed.push(*id);
+                }
+            }
+        }
+    }
+
+    closed
+}
@@ -1,15 +1,23 @@
-use std::path::{Path, PathBuf};
+use std::path::{Path, PathBuf, Component};
 
-use crate::{data, misc, cfg};
-use crate::error::{Result, Error, ErrorCategory};
+use crate::data;
+use crate::error::{Result, Error, ErrorCategory, ErrorKind};
 use super::{
     MM_REPOS_SUBFOLDER, 
     MM_MAIN_REPO_NAME,
     MM_CONFIG_FILE, 
+    MM_LOCAL_CONFIG_FILE,
     MM_CONFIG_FOLDER,
-    MM_GIT_HEAD_REF,
-    MM_INITIAL_COMMIT_MESSAGE,
-    MM_DEFAULT_COMMIT_MESSAGE,
+    MM_IDS_FILE,
+    MM_METADATA_FILE,
+    MM_TRASH_FILE,
+    MM_TRASH_FOLDER,
+    MM_KEY_CHECK_FILE,
+    MM_TEMPLATES_FOLDER,
+    MM_LOCKS_FOLDER,
+    MM_ATTACHMENTS_FOLDER,
+    MM_HISTORY_BUNDLE_FILE,
+    MM_VCS_FOLDER,
 };
 
 
@@ -27,7 +35,7 @@ pub(super) fn is_repos_folder_present() -> bool {
     //
 
     get_repos_folder()
-        .map_or(false, |path| path.exists())
+        .is_some_and(|path| path.exists())
 }
 
 
@@ -40,138 +48,138 @@ pub(super) fn get_repo_path(repo_name: &Option<&str>) -> Option<PathBuf> {
 }
 
 
-/// Returns path to a repository's working directory
-/// 
-/// * `repo` - repository to get working directory of
-pub(super) fn get_workdir(repo: &git2::Repository) -> Result<&Path> {
-    repo.workdir()
-        .ok_or(Error::from_string("cannot get working directory", ErrorCategory::Git))
+/// Returns path to a repository's configuration folder relative 
+/// to working directory
+pub(super) fn get_config_directory() -> PathBuf {
+    PathBuf::from(MM_CONFIG_FOLDER)
 }
 
 
-/// Returns path to a repository's configuration folder 
-/// 
-/// * `repo` - repository to get working directory of
-pub(super) fn get_config_girectory(repo: &git2::Repository) -> Result<PathBuf> {
-    get_workdir(&repo)
-        .map(|workdir| workdir.join(MM_CONFIG_FOLDER))
+/// Returns path to a repository's configuation file relative 
+/// to working directory
+pub(super) fn get_config_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_CONFIG_FILE)
 }
 
 
-/// Returns path to a repository's configuation file
-/// 
-/// * `repo` - repository to get working directory of
-pub(super) fn get_config_file(repo: &git2::Repository) -> Result<PathBuf> {
-    get_config_girectory(&repo)
-        .map(|config_folder| config_folder.join(MM_CONFIG_FILE))
+/// Returns path to a repository's local configuration file relative 
+/// to working directory
+pub(super) fn get_local_config_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_LOCAL_CONFIG_FILE)
 }
 
 
-/// Stages and commits all specified files.
-/// 
-/// * `repo` - reference to git repository instance
-/// * `config` - reference to configuration instance
-/// * `pathspecs` - list of files to be committed (paths 
-///                 MUST be relative to the repository's 
-///                 working directory)
-/// * `message` - optional commit message (default one is 
-///               [`super::MM_DEFAULT_COMMIT_MESSAGE`])
-pub(super) fn commit_files<T, I>(repo: &git2::Repository, config: &cfg::Config, pathspecs: I, message: Option<&str>) -> Result<()>
-where
-    T: git2::IntoCString,
-    I: IntoIterator<Item = T>
-{
-    //
-    // First of all, we need to stage all the changes
-    //
-
-    let mut index = repo.index()?;
-    
-    index.add_all(pathspecs, git2::IndexAddOption::DEFAULT, None)?;
-    index.write()?;
+/// Returns path to a repository's note identifiers file relative 
+/// to working directory
+pub(super) fn get_ids_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_IDS_FILE)
+}
 
-    let tree_oid = index.write_tree()?;
 
-    //
-    // Now let's create a commit
-    //
+/// Returns path to a repository's notes metadata file relative 
+/// to working directory
+pub(super) fn get_metadata_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_METADATA_FILE)
+}
 
-    let tree = repo.find_tree(tree_oid)?;
-    let author = git2::Signature::now(config.query_name()?, config.query_email()?)?;
-    let message = message
-        .unwrap_or(MM_DEFAULT_COMMIT_MESSAGE);
 
-    //
-    // Well... Here I need a slice with references for parents container,
-    // hence I MUST do it in the following scary way :(
-    // Error is ignored intentionally
-    //
+/// Returns path to a repository's trash index file relative 
+/// to working directory
+pub(super) fn get_trash_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_TRASH_FILE)
+}
 
-    let head = repo
-        .refname_to_id(MM_GIT_HEAD_REF)
-        .and_then(|head_oid| repo.find_commit(head_oid))
-        .ok();
 
-    let head_holder;
-    let parents = match head.as_ref() {
-        Some(head) => {
-            head_holder = [head];
-            &head_holder[..]
-        },
-        _ => &[]
-    };
+/// Returns path to a repository's templates folder relative 
+/// to working directory
+pub(super) fn get_templates_directory() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_TEMPLATES_FOLDER)
+}
 
-    repo.commit(Some(MM_GIT_HEAD_REF), &author, &author, message, &tree, parents)?;
 
-    Ok(())
+/// Returns path to a repository's trash folder relative 
+/// to working directory
+pub(super) fn get_trash_directory() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_TRASH_FOLDER)
 }
 
 
-/// Open or create a git repository by its path.
-/// 
-/// * `path` - path to the repository's directory
-pub(super) fn open_or_create_repository(path: PathBuf) -> Result<git2::Repository> {
-    git2::Repository::open(path.to_owned())
-        .or_else(|_error| create_repository(&path))
-        .map_err(Error::from)
+/// Returns path to a repository's lock files folder relative 
+/// to working directory
+pub(super) fn get_locks_directory() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_LOCKS_FOLDER)
 }
 
 
-/// Creates a git repository with a configuration file
-/// 
-/// * `path` - path to the repository's directory
-fn create_repository(path: &Path) -> Result<git2::Repository> {
-    //
-    // Fistly create a repository
-    //
+/// Returns path to a repository's passphrase check file relative 
+/// to working directory
+pub(super) fn get_key_check_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_KEY_CHECK_FILE)
+}
 
-    let repo = git2::Repository::init(path)?;
 
-    //
-    // Now we need to create a configuration file inside of a special folder
-    //
+/// Returns path to a history bundle inside of archives
+pub(super) fn get_history_bundle_file() -> PathBuf {
+    Path::new(MM_CONFIG_FOLDER).join(MM_HISTORY_BUNDLE_FILE)
+}
 
-    let config_folder = get_config_girectory(&repo)?;
-    misc::create_folder(&config_folder)?;
 
-    let config_file = get_config_file(&repo)?; 
-    misc::touch_new_file(&config_file)?;
+/// Checks if a path may be exported into an archive or imported from one.
+/// Files of this machine (lock files and local configuration) are never
+/// archived. Version control's internal files are never taken from an
+/// archive, because they may run arbitrary programs (e.g. hooks).
+/// 
+/// * `relative_path` - path relative to working directory
+pub(super) fn is_archived_path(relative_path: &Path) -> bool {
+    let is_vcs = relative_path
+        .components()
+        .any(|component| component.as_os_str().eq_ignore_ascii_case(MM_VCS_FOLDER));
+
+    !is_vcs
+        && !relative_path.starts_with(get_locks_directory())
+        && relative_path != get_local_config_file()
+        && relative_path != get_history_bundle_file()
+}
 
-    let config = cfg::Config::new()?;
-    config.save(&config_file)?;
 
-    //
-    // To commit config file I need to convert its path to the relative one
-    //
+/// Checks if a path inside of working directory may contain notes, i.e. it
+/// is not inside of configuration or attachments folders.
+/// 
+/// * `relative_path` - path relative to working directory
+pub(super) fn is_note_path(relative_path: &Path) -> bool {
+    match relative_path.components().next() {
+        Some(component) => {
+            let component = component.as_os_str();
+            component != MM_CONFIG_FOLDER && component != MM_ATTACHMENTS_FOLDER
+        },
+        _ => true
+    }
+}
 
-    let workdir = get_workdir(&repo)?;
-    let relative_path = config_file.strip_prefix(workdir)?;
 
-    commit_files(&repo, &config, [relative_path].iter(), Some(MM_INITIAL_COMMIT_MESSAGE))?;
+/// Checks if a path is relative and consists of plain names only, so it
+/// cannot lead outside of working directory. Paths read from repository's
+/// files may come from elsewhere (e.g. an imported archive) and must be
+/// checked before use.
+/// 
+/// * `relative_path` - path to check
+pub(super) fn is_plain_relative_path(relative_path: &Path) -> bool {
+    relative_path.components().next().is_some()
+        && relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
 
-    //
-    // Done for now!
-    //
 
-    Ok(repo)
+/// Checks if a name of a template refers to a file inside of templates
+/// folder.
+/// 
+/// * `template_name` - name of a template
+pub(super) fn check_template_name(template_name: &str) -> Result<()> {
+    if !is_plain_relative_path(Path::new(template_name)) {
+        return Err(Error::from_string(format!("invalid template name '{}'", template_name), ErrorCategory::Repo)
+            .with_kind(ErrorKind::InvalidPath));
+    }
+
+    Ok(())
 }
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use serde_json as sj;
+use uuid::Uuid;
+
+use crate::data::Storage;
+use crate::error::{Result, Error, ErrorCategory};
+use super::helpers;
+
+
+/// Map of stable note identifiers.
+/// 
+/// Each note in a repository gets a unique identifier, that doesn't depend
+/// on a note's location. Map is stored as a JSON object inside of repository's
+/// configuration folder, where keys are identifiers and values are paths
+/// relative to the repository's working directory.
+pub(super) struct NoteIds {
+    /// Internal JSON object (identifier -> relative path)
+    internal: sj::Map<String, sj::Value>,
+}
+
+
+impl NoteIds {
+    /// Loads identifiers map from a file. Absent file is treated as an
+    /// empty map.
+    /// 
+    /// * `storage` - storage to read the file from
+    /// * `ids_file` - relative path to a file to read identifiers from
+    pub(super) fn load(storage: &dyn Storage, ids_file: &Path) -> Result<Self> {
+        if !storage.exists(ids_file) {
+            return Ok(NoteIds { internal: sj::Map::new() });
+        }
+
+        let file_content = storage.read(ids_file)?;
+
+        match sj::from_slice(file_content.as_slice())? {
+            sj::Value::Object(internal) => Ok(NoteIds { internal }),
+            _ => Err(Error::from_string("note identifiers map is not a JSON object", ErrorCategory::Repo))
+        }
+    }
+
+
+    /// Checks if an identifiers map is well-formed, i.e. all paths in it
+    /// are plain relative paths, that may contain notes.
+    /// 
+    /// * `content` - content of an identifiers file
+    pub(super) fn check(content: &[u8]) -> Result<()> {
+        let is_note_path = |value: &sj::Value| value
+            .as_str()
+            .map(Path::new)
+            .is_some_and(|path| helpers::is_plain_relative_path(path) && helpers::is_note_path(path));
+
+        match sj::from_slice(content)? {
+            sj::Value::Object(internal) if internal.values().all(is_note_path) => Ok(()),
+            _ => Err(Error::from_string("note identifiers map contains invalid paths", ErrorCategory::Repo))
+        }
+    }
+
+
+    /// Saves identifiers map into a file.
+    /// 
+    /// * `storage` - storage to write the file to
+    /// * `ids_file` - relative path to a file to write identifiers to
+    pub(super) fn save(&self, storage: &dyn Storage, ids_file: &Path) -> Result<()> {
+        storage.write(ids_file, &sj::to_vec_pretty(&self.internal)?)
+    }
+
+
+    /// Returns an identifier of a note. Assigns a new one if the note
+    /// has no identifier yet.
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    pub(super) fn assign(&mut self, relative_path: &Path) -> String {
+        if let Some(id) = self.id_of(relative_path) {
+            return id.to_owned();
+        }
+
+        let id = Uuid::new_v4().to_string();
+        self.internal.insert(id.clone(), sj::Value::String(Self::path_to_string(relative_path)));
+
+        id
+    }
+
+
+    /// Inserts an identifier for a note. Previous path of the identifier
+    /// (if any) is replaced.
+    /// 
+    /// * `id` - identifier to insert
+    /// * `relative_path` - relative to working directory path to the note
+    pub(super) fn insert(&mut self, id: &str, relative_path: &Path) {
+        self.internal.insert(id.to_owned(), sj::Value::String(Self::path_to_string(relative_path)));
+    }
+
+
+    /// Looks for a note's relative path by its identifier.
+    /// 
+    /// * `id` - identifier of a note
+    pub(super) fn find(&self, id: &str) -> Option<PathBuf> {
+        self.internal
+            .get(id)
+            .and_then(sj::Value::as_str)
+            .map(PathBuf::from)
+    }
+
+
+    /// Looks for an identifier of a note.
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    pub(super) fn id_of(&self, relative_path: &Path) -> Option<&str> {
+        let path = Self::path_to_string(relative_path);
+
+        self.internal
+            .iter()
+            .find(|(_id, value)| value.as_str() == Some(path.as_str()))
+            .map(|(id, _value)| id.as_str())
+    }
+
+
+    /// Removes a note from the map. Returns its identifier if the note
+    /// was present.
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    pub(super) fn remove(&mut self, relative_path: &Path) -> Option<String> {
+        let id = self.id_of(relative_path)?.to_owned();
+        self.internal.remove(&id);
+
+        Some(id)
+    }
+
+
+    /// Removes a note or all notes inside of a folder from the map.
+    /// Returns relative paths and identifiers of removed notes.
+    /// 
+    /// * `relative_path` - relative to working directory path to a note
+    ///   or a folder
+    pub(super) fn remove_under(&mut self, relative_path: &Path) -> Vec<(PathBuf, String)> {
+        let mut removed = Vec::new();
+
+        self.internal.retain(|id, value| {
+            match value.as_str().map(Path::new) {
+                Some(path) if path.starts_with(relative_path) => {
+                    removed.push((path.to_path_buf(), id.clone()));
+                    false
+                },
+                _ => true
+            }
+        });
+
+        removed
+    }
+
+
+    /// Updates paths after a note or a folder is moved. All notes located
+    /// inside of a moved folder are updated too.
+    /// 
+    /// * `from` - old relative path
+    /// * `to` - new relative path
+    pub(super) fn rename(&mut self, from: &Path, to: &Path) {
+        for value in self.internal.values_mut() {
+            let new_path = value
+                .as_str()
+                .map(Path::new)
+                .and_then(|path| path.strip_prefix(from).ok())
+                .map(|rest| to.join(rest));
+
+            if let Some(new_path) = new_path {
+                *value = sj::Value::String(Self::path_to_string(&new_path));
+            }
+        }
+    }
+
+
+    /// Removes all identifiers, whose notes are not accepted by a predicate.
+    /// Returns `true` if the map was changed.
+    /// 
+    /// * `predicate` - function, that checks a relative path of a note
+    pub(super) fn retain<F>(&mut self, mut predicate: F) -> bool
+    where
+        F: FnMut(&Path) -> bool
+    {
+        let len = self.internal.len();
+
+        self.internal.retain(|_id, value| {
+            value.as_str()
+                .map(Path::new)
+                .is_some_and(&mut predicate)
+        });
+
+        len != self.internal.len()
+    }
+
+
+    /// Converts a relative path into a platform independent string (with
+    /// forward slashes), so the map may be shared between systems.
+    fn path_to_string(relative_path: &Path) -> String {
+        relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use chrono::format::{Item, StrftimeItems};
+
+use crate::error::{Result, Error, ErrorCategory, ErrorKind};
+use super::helpers;
+
+
+/// Checks if a folder may contain journal entries, i.e. it is located
+/// inside of working directory and outside of configuration folder.
+/// Journal settings may come from elsewhere (e.g. a committed
+/// configuration or an imported vault), so they are checked before use.
+/// 
+/// * `folder` - folder relative to working directory
+pub(super) fn check_folder(folder: &str) -> Result<()> {
+    let folder = Path::new(folder);
+    if !helpers::is_plain_relative_path(folder) || !helpers::is_note_path(folder) {
+        return Err(Error::from_string("invalid journal folder", ErrorCategory::Config)
+            .with_kind(ErrorKind::InvalidPath)
+            .with_path(folder));
+    }
+
+    Ok(())
+}
+
+
+/// Checks if a pattern of journal entries' paths is a valid `strftime`
+/// format, that produces paths inside of journal folder.
+/// 
+/// * `pattern` - pattern of paths
+pub(super) fn check_pattern(pattern: &str) -> Result<()> {
+    let date = NaiveDate::from_ymd_opt(2000, 12, 31)
+        .expect("sample date is valid");
+
+    format_path(pattern, date)
+        .map(|_path| ())
+}
+
+
+/// Composes a path to a journal entry relative to working directory.
+/// 
+/// * `folder` - folder with journal entries relative to working directory
+/// * `pattern` - pattern of entries' paths (in `strftime` format)
+/// * `date` - date of the entry
+pub(super) fn get_entry_path(folder: &str, pattern: &str, date: NaiveDate) -> Result<PathBuf> {
+    check_folder(folder)?;
+
+    Ok(Path::new(folder).join(format_path(pattern, date)?))
+}
+
+
+/// Formats a date according to a pattern and checks, that the result is
+/// a relative path with plain names only.
+/// 
+/// * `pattern` - pattern of paths (in `strftime` format)
+/// * `date` - date to format
+fn format_path(pattern: &str, date: NaiveDate) -> Result<PathBuf> {
+    let invalid_pattern = || Error::from_string(format!("invalid journal pattern '{}'", pattern), ErrorCategory::Config)
+        .with_kind(ErrorKind::InvalidPath);
+
+    let items: Vec<Item> = StrftimeItems::new(pattern).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(invalid_pattern());
+    }
+
+    //
+    // Valid specifiers may still require time or time zone,
+    // which a date lacks, so formatting itself may fail
+    //
+
+    let mut path = String::new();
+    write!(path, "{}", date.format_with_items(items.iter()))
+        .map_err(|_| invalid_pattern())?;
+
+    let path = PathBuf::from(path);
+    if !helpers::is_plain_relative_path(&path) {
+        return Err(invalid_pattern());
+    }
+
+    Ok(path)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn entry_path_is_composed() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+
+        assert_eq!(get_entry_path("journal", "%Y/%m/%Y-%m-%d.md", date).unwrap(), Path::new("journal/2024/01/2024-01-02.md"));
+        assert_eq!(get_entry_path("notes/daily", "%d.%m.%Y.md", date).unwrap(), Path::new("notes/daily/02.01.2024.md"));
+    }
+
+
+    #[test]
+    fn unsafe_folders_are_rejected() {
+        for folder in ["", "..", "../..", "/tmp", "journal/../..", ".mm", ".mm/templates", "attachments"] {
+            assert_eq!(check_folder(folder).unwrap_err().get_kind(), ErrorKind::InvalidPath, "{}", folder);
+        }
+
+        assert!(check_folder("journal/daily").is_ok());
+    }
+
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        for pattern in ["", "%Q.md", "%Y-%", "%H.md", "%z.md", "../%Y.md", "%Y/../../%d.md", "/%Y.md"] {
+            assert_eq!(check_pattern(pattern).unwrap_err().get_kind(), ErrorKind::InvalidPath, "{}", pattern);
+        }
+
+        assert!(check_pattern("%Y/%B/%e.md").is_ok());
+    }
+}
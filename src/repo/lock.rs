@@ -0,0 +1,311 @@
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use uuid::Uuid;
+
+use crate::misc;
+use crate::error::{Result, Error, ErrorCategory, ErrorKind};
+
+
+/// Extension of lock files of shared holders
+const SHARED_EXTENSION: &str = "shared";
+
+/// Extension of lock files of exclusive holders
+const EXCLUSIVE_EXTENSION: &str = "exclusive";
+
+
+/// Mode of access to a repository on disk.
+#[derive(PartialEq, Eq, Clone, Debug, Copy)]
+pub enum LockMode {
+    /// Read-only access, any number of processes may hold the lock
+    /// at the same time
+    Shared,
+
+    /// Read-write access, only one holder is allowed
+    Exclusive,
+}
+
+
+/// Advisory lock of a repository, that is shared between processes.
+/// 
+/// Each holder creates a file named after its process identifier inside
+/// of a lock folder. The file is removed when the lock is dropped. Files
+/// of dead processes are considered stale and removed. Liveness of
+/// processes is checked on unix only, elsewhere stale files must be
+/// removed by hand.
+pub(super) struct RepositoryLock {
+    /// Path to holder's lock file
+    path: PathBuf,
+
+    /// Mode of the lock
+    mode: LockMode,
+}
+
+
+impl RepositoryLock {
+    /// Acquires a lock. Fails with [`ErrorKind::Locked`] if there are
+    /// conflicting holders.
+    /// 
+    /// * `lock_folder` - absolute path to a folder with lock files
+    /// * `mode` - mode of the lock
+    pub(super) fn acquire(lock_folder: &Path, mode: LockMode) -> Result<Self> {
+        misc::create_folder_recursive(lock_folder)?;
+        check_conflicts(lock_folder, mode, None)?;
+
+        let extension = match mode {
+            LockMode::Shared => SHARED_EXTENSION,
+            LockMode::Exclusive => EXCLUSIVE_EXTENSION
+        };
+
+        let path = lock_folder.join(format!("{}-{}.{}", process::id(), Uuid::new_v4(), extension));
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        let lock = RepositoryLock {
+            path,
+            mode
+        };
+
+        //
+        // Another process may have acquired a conflicting lock
+        // between the check and creation of the file. Each of them
+        // checks again after publishing its file, so at least one
+        // of them backs off (the lock is released at drop)
+        //
+
+        check_conflicts(lock_folder, mode, Some(&lock.path))?;
+
+        Ok(lock)
+    }
+
+
+    /// Obtains a mode of the lock.
+    pub(super) fn get_mode(&self) -> LockMode {
+        self.mode
+    }
+}
+
+
+impl Drop for RepositoryLock {
+    fn drop(&mut self) {
+        //
+        // A missing file means, that the lock was considered
+        // stale by someone, so there is nothing to do anyway
+        //
+
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+
+/// Checks if there are holders, that conflict with a lock in a specific
+/// mode. Stale lock files are removed.
+/// 
+/// * `lock_folder` - absolute path to a folder with lock files
+/// * `mode` - mode of a lock to acquire
+/// * `own` - path to a lock file of the caller (if it exists already)
+fn check_conflicts(lock_folder: &Path, mode: LockMode, own: Option<&Path>) -> Result<()> {
+    for entry in fs::read_dir(lock_folder)? {
+        let path = entry?.path();
+        if own.is_some_and(|own| own == path) {
+            continue;
+        }
+
+        let (pid, holder_mode) = match parse_lock_file(&path) {
+            Some(holder) => holder,
+            None => continue
+        };
+
+        if !is_process_alive(pid) {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+
+        if mode == LockMode::Exclusive || holder_mode == LockMode::Exclusive {
+            return Err(Error::from_string(format!("repository is locked by process {}", pid), ErrorCategory::Repo)
+                .with_kind(ErrorKind::Locked)
+                .with_path(path));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Parses a name of a lock file. Returns holder's process identifier
+/// and mode of the lock.
+/// 
+/// * `path` - path to a lock file
+fn parse_lock_file(path: &Path) -> Option<(u32, LockMode)> {
+    let mode = match path.extension()?.to_str()? {
+        SHARED_EXTENSION => LockMode::Shared,
+        EXCLUSIVE_EXTENSION => LockMode::Exclusive,
+        _ => return None
+    };
+
+    let pid = path
+        .file_stem()?
+        .to_str()?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()?;
+
+    Some((pid, mode))
+}
+
+
+/// Checks if a process is running.
+/// 
+/// * `pid` - identifier of a process
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    if pid == process::id() {
+        return true;
+    }
+
+    //
+    // Signal 0 only checks if the process exists. Processes
+    // of other users can't be signaled, but they exist
+    //
+
+    //
+    // Zero and negative identifiers address process groups,
+    // so they never belong to a live holder
+    //
+
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return false
+    };
+
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+
+/// Checks if a process is running. There is no portable way to check
+/// it here, so all holders are considered alive and lock files of
+/// crashed processes must be removed by hand.
+/// 
+/// * `pid` - identifier of a process
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+
+    #[test]
+    #[cfg(unix)]
+    fn invalid_pids_are_not_alive() {
+        assert!(is_process_alive(process::id()));
+        assert!(!is_process_alive(0));
+        assert!(!is_process_alive(u32::MAX));
+        assert!(!is_process_alive(i32::MAX as u32 + 1));
+    }
+
+
+    #[test]
+    #[cfg(unix)]
+    fn stale_lock_is_removed() {
+        let lock_folder = env::temp_dir().join(format!("mm-lock-test-{}", Uuid::new_v4()));
+        misc::create_folder_recursive(&lock_folder).unwrap();
+
+        let stale = lock_folder.join(format!("0-{}.{}", Uuid::new_v4(), EXCLUSIVE_EXTENSION));
+        fs::write(&stale, b"").unwrap();
+
+        let lock = RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).unwrap();
+        assert!(!stale.exists());
+        assert!(RepositoryLock::acquire(&lock_folder, LockMode::Shared).is_err());
+
+        drop(lock);
+        fs::remove_dir_all(lock_folder).unwrap();
+    }
+
+
+    #[test]
+    #[cfg(unix)]
+    fn lock_of_dead_process_is_removed() {
+        let lock_folder = env::temp_dir().join(format!("mm-lock-test-{}", Uuid::new_v4()));
+        misc::create_folder_recursive(&lock_folder).unwrap();
+
+        //
+        // A finished and reaped child is surely dead
+        //
+
+        let mut child = process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+
+        let stale = lock_folder.join(format!("{}-{}.{}", child.id(), Uuid::new_v4(), SHARED_EXTENSION));
+        fs::write(&stale, b"").unwrap();
+
+        let lock = RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).unwrap();
+        assert!(!stale.exists());
+
+        drop(lock);
+        fs::remove_dir_all(lock_folder).unwrap();
+    }
+
+
+    #[test]
+    #[cfg(unix)]
+    fn lock_with_invalid_pid_is_ignored() {
+        let lock_folder = env::temp_dir().join(format!("mm-lock-test-{}", Uuid::new_v4()));
+        misc::create_folder_recursive(&lock_folder).unwrap();
+
+        let unparsable = lock_folder.join(format!("pid-{}.{}", Uuid::new_v4(), EXCLUSIVE_EXTENSION));
+        let overflowing = lock_folder.join(format!("{}-{}.{}", u64::MAX, Uuid::new_v4(), EXCLUSIVE_EXTENSION));
+        let out_of_range = lock_folder.join(format!("{}-{}.{}", i32::MAX as u32 + 1, Uuid::new_v4(), EXCLUSIVE_EXTENSION));
+        for path in [&unparsable, &overflowing, &out_of_range] {
+            fs::write(path, b"").unwrap();
+        }
+
+        let lock = RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).unwrap();
+
+        //
+        // Unparsable names are not lock files at all, so they are kept
+        //
+
+        assert!(unparsable.exists());
+        assert!(overflowing.exists());
+        assert!(!out_of_range.exists());
+
+        drop(lock);
+        fs::remove_dir_all(lock_folder).unwrap();
+    }
+
+
+    #[test]
+    fn shared_lock_blocks_exclusive_one() {
+        let lock_folder = env::temp_dir().join(format!("mm-lock-test-{}", Uuid::new_v4()));
+
+        let first = RepositoryLock::acquire(&lock_folder, LockMode::Shared).unwrap();
+        let second = RepositoryLock::acquire(&lock_folder, LockMode::Shared).unwrap();
+        assert_eq!(second.get_mode(), LockMode::Shared);
+
+        let err = RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).err().unwrap();
+        assert_eq!(err.get_kind(), ErrorKind::Locked);
+        assert_eq!(fs::read_dir(&lock_folder).unwrap().count(), 2);
+
+        drop(first);
+        assert!(RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).is_err());
+
+        drop(second);
+        let lock = RepositoryLock::acquire(&lock_folder, LockMode::Exclusive).unwrap();
+        assert_eq!(lock.get_mode(), LockMode::Exclusive);
+
+        drop(lock);
+        assert_eq!(fs::read_dir(&lock_folder).unwrap().count(), 0);
+        fs::remove_dir_all(lock_folder).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json as sj;
+
+use crate::data::Storage;
+use crate::error::{Result, Error, ErrorCategory};
+
+
+/// Key of note's tags
+const TAGS_KEY: &str = "tags";
+
+/// Key of note's creation time (seconds since UNIX epoch)
+const CREATED_KEY: &str = "created";
+
+/// Key of note's modification time (seconds since UNIX epoch)
+const UPDATED_KEY: &str = "updated";
+
+
+/// Describes additional information about a note, that is not
+/// a part of note's content.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoteMetadata {
+    /// Tags of the note (sorted, without duplicates)
+    tags: Vec<String>,
+
+    /// Time of note's creation (if known)
+    created: Option<SystemTime>,
+
+    /// Time of note's last modification (if known)
+    updated: Option<SystemTime>,
+}
+
+
+impl NoteMetadata {
+    /// Constructs note's metadata.
+    /// 
+    /// * `tags` - tags of the note
+    /// * `created` - time of note's creation (if known)
+    /// * `updated` - time of note's last modification (if known)
+    pub fn new(mut tags: Vec<String>, created: Option<SystemTime>, updated: Option<SystemTime>) -> Self {
+        tags.sort();
+        tags.dedup();
+
+        NoteMetadata {
+            tags,
+            created,
+            updated
+        }
+    }
+
+
+    /// Obtains tags of the note.
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+
+    /// Obtains a time of note's creation (if known).
+    pub fn get_created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+
+    /// Obtains a time of note's last modification (if known).
+    pub fn get_updated(&self) -> Option<SystemTime> {
+        self.updated
+    }
+
+
+    /// Checks if there is no information about the note.
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.created.is_none() && self.updated.is_none()
+    }
+
+
+    /// Parses metadata from JSON.
+    fn from_json(value: &sj::Value) -> Self {
+        let tags = value
+            .get(TAGS_KEY)
+            .and_then(sj::Value::as_array)
+            .map(|tags| tags.iter().filter_map(sj::Value::as_str).map(str::to_owned).collect())
+            .unwrap_or_default();
+
+        let time = |key| value
+            .get(key)
+            .and_then(sj::Value::as_u64)
+            .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+
+        NoteMetadata::new(tags, time(CREATED_KEY), time(UPDATED_KEY))
+    }
+
+
+    /// Converts metadata to JSON. Unknown values are omitted.
+    fn to_json(&self) -> sj::Value {
+        let mut value = sj::Map::new();
+
+        if !self.tags.is_empty() {
+            value.insert(TAGS_KEY.to_owned(), sj::json!(self.tags));
+        }
+
+        let seconds = |time: SystemTime| time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        if let Some(created) = self.created {
+            value.insert(CREATED_KEY.to_owned(), sj::Value::from(seconds(created)));
+        }
+
+        if let Some(updated) = self.updated {
+            value.insert(UPDATED_KEY.to_owned(), sj::Value::from(seconds(updated)));
+        }
+
+        sj::Value::Object(value)
+    }
+}
+
+
+/// Map of notes' metadata.
+/// 
+/// Metadata is bound to stable note identifiers, so it follows a note when
+/// the note is moved. Map is stored as a JSON object inside of repository's
+/// configuration folder, where keys are identifiers.
+pub(super) struct Metadata {
+    /// Internal JSON object (identifier -> metadata)
+    internal: sj::Map<String, sj::Value>,
+}
+
+
+impl Metadata {
+    /// Loads metadata map from a file. Absent file is treated as an
+    /// empty map.
+    /// 
+    /// * `storage` - storage to read the file from
+    /// * `metadata_file` - relative path to a file to read metadata from
+    pub(super) fn load(storage: &dyn Storage, metadata_file: &Path) -> Result<Self> {
+        if !storage.exists(metadata_file) {
+            return Ok(Metadata { internal: sj::Map::new() });
+        }
+
+        let file_content = storage.read(metadata_file)?;
+
+        match sj::from_slice(file_content.as_slice())? {
+            sj::Value::Object(internal) => Ok(Metadata { internal }),
+            _ => Err(Error::from_string("notes metadata map is not a JSON object", ErrorCategory::Repo))
+        }
+    }
+
+
+    /// Checks if a metadata map is well-formed, i.e. metadata of each
+    /// note is a JSON object.
+    /// 
+    /// * `content` - content of a metadata file
+    pub(super) fn check(content: &[u8]) -> Result<()> {
+        match sj::from_slice(content)? {
+            sj::Value::Object(internal) if internal.values().all(sj::Value::is_object) => Ok(()),
+            _ => Err(Error::from_string("notes metadata map is malformed", ErrorCategory::Repo))
+        }
+    }
+
+
+    /// Saves metadata map into a file.
+    /// 
+    /// * `storage` - storage to write the file to
+    /// * `metadata_file` - relative path to a file to write metadata to
+    pub(super) fn save(&self, storage: &dyn Storage, metadata_file: &Path) -> Result<()> {
+        storage.write(metadata_file, &sj::to_vec_pretty(&self.internal)?)
+    }
+
+
+    /// Returns metadata of a note. Notes without metadata get an
+    /// empty one.
+    /// 
+    /// * `id` - identifier of a note
+    pub(super) fn get(&self, id: &str) -> NoteMetadata {
+        self.internal
+            .get(id)
+            .map(NoteMetadata::from_json)
+            .unwrap_or_default()
+    }
+
+
+    /// Sets metadata of a note. Empty metadata is removed from the map.
+    /// 
+    /// * `id` - identifier of a note
+    /// * `metadata` - new metadata of the note
+    pub(super) fn set(&mut self, id: &str, metadata: &NoteMetadata) {
+        if metadata.is_empty() {
+            self.internal.remove(id);
+        }
+        else {
+            self.internal.insert(id.to_owned(), metadata.to_json());
+        }
+    }
+
+
+    /// Removes metadata of notes, whose identifiers are not accepted by
+    /// a predicate. Returns `true` if the map was changed.
+    /// 
+    /// * `predicate` - function, that checks an identifier of a note
+    pub(super) fn retain<F>(&mut self, mut predicate: F) -> bool
+    where
+        F: FnMut(&str) -> bool
+    {
+        let len = self.internal.len();
+
+        self.internal.retain(|id, _value| predicate(id));
+
+        len != self.internal.len()
+    }
+}
@@ -1,8 +1,37 @@
 mod helpers;
+mod ids;
+mod attachments;
+mod trash;
+mod metadata;
+mod templates;
+mod journal;
+mod lock;
+mod events;
+mod shared;
+mod view;
+mod operation;
+mod undo;
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "watcher")]
+mod watcher;
 mod repository;
 
 
 pub use self::repository::{Repository};
+pub use self::trash::{TrashEntry};
+pub use self::metadata::{NoteMetadata};
+pub use self::lock::{LockMode};
+pub use self::events::{RepositoryEvent, SubscriptionId};
+pub use self::shared::{SharedRepository};
+pub use self::view::{RepositoryView};
+pub use self::operation::{Operation, OperationKind, ChangeAction, FileChange, Activity};
+#[cfg(feature = "async")]
+pub use self::asynchronous::{AsyncRepository};
+#[cfg(feature = "watcher")]
+pub use self::watcher::{RepositoryWatcher};
+pub use crate::export::{ArchiveFormat};
+pub use crate::import::{ImportReport};
 
 
 /// Path to repositories relative to mm's data folder.
@@ -17,11 +46,57 @@ const MM_CONFIG_FOLDER: &str = ".mm";
 /// Name of configuration file, that is located in each repository
 const MM_CONFIG_FILE: &str = "mm_config.json";
 
-/// Reference being updated in git repositories
-const MM_GIT_HEAD_REF: &str = "HEAD";
+/// Name of a file with local configuration, that is never committed
+const MM_LOCAL_CONFIG_FILE: &str = "mm_local.json";
+
+/// Name of note identifiers file, that is located in each repository
+const MM_IDS_FILE: &str = "mm_ids.json";
+
+/// Name of notes metadata file, that is located in each repository
+const MM_METADATA_FILE: &str = "mm_metadata.json";
+
+/// Name of trash index file, that is located in each repository
+const MM_TRASH_FILE: &str = "mm_trash.json";
+
+/// Name of a folder with removed notes inside of configuration folder
+const MM_TRASH_FOLDER: &str = "trash";
+
+/// Name of a file, that contains a check value for passphrase verification
+const MM_KEY_CHECK_FILE: &str = "mm_key_check";
+
+/// Name of a folder with lock files of processes, that opened a repository,
+/// inside of configuration folder
+const MM_LOCKS_FOLDER: &str = "locks";
+
+/// Name of a file with history bundle inside of configuration folder of
+/// archives (never written to working directory)
+const MM_HISTORY_BUNDLE_FILE: &str = "mm_history.bundle";
+
+/// Name of version control's internal folder, that is never taken
+/// from archives
+const MM_VCS_FOLDER: &str = ".git";
+
+/// Name of a folder with note templates inside of configuration folder
+const MM_TEMPLATES_FOLDER: &str = "templates";
+
+/// Format of `{{date}}` variable in templates
+const MM_TEMPLATE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Name of a folder with content-addressed attachments
+const MM_ATTACHMENTS_FOLDER: &str = "attachments";
+
+/// Message for a commit with files imported from an archive
+const MM_IMPORT_COMMIT_MESSAGE: &str = "chore: imported from archive";
 
 /// Message for initial commit
 const MM_INITIAL_COMMIT_MESSAGE: &str = "chore: initial commit";
 
-/// Default commit message (if no one is specified)
-const MM_DEFAULT_COMMIT_MESSAGE: &str = "chore: several files updated";
+/// Commit message trailer with a name of an operation, that made a commit
+const MM_OPERATION_TRAILER: &str = "Mm-Operation";
+
+/// Commit message trailer with a change of a file made by an operation
+const MM_CHANGE_TRAILER: &str = "Mm-Change";
+
+/// Commit message trailer with a revision of an operation, that is undone
+/// or redone by a commit
+const MM_TARGET_TRAILER: &str = "Mm-Target";
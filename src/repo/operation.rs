@@ -0,0 +1,691 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde_json as sj;
+
+use crate::data::Revision;
+use super::helpers;
+use super::{MM_OPERATION_TRAILER, MM_CHANGE_TRAILER, MM_TARGET_TRAILER, MM_ATTACHMENTS_FOLDER};
+
+
+/// Key of change's action
+const ACTION_KEY: &str = "action";
+
+/// Key of changed note's path
+const PATH_KEY: &str = "path";
+
+/// Key of moved note's previous path
+const FROM_KEY: &str = "from";
+
+/// Key of note's stable identifier
+const NOTE_ID_KEY: &str = "id";
+
+
+/// Kind of an operation, that the library records as a commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OperationKind {
+    /// A note was added
+    Add,
+
+    /// Content of a note was changed
+    Update,
+
+    /// A note was moved to trash
+    Remove,
+
+    /// A note was restored from trash
+    Restore,
+
+    /// A note was moved or renamed
+    Move,
+
+    /// Metadata of a note was changed
+    Metadata,
+
+    /// Notes were encrypted
+    Encrypt,
+
+    /// Notes were decrypted
+    Decrypt,
+
+    /// An attachment was added
+    Attach,
+
+    /// Notes were permanently removed from trash
+    Purge,
+
+    /// Unreferenced attachments were removed
+    CleanUp,
+
+    /// Note identifiers were reconciled with working directory
+    Reconcile,
+
+    /// Configuration was changed
+    Config,
+
+    /// Notes were imported
+    Import,
+
+    /// Changes made outside of the library were committed
+    External,
+
+    /// Another operation was undone
+    Undo,
+
+    /// An undone operation was applied again
+    Redo,
+}
+
+
+/// Names of operation kinds in commit messages
+const OPERATION_NAMES: [(OperationKind, &str); 17] = [
+    (OperationKind::Add, "add"),
+    (OperationKind::Update, "update"),
+    (OperationKind::Remove, "remove"),
+    (OperationKind::Restore, "restore"),
+    (OperationKind::Move, "move"),
+    (OperationKind::Metadata, "metadata"),
+    (OperationKind::Encrypt, "encrypt"),
+    (OperationKind::Decrypt, "decrypt"),
+    (OperationKind::Attach, "attach"),
+    (OperationKind::Purge, "purge"),
+    (OperationKind::CleanUp, "clean-up"),
+    (OperationKind::Reconcile, "reconcile"),
+    (OperationKind::Config, "config"),
+    (OperationKind::Import, "import"),
+    (OperationKind::External, "external"),
+    (OperationKind::Undo, "undo"),
+    (OperationKind::Redo, "redo"),
+];
+
+
+impl OperationKind {
+    /// Checks if operations of this kind may be undone. Only operations,
+    /// that add, change, move or remove notes, are reversible.
+    pub fn is_reversible(self) -> bool {
+        matches!(self, OperationKind::Add
+            | OperationKind::Update
+            | OperationKind::Remove
+            | OperationKind::Restore
+            | OperationKind::Move
+            | OperationKind::External)
+    }
+}
+
+
+/// Action, that was applied to a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChangeAction {
+    /// A file was added
+    Added,
+
+    /// A file was modified
+    Modified,
+
+    /// A file was moved from another path
+    Moved {
+        /// Previous path of the file
+        from: PathBuf,
+    },
+
+    /// A file was removed
+    Removed,
+}
+
+
+/// Describes a change of a single file within an operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileChange {
+    /// Action, that was applied to the file
+    action: ChangeAction,
+
+    /// Path to the file (new path for moved files)
+    path: PathBuf,
+
+    /// Stable identifier of a note (if the file is a note with identifier)
+    note_id: Option<String>,
+}
+
+
+impl FileChange {
+    /// Constructs a change description.
+    /// 
+    /// * `action` - action, that was applied to the file
+    /// * `path` - path to the file
+    /// * `note_id` - stable identifier of a note (if any)
+    pub(crate) fn new(action: ChangeAction, path: &Path, note_id: Option<&str>) -> Self {
+        FileChange {
+            action,
+            path: path.to_path_buf(),
+            note_id: note_id.map(str::to_owned)
+        }
+    }
+
+
+    /// Obtains an action, that was applied to the file.
+    pub fn get_action(&self) -> &ChangeAction {
+        &self.action
+    }
+
+
+    /// Obtains a path to the file (new path for moved files).
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+
+    /// Obtains a stable identifier of a note (if any).
+    pub fn get_note_id(&self) -> Option<&str> {
+        self.note_id.as_deref()
+    }
+
+
+    /// Returns a change, that reverts this one.
+    pub(crate) fn invert(&self) -> Self {
+        let (action, path) = match &self.action {
+            ChangeAction::Added => (ChangeAction::Removed, self.path.clone()),
+            ChangeAction::Modified => (ChangeAction::Modified, self.path.clone()),
+            ChangeAction::Moved { from } => (ChangeAction::Moved { from: self.path.clone() }, from.clone()),
+            ChangeAction::Removed => (ChangeAction::Added, self.path.clone())
+        };
+
+        FileChange {
+            action,
+            path,
+            note_id: self.note_id.clone()
+        }
+    }
+
+
+    /// Parses a change from JSON.
+    fn from_json(value: &sj::Value) -> Option<Self> {
+        let action = match value.get(ACTION_KEY)?.as_str()? {
+            "added" => ChangeAction::Added,
+            "modified" => ChangeAction::Modified,
+            "moved" => ChangeAction::Moved {
+                from: PathBuf::from(value.get(FROM_KEY)?.as_str()?)
+            },
+            "removed" => ChangeAction::Removed,
+            _ => return None
+        };
+
+        Some(FileChange {
+            action,
+            path: PathBuf::from(value.get(PATH_KEY)?.as_str()?),
+            note_id: value.get(NOTE_ID_KEY).and_then(sj::Value::as_str).map(str::to_owned)
+        })
+    }
+
+
+    /// Converts a change to JSON.
+    fn to_json(&self) -> sj::Value {
+        let mut value = sj::json!({
+            ACTION_KEY: match self.action {
+                ChangeAction::Added => "added",
+                ChangeAction::Modified => "modified",
+                ChangeAction::Moved { .. } => "moved",
+                ChangeAction::Removed => "removed"
+            },
+            PATH_KEY: path_to_string(&self.path),
+        });
+
+        if let ChangeAction::Moved { from } = &self.action {
+            value[FROM_KEY] = sj::Value::String(path_to_string(from));
+        }
+
+        if let Some(note_id) = &self.note_id {
+            value[NOTE_ID_KEY] = sj::Value::String(note_id.clone());
+        }
+
+        value
+    }
+
+
+    /// Returns the same change with paths resolved against a folder.
+    /// 
+    /// * `root` - folder to resolve paths against
+    fn resolve(mut self, root: &Path) -> Self {
+        if let ChangeAction::Moved { from } = &mut self.action {
+            *from = root.join(&*from);
+        }
+
+        self.path = root.join(&self.path);
+        self
+    }
+}
+
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.action {
+            ChangeAction::Added => write!(f, "added {}", self.path.display()),
+            ChangeAction::Modified => write!(f, "modified {}", self.path.display()),
+            ChangeAction::Moved { from } => write!(f, "moved {} to {}", from.display(), self.path.display()),
+            ChangeAction::Removed => write!(f, "removed {}", self.path.display())
+        }
+    }
+}
+
+
+/// Describes an operation, that the library recorded as a commit.
+/// 
+/// Operations are stored in commit messages as git trailers, so they can
+/// be parsed back from history (see [`Operation::parse`]):
+/// 
+/// ```text
+/// chore: moved a.md to notes/b.md
+/// 
+/// Mm-Operation: move
+/// Mm-Change: {"action":"moved","from":"a.md","id":"...","path":"notes/b.md"}
+/// ```
+/// 
+/// Undo and redo operations refer to revisions of their targets with
+/// `Mm-Target` trailer.
+/// 
+/// Paths in commit messages are relative to working directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operation {
+    /// Kind of the operation
+    kind: OperationKind,
+
+    /// Changes of files made by the operation
+    changes: Vec<FileChange>,
+
+    /// Revision of an operation, that is undone or redone
+    target: Option<String>,
+}
+
+
+impl Operation {
+    /// Constructs an operation without changes of files.
+    /// 
+    /// * `kind` - kind of the operation
+    pub(crate) fn new(kind: OperationKind) -> Self {
+        Operation {
+            kind,
+            changes: Vec::new(),
+            target: None
+        }
+    }
+
+
+    /// Returns the same operation with one more change of a file.
+    /// 
+    /// * `action` - action, that was applied to the file
+    /// * `path` - path to the file relative to working directory
+    /// * `note_id` - stable identifier of a note (if any)
+    pub(crate) fn with_change(mut self, action: ChangeAction, path: &Path, note_id: Option<&str>) -> Self {
+        self.changes.push(FileChange::new(action, path, note_id));
+        self
+    }
+
+
+    /// Returns the same operation with more changes of files.
+    /// 
+    /// * `changes` - changes of files
+    pub(crate) fn with_changes<I>(mut self, changes: I) -> Self
+    where
+        I: IntoIterator<Item = FileChange>
+    {
+        self.changes.extend(changes);
+        self
+    }
+
+
+    /// Returns the same operation with a revision of an operation, that
+    /// it undoes or redoes.
+    /// 
+    /// * `revision_id` - identifier of a revision
+    pub(crate) fn with_target(mut self, revision_id: &str) -> Self {
+        self.target = Some(revision_id.to_owned());
+        self
+    }
+
+
+    /// Parses an operation from a commit message. Returns `None` for
+    /// messages without operation trailers (e.g. commits made outside
+    /// of the library) and for operations with paths, that may lead
+    /// outside of working directory or into internal folders.
+    /// 
+    /// * `message` - commit message
+    pub fn parse(message: &str) -> Option<Self> {
+        //
+        // Trailers are located in the last paragraph
+        //
+
+        let trailers = message
+            .trim_end()
+            .rsplit("\n\n")
+            .next()?;
+
+        let mut kind = None;
+        let mut changes = Vec::new();
+        let mut target = None;
+
+        for line in trailers.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return None
+            };
+
+            if key == MM_OPERATION_TRAILER {
+                kind = OPERATION_NAMES
+                    .iter()
+                    .find(|(_kind, name)| *name == value)
+                    .map(|(kind, _name)| *kind);
+            }
+            else if key == MM_CHANGE_TRAILER {
+                changes.push(FileChange::from_json(&sj::from_str(value).ok()?)?);
+            }
+            else if key == MM_TARGET_TRAILER {
+                target = Some(value.to_owned());
+            }
+        }
+
+        //
+        // Changes are applied to files on undo and redo, so
+        // messages are not trusted: notes must stay inside of
+        // working directory, attachments inside of their folder
+        //
+
+        let kind = kind?;
+        let is_valid_path = |path: &Path| helpers::is_plain_relative_path(path) && match kind {
+            OperationKind::Attach | OperationKind::CleanUp => path.starts_with(MM_ATTACHMENTS_FOLDER),
+            _ => helpers::is_note_path(path)
+        };
+
+        for change in &changes {
+            if let ChangeAction::Moved { from } = &change.action {
+                if !is_valid_path(from) {
+                    return None;
+                }
+            }
+
+            if !is_valid_path(&change.path) {
+                return None;
+            }
+        }
+
+        Some(Operation {
+            kind,
+            changes,
+            target
+        })
+    }
+
+
+    /// Obtains a kind of the operation.
+    pub fn get_kind(&self) -> OperationKind {
+        self.kind
+    }
+
+
+    /// Obtains changes of files made by the operation.
+    pub fn get_changes(&self) -> &[FileChange] {
+        &self.changes
+    }
+
+
+    /// Obtains an identifier of a revision with an operation, that this
+    /// one undoes or redoes (`None` for other kinds of operations).
+    pub fn get_target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+
+    /// Composes a commit message with the operation's trailers.
+    /// 
+    /// * `summary` - first line of the message (`None` means a summary
+    ///   composed from the operation)
+    pub(crate) fn compose_message(&self, summary: Option<&str>) -> String {
+        let mut message = match summary {
+            Some(summary) => summary.to_owned(),
+            None => format!("chore: {}", self)
+        };
+
+        message.push_str(&format!("\n\n{}: {}", MM_OPERATION_TRAILER, self.get_name()));
+        if let Some(target) = &self.target {
+            message.push_str(&format!("\n{}: {}", MM_TARGET_TRAILER, target));
+        }
+
+        for change in &self.changes {
+            message.push_str(&format!("\n{}: {}", MM_CHANGE_TRAILER, change.to_json()));
+        }
+
+        message
+    }
+
+
+    /// Returns the same operation with paths resolved against a folder.
+    /// 
+    /// * `root` - folder to resolve paths against
+    pub(crate) fn resolve(mut self, root: &Path) -> Self {
+        self.changes = self.changes
+            .into_iter()
+            .map(|change| change.resolve(root))
+            .collect();
+
+        self
+    }
+
+
+    /// Obtains a name of operation's kind in commit messages.
+    fn get_name(&self) -> &'static str {
+        OPERATION_NAMES
+            .iter()
+            .find(|(kind, _name)| *kind == self.kind)
+            .map_or("unknown", |(_kind, name)| name)
+    }
+}
+
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths = || self.changes
+            .iter()
+            .map(|change| change.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let changes = || self.changes
+            .iter()
+            .map(FileChange::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match self.kind {
+            OperationKind::Add | OperationKind::Update | OperationKind::Remove | OperationKind::Move | OperationKind::External => write!(f, "{}", changes()),
+            OperationKind::Restore => write!(f, "restored {} from trash", paths()),
+            OperationKind::Metadata => write!(f, "updated metadata of {}", paths()),
+            OperationKind::Encrypt => write!(f, "encrypted {}", paths()),
+            OperationKind::Decrypt => write!(f, "decrypted {}", paths()),
+            OperationKind::Attach => write!(f, "attached {}", paths()),
+            OperationKind::Purge => write!(f, "removed {} notes from trash", self.changes.len()),
+            OperationKind::CleanUp => write!(f, "removed {} unreferenced attachments", self.changes.len()),
+            OperationKind::Reconcile => write!(f, "reconciled note identifiers"),
+            OperationKind::Config => write!(f, "changed configuration"),
+            OperationKind::Import => write!(f, "imported {} notes", self.changes.len()),
+            OperationKind::Undo => write!(f, "undo ({})", changes()),
+            OperationKind::Redo => write!(f, "redo ({})", changes())
+        }
+    }
+}
+
+
+/// An entry of repository's activity log: an operation together with
+/// a revision, that recorded it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Activity {
+    /// Revision, that recorded the operation
+    revision: Revision,
+
+    /// Recorded operation
+    operation: Operation,
+}
+
+
+impl Activity {
+    /// Constructs an entry of activity log.
+    /// 
+    /// * `revision` - revision, that recorded the operation
+    /// * `operation` - recorded operation
+    pub(crate) fn new(revision: Revision, operation: Operation) -> Self {
+        Activity {
+            revision,
+            operation
+        }
+    }
+
+
+    /// Obtains a revision, that recorded the operation.
+    pub fn get_revision(&self) -> &Revision {
+        &self.revision
+    }
+
+
+    /// Obtains the recorded operation.
+    pub fn get_operation(&self) -> &Operation {
+        &self.operation
+    }
+}
+
+
+/// Converts a relative path into a platform independent string (with
+/// forward slashes).
+/// 
+/// * `relative_path` - path to convert
+fn path_to_string(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Creates an operation with all kinds of changes.
+    fn create_operation() -> Operation {
+        Operation::new(OperationKind::External)
+            .with_change(ChangeAction::Added, Path::new("added.md"), Some("1"))
+            .with_change(ChangeAction::Modified, Path::new("folder/modified.md"), Some("2"))
+            .with_change(ChangeAction::Moved { from: PathBuf::from("from.md") }, Path::new("to.md"), Some("3"))
+            .with_change(ChangeAction::Removed, Path::new("removed"), None)
+    }
+
+
+    #[test]
+    fn operation_survives_message() {
+        let operation = create_operation();
+        assert_eq!(Operation::parse(&operation.compose_message(None)), Some(operation.clone()));
+        assert_eq!(Operation::parse(&operation.compose_message(Some("feat: custom summary"))), Some(operation));
+
+        let operation = Operation::new(OperationKind::Undo).with_target("0123abcd");
+        assert_eq!(Operation::parse(&operation.compose_message(None)), Some(operation));
+    }
+
+
+    #[test]
+    fn all_kinds_survive_message() {
+        for (kind, _name) in OPERATION_NAMES {
+            let operation = Operation::new(kind);
+            assert_eq!(Operation::parse(&operation.compose_message(None)).map(|operation| operation.get_kind()), Some(kind));
+        }
+    }
+
+
+    #[test]
+    fn special_characters_in_paths_survive_message() {
+        //
+        // Summary contains paths as is, so a path with an empty
+        // line splits the summary into paragraphs
+        //
+
+        let operation = Operation::new(OperationKind::Move)
+            .with_change(ChangeAction::Moved { from: PathBuf::from("a:\n\nb.md") }, Path::new("c: d\nMm-Operation: add.md"), Some("id: 1"));
+
+        let message = operation.compose_message(None);
+        assert_eq!(message.lines().filter(|line| line.starts_with(MM_CHANGE_TRAILER)).count(), 1);
+        assert_eq!(Operation::parse(&message), Some(operation));
+    }
+
+
+    #[test]
+    fn unknown_trailers_are_ignored() {
+        let operation = create_operation();
+        let message = format!("{}\nSigned-off-by: Tester <tester@example.com>\n", operation.compose_message(None));
+
+        assert_eq!(Operation::parse(&message), Some(operation));
+    }
+
+
+    #[test]
+    fn messages_without_operation_are_skipped() {
+        assert_eq!(Operation::parse(""), None);
+        assert_eq!(Operation::parse("fix: made elsewhere"), None);
+        assert_eq!(Operation::parse("fix: made elsewhere\n\nSigned-off-by: Tester"), None);
+        assert_eq!(Operation::parse("fix: made elsewhere\n\nMm-Change: {\"action\":\"added\",\"path\":\"a.md\"}"), None);
+        assert_eq!(Operation::parse("chore: text\n\nMm-Operation: unknown"), None);
+        assert_eq!(Operation::parse("chore: text\n\nMm-Operation: add\nnot a trailer"), None);
+    }
+
+
+    #[test]
+    fn malformed_changes_are_rejected() {
+        for change in ["{", "{\"action\":\"added\"}", "{\"action\":\"moved\",\"path\":\"a.md\"}", "{\"action\":\"copied\",\"path\":\"a.md\"}"] {
+            let message = format!("chore: text\n\n{}: add\n{}: {}", MM_OPERATION_TRAILER, MM_CHANGE_TRAILER, change);
+            assert_eq!(Operation::parse(&message), None, "{}", change);
+        }
+    }
+
+
+    #[test]
+    fn unsafe_paths_are_rejected() {
+        for path in ["", "..", "../note.md", "folder/../../note.md", "/etc/passwd", "./note.md", ".mm/mm_ids.json", "attachments/image.png"] {
+            let operation = Operation::new(OperationKind::Add)
+                .with_change(ChangeAction::Added, Path::new(path), Some("1"));
+
+            assert_eq!(Operation::parse(&operation.compose_message(None)), None, "{}", path);
+
+            let operation = Operation::new(OperationKind::Move)
+                .with_change(ChangeAction::Moved { from: PathBuf::from(path) }, Path::new("note.md"), Some("1"));
+
+            assert_eq!(Operation::parse(&operation.compose_message(None)), None, "{}", path);
+        }
+    }
+
+
+    #[test]
+    fn attachments_are_changed_only_by_attachment_operations() {
+        let attachment = Path::new("attachments/image.png");
+        for kind in [OperationKind::Attach, OperationKind::CleanUp] {
+            let operation = Operation::new(kind).with_change(ChangeAction::Added, attachment, None);
+            assert_eq!(Operation::parse(&operation.compose_message(None)), Some(operation));
+
+            let operation = Operation::new(kind).with_change(ChangeAction::Added, Path::new("note.md"), None);
+            assert_eq!(Operation::parse(&operation.compose_message(None)), None, "{:?}", kind);
+
+            let operation = Operation::new(kind).with_change(ChangeAction::Added, Path::new("attachments/../note.md"), None);
+            assert_eq!(Operation::parse(&operation.compose_message(None)), None, "{:?}", kind);
+        }
+    }
+
+
+    #[test]
+    fn inverted_changes_revert_operation() {
+        let operation = create_operation();
+        for change in operation.get_changes() {
+            assert_eq!(&change.invert().invert(), change);
+        }
+
+        let moved = &operation.get_changes()[2].invert();
+        assert_eq!(moved.get_path(), Path::new("from.md"));
+        assert_eq!(moved.get_action(), &ChangeAction::Moved { from: PathBuf::from("to.md") });
+    }
+}
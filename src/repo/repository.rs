@@ -1,27 +1,57 @@
-use std::path::Path;
+use std::{env, fs};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use std::ops::Drop;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::sync::mpsc::{self, Receiver};
 
-use git2;
+use chrono::{Local, NaiveDate};
 
-use super::{MM_MAIN_REPO_NAME};
-use super::helpers;
-use crate::{misc, cfg};
-use crate::error::{Error, Result, ErrorCategory};
+use super::{
+    MM_MAIN_REPO_NAME,
+    MM_ATTACHMENTS_FOLDER,
+    MM_TEMPLATE_DATE_FORMAT,
+    MM_INITIAL_COMMIT_MESSAGE,
+    MM_IMPORT_COMMIT_MESSAGE,
+};
+use super::{helpers, ids, attachments, trash, templates, journal, metadata, events, undo};
+use super::trash::{TrashEntry};
+use super::metadata::{NoteMetadata};
+use super::lock::{RepositoryLock, LockMode};
+use super::events::{RepositoryEvent, SubscriptionId};
+use super::operation::{Operation, OperationKind, ChangeAction, FileChange, Activity};
+use super::ArchiveFormat;
+use crate::{misc, cfg, editor, crypto, export, wiki, import};
+use crate::import::{ImportReport};
+use crate::data::{Storage, GitStorage, MemoryStorage, Revision, StatusEntry, StatusKind};
+use crate::error::{Error, Result, ErrorCategory, ErrorKind};
+
+
+/// Number of seconds in a day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
 
 
 /// A structure, that describes a repository for notes.
 pub struct Repository {
-    /// Internal git repository, that manages version control
-    internal_repo: git2::Repository,
+    /// Storage, that keeps repository's files and manages version control
+    storage: Box<dyn Storage>,
 
     /// Name of the repository
     name: String,
 
-    /// Optional list of remotes. `None` if repository has no remotes
-    remotes: Option<git2::string_array::StringArray>,
-
     /// Repository's configuration
-    config: cfg::Config
+    config: cfg::Config,
+
+    /// Unlock session, that holds a key for encrypted notes
+    session: Mutex<crypto::Session>,
+
+    /// Lock, that prevents other processes from concurrent access (only
+    /// for repositories under repositories folder)
+    lock: Option<RepositoryLock>,
+
+    /// Subscribers to repository's events
+    observers: Mutex<events::Observers>,
 }
 
 
@@ -29,39 +59,157 @@ impl Repository {
     /// Returns a repository ready to use.
     /// 
     /// Supports opening a repository by its name or a main repo if no name given.
+    /// Repository is backed by git (see [`crate::data::GitStorage`]).
+    /// 
+    /// The repository is locked exclusively (see [`LockMode::Exclusive`]), so
+    /// other processes can't open it until the returned object is dropped.
+    /// Opening a repository, that is used by another process, fails with
+    /// [`crate::error::ErrorKind::Locked`]. Lock files of crashed processes
+    /// are removed automatically on unix only, elsewhere they must be removed
+    /// from `.mm/locks` folder by hand.
     /// 
     /// * `repo_name` - a name of repository to open (pass `None` to open a main repository)
     pub fn open_or_create(repo_name: Option<&str>) -> Result<Self> {
         //
-        // Firstly we need to ensure, that we have repositories folder.
-        // App may be run for the first time or data may be erased, so
-        // we need to create the folder if necessary
+        // Firstly we need to ensure, that we have repositories folder
         //
 
-        if !helpers::is_repos_folder_present() {
-            helpers::get_repos_folder()
-                .ok_or(Error::from_string("cannot get repositories folder", ErrorCategory::Os))
-                .and_then(misc::create_folder_recursive)?;
-        }
+        Repository::create_repos_folder()?;
 
         //
         // Now let's try to open an internal git repository.
         // If it doesn't exists, it is neessary to create it.
         //
 
-        let internal_repo = helpers::get_repo_path(&repo_name)
-            .ok_or(Error::from_string("cannot get repository path", ErrorCategory::Os))
-            .and_then(helpers::open_or_create_repository)?;
+        let repo_path = helpers::get_repo_path(&repo_name)
+            .ok_or(Error::from_string("cannot get repository path", ErrorCategory::Os))?;
+
+        let lock = RepositoryLock::acquire(&repo_path.join(helpers::get_locks_directory()), LockMode::Exclusive)?;
+        let storage = GitStorage::open_or_create(&repo_path)?;
+
+        Repository::from_storage_internal(storage, repo_name.unwrap_or(MM_MAIN_REPO_NAME), cfg::Config::new, Some(lock))
+    }
+
+
+    /// Opens an existing repository for reading. Any number of processes
+    /// may open a repository this way at the same time (see [`LockMode::Shared`]),
+    /// but not together with [`Repository::open_or_create`].
+    /// 
+    /// Changes of the repository fail with [`crate::error::ErrorKind::Locked`]
+    /// and configuration is not saved at destruction.
+    /// 
+    /// * `repo_name` - a name of repository to open (pass `None` to open a main repository)
+    pub fn open_shared(repo_name: Option<&str>) -> Result<Self> {
+        let repo_path = helpers::get_repo_path(&repo_name)
+            .ok_or(Error::from_string("cannot get repository path", ErrorCategory::Os))?;
+
+        if !repo_path.join(helpers::get_config_file()).exists() {
+            return Err(Error::from_string("repository not found", ErrorCategory::Repo)
+                .with_kind(ErrorKind::NotFound)
+                .with_path(repo_path));
+        }
+
+        let lock = RepositoryLock::acquire(&repo_path.join(helpers::get_locks_directory()), LockMode::Shared)?;
+        let storage = GitStorage::open_or_create(&repo_path)?;
+
+        Repository::from_storage_internal(storage, repo_name.unwrap_or(MM_MAIN_REPO_NAME), cfg::Config::new, Some(lock))
+    }
+
+
+    /// Returns a repository, that keeps its files in a specific storage.
+    /// 
+    /// Storage is initialized with a default configuration if necessary.
+    /// 
+    /// * `storage` - storage to use (see [`crate::data::Storage`])
+    /// * `repo_name` - a name of repository
+    pub fn from_storage<S>(storage: S, repo_name: &str) -> Result<Self>
+    where
+        S: Storage + 'static
+    {
+        Repository::from_storage_internal(storage, repo_name, cfg::Config::new, None)
+    }
+
+
+    /// Returns a repository, that keeps all its files in memory (see 
+    /// [`crate::data::MemoryStorage`]). Nothing is written to disk, 
+    /// so such repositories are useful for tests.
+    /// 
+    /// Git user is specified explicitly, so the repository doesn't 
+    /// depend on system-default git configuration.
+    /// 
+    /// * `repo_name` - a name of repository
+    /// * `user_name` - name of the user, that makes changes
+    /// * `user_email` - email of the user, that makes changes
+    pub fn in_memory(repo_name: &str, user_name: &str, user_email: &str) -> Result<Self> {
+        Repository::from_storage_internal(MemoryStorage::new(), repo_name, || cfg::Config::with_user(user_name, user_email), None)
+    }
+
 
-        Repository::from_git_repository(internal_repo, repo_name)
+    /// Sets git user, that makes changes, instead of system-default one.
+    /// 
+    /// * `user_name` - name of the user
+    /// * `user_email` - email of the user
+    pub fn set_user(&mut self, user_name: &str, user_email: &str) {
+        self.config.set_user(user_name, user_email);
+        self.notify(RepositoryEvent::ConfigChanged);
     }
 
 
     /// Obtains a working directory for current repository.
     pub fn get_workdir(&self) -> Result<&Path> {
-        helpers::get_workdir(&self.internal_repo)
+        Ok(self.storage.get_root())
+    }
+
+
+    /// Obtains a name of current repository.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+
+    /// Obtains a mode, that the repository is locked in. Repositories
+    /// outside of repositories folder are not locked (`None`).
+    pub fn get_lock_mode(&self) -> Option<LockMode> {
+        self.lock
+            .as_ref()
+            .map(RepositoryLock::get_mode)
+    }
+
+
+    /// Subscribes a callback to repository's events. The callback is called
+    /// after each change made through the library (see [`RepositoryEvent`]).
+    /// Returns an identifier of the subscription.
+    /// 
+    /// Subscribers are not locked while the callback is called, so it may
+    /// use the repository and subscribe or unsubscribe. A subscription,
+    /// that is removed during delivery of an event, may still receive it.
+    /// 
+    /// * `callback` - function, that is called for each event
+    pub fn subscribe<F>(&self, callback: F) -> Result<SubscriptionId>
+    where
+        F: Fn(&RepositoryEvent) + Send + Sync + 'static
+    {
+        Ok(self.lock_observers()?.add_callback(Arc::new(callback)))
+    }
+
+
+    /// Subscribes a channel to repository's events. Returns a receiving end
+    /// of the channel. The subscription ends when the receiver is dropped.
+    pub fn subscribe_channel(&self) -> Result<Receiver<RepositoryEvent>> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock_observers()?.add_channel(sender);
+
+        Ok(receiver)
+    }
+
+
+    /// Removes a subscription. Returns `true` if the subscription existed.
+    /// 
+    /// * `id` - identifier of the subscription (see [`Repository::subscribe`])
+    pub fn unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        Ok(self.lock_observers()?.remove(id))
     }
-    
+
 
     /// Adds a note to repository.
     /// 
@@ -70,28 +218,47 @@ impl Repository {
     /// * `note_path` - absolute path to a note to add
     pub fn add_note(&self, note_path: &Path) -> Result<()> {
         //
-        // First check existence of a note and that path 
-        // is actually absolute path to a file
+        // First check existence of a note and that path
+        // is actually absolute path to a file in current
+        // repository. And now let's add a note
         //
 
-        if !note_path.exists() || !note_path.is_file() || !note_path.is_absolute() {
-            return Err(Error::from_string("invalid absolute note path", ErrorCategory::Os));
-        }
+        self.get_note_relative_path(note_path)
+            .and_then(|relative_path| self.add_note_internal(relative_path))
+    }
 
-        //
-        // And now let's add a note if it is actually located
-        // in current repository
-        //
 
-        let workdir = self.get_workdir()?;
-        note_path
-            .strip_prefix(workdir)
-            .map_err(Error::from)
-            .and_then(|relative_path| self.add_note_internal(relative_path))
+    /// Reads content of a note.
+    /// 
+    /// * `note_path` - absolute path to a note to read
+    pub fn read_note(&self, note_path: &Path) -> Result<Vec<u8>> {
+        self.get_note_relative_path(note_path)
+            .and_then(|relative_path| self.read_content(relative_path))
+    }
+
+
+    /// Writes content of a note and adds it to repository. The note is
+    /// created if necessary.
+    /// 
+    /// The note is encrypted if it is encrypted already or encryption of
+    /// all notes is enabled (see [`Repository::set_encryption`]).
+    /// 
+    /// * `note_path` - absolute path to a note to write
+    /// * `content` - new content of the note
+    pub fn write_note(&self, note_path: &Path, content: &[u8]) -> Result<()> {
+        let relative_path = self.get_relative_path(note_path)?;
+        if self.storage.is_folder(relative_path) {
+            return Err(Error::from_string("invalid absolute note path", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(note_path));
+        }
+
+        self.write_content(relative_path, content)?;
+        self.add_note_internal(relative_path)
     }
 
 
-    /// Adds a folder to repository. 
+    /// Adds a folder to repository.
     /// 
     /// Folder MUST exist.
     /// 
@@ -101,104 +268,3501 @@ impl Repository {
         // Firstly we need to ensure, that we create a valid folder
         //
 
-        if !folder_path.exists() || !folder_path.is_dir() || !folder_path.is_absolute() {
-            return Err(Error::from_string("invalid absolute folder path", ErrorCategory::Os));
+        let relative_path = self.get_relative_path(folder_path)?;
+        if !self.storage.is_folder(relative_path) {
+            return Err(Error::from_string("invalid absolute folder path", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(folder_path));
         }
 
         //
         // Just create a directory. Nothing else is required.
         //
 
+        self.add_folder_internal(relative_path)?;
+        self.notify(RepositoryEvent::FolderAdded(folder_path.to_path_buf()));
+
+        Ok(())
+    }
+
+
+    /// Returns history of revisions (the newest first).
+    /// 
+    /// * `note_path` - absolute path to a note to get history of (`None`
+    ///   means history of the whole repository)
+    pub fn get_history(&self, note_path: Option<&Path>) -> Result<Vec<Revision>> {
+        match note_path {
+            Some(note_path) => self.storage.history(Some(self.get_relative_path(note_path)?)),
+            None => self.storage.history(None)
+        }
+    }
+
+
+    /// Returns operations made through the library (the newest first).
+    /// Revisions without recorded operations (e.g. made by other tools)
+    /// are skipped. Paths of changed files are absolute.
+    /// 
+    /// * `note_path` - absolute path to a note to get activity of (`None`
+    ///   means activity of the whole repository)
+    pub fn get_activity(&self, note_path: Option<&Path>) -> Result<Vec<Activity>> {
         let workdir = self.get_workdir()?;
-        folder_path
-            .strip_prefix(workdir)
-            .map_err(Error::from)
-            .and_then(|relative_path| self.add_folder_internal(relative_path))
+
+        Ok(self.get_history(note_path)?
+            .into_iter()
+            .filter_map(|revision| {
+                let operation = Operation::parse(revision.get_message())?.resolve(workdir);
+                Some(Activity::new(revision, operation))
+            })
+            .collect())
     }
 
 
-    /// Internal constructor, that constructs a repository instance from 
-    /// internal [`git2::Repository`] instance.
+    /// Returns changes of notes in working directory, that are not committed
+    /// yet (e.g. made by external editors), and files with unresolved merge
+    /// conflicts. Paths are absolute.
     /// 
-    /// * `repo` - git repository instance to wrap
-    /// * `repo_name` - a name of repository to open (pass `None` to open a main repository)
-    fn from_git_repository(repo: git2::Repository, repo_name: Option<&str>) -> Result<Repository> {
-        let remotes = repo
-            .remotes()
-            .ok();
+    /// Internal files (configuration, lock files, attachments) are reported
+    /// only if they are conflicted.
+    pub fn status(&self) -> Result<Vec<StatusEntry>> {
+        let workdir = self.get_workdir()?;
 
-        let config_file = helpers::get_config_file(&repo)?;
+        Ok(self.storage.status()?
+            .into_iter()
+            .filter(|entry| *entry.get_kind() == StatusKind::Conflicted || helpers::is_note_path(entry.get_path()))
+            .map(|entry| {
+                let kind = match entry.get_kind() {
+                    StatusKind::Renamed { from } => StatusKind::Renamed { from: workdir.join(from) },
+                    kind => kind.clone()
+                };
+
+                StatusEntry::new(workdir.join(entry.get_path()), kind)
+            })
+            .collect())
+    }
 
-        Ok(Repository { 
-            internal_repo: repo, 
 
-            name: repo_name
-                .unwrap_or(MM_MAIN_REPO_NAME)
-                .to_owned(), 
+    /// Commits all changes of notes, that were made outside of the library
+    /// (see [`Repository::status`]). Ignored and internal files are skipped,
+    /// removed notes are recorded as removed and moved notes keep their
+    /// identifiers. Returns number of committed notes.
+    /// 
+    /// Fails with [`ErrorKind::Conflict`] if there are unresolved merge
+    /// conflicts.
+    /// 
+    /// * `message` - commit message (`None` means a generated summary of
+    ///   changes)
+    pub fn commit_pending(&self, message: Option<&str>) -> Result<usize> {
+        let status = self.status()?;
+        if let Some(entry) = status.iter().find(|entry| *entry.get_kind() == StatusKind::Conflicted) {
+            return Err(Error::from_string("repository has unresolved conflicts", ErrorCategory::Repo)
+                .with_kind(ErrorKind::Conflict)
+                .with_path(entry.get_path()));
+        }
 
-            remotes: remotes,
+        //
+        // Both paths of a rename are passed as changed too,
+        // in case the rename is not confirmed by working directory
+        //
 
-            config: cfg::Config::load(&config_file)?
-        })
+        let mut paths = Vec::with_capacity(status.len());
+        let mut renamed = Vec::new();
+        for entry in &status {
+            if let StatusKind::Renamed { from } = entry.get_kind() {
+                renamed.push((from.clone(), entry.get_path().to_path_buf()));
+                paths.push(from.clone());
+            }
+
+            paths.push(entry.get_path().to_path_buf());
+        }
+
+        self.commit_external_changes(&paths, &renamed, message)
     }
 
 
-    /// Adds a note to repository (internal implementation).
+    /// Reverts the most recent operation made through the library, that is
+    /// not undone yet. Changes are reverted by a new commit: moved notes are
+    /// moved back, removed notes are restored from trash, added notes are
+    /// moved to trash and modified notes get their previous content. Returns
+    /// the undone operation (`None` if there is nothing to undo).
     /// 
-    /// Calls `git2::Index::add_all` in order to take `.gitignore` into 
-    /// account, because `git2::Index::add_path` forces files to be added.
+    /// Only operations with notes may be undone (see
+    /// [`OperationKind::is_reversible`]). Other operations and commits made
+    /// outside of the library can not be undone, as well as operations
+    /// before them. Fails if affected notes have uncommitted changes.
+    pub fn undo(&self) -> Result<Option<Operation>> {
+        let stacks = undo::UndoStacks::from_history(&self.storage.history(None)?);
+        let step = match stacks.get_undo() {
+            Some(step) => step,
+            None => return Ok(None)
+        };
+
+        //
+        // Changes are reverted in reverse order, contents
+        // of notes are taken from the preceding revision
+        //
+
+        let operation = Operation::new(OperationKind::Undo)
+            .with_target(step.get_revision_id())
+            .with_changes(step.get_operation()
+                .get_changes()
+                .iter()
+                .rev()
+                .map(FileChange::invert));
+
+        self.apply_operation(&operation, step.get_previous_id())?;
+
+        Ok(Some(step.get_operation().clone().resolve(self.get_workdir()?)))
+    }
+
+
+    /// Applies the most recently undone operation again (see
+    /// [`Repository::undo`]). Returns the redone operation (`None` if
+    /// there is nothing to redo). Any other operation made after undo
+    /// discards undone operations.
+    pub fn redo(&self) -> Result<Option<Operation>> {
+        let stacks = undo::UndoStacks::from_history(&self.storage.history(None)?);
+        let step = match stacks.get_redo() {
+            Some(step) => step,
+            None => return Ok(None)
+        };
+
+        let operation = Operation::new(OperationKind::Redo)
+            .with_target(step.get_revision_id())
+            .with_changes(step.get_operation().get_changes().iter().cloned());
+
+        self.apply_operation(&operation, Some(step.get_revision_id()))?;
+
+        Ok(Some(step.get_operation().clone().resolve(self.get_workdir()?)))
+    }
+
+
+    /// Removes a note from repository. The note is moved to trash, so
+    /// it can be restored later (see [`Repository::restore_from_trash`]).
     /// 
-    /// * `relative_path` - relative to working directory path to the note
-    fn add_note_internal(&self, relative_path: &Path) -> Result<()> {
+    /// * `note_path` - absolute path to a note to remove
+    pub fn remove_note(&self, note_path: &Path) -> Result<()> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+
         //
-        // Let's add folder first (skip step for notes in repository's root)
+        // Forget note's identifier. It is stored in trash and
+        // will be restored together with the note
         //
 
-        if let Some(parent) = relative_path.parent() {
-            self.add_folder_internal(parent)?;
+        let mut note_id = None;
+        let ids_file = self.update_note_ids(|ids| {
+            note_id = ids.remove(relative_path);
+        })?;
+
+        //
+        // Now move the note into trash
+        //
+
+        let trash_file = helpers::get_trash_file();
+        let mut trash = trash::Trash::load(self.storage.as_ref(), &trash_file)?;
+
+        let trashed_path = trash
+            .push(relative_path, note_id.clone())
+            .get_trashed_path();
+
+        self.storage.rename(relative_path, &trashed_path)?;
+        trash.save(self.storage.as_ref(), &trash_file)?;
+
+        let operation = Operation::new(OperationKind::Remove)
+            .with_change(ChangeAction::Removed, relative_path, note_id.as_deref());
+
+        self.commit_changes(&[&trashed_path, &ids_file, &trash_file], &[relative_path], &operation, None)?;
+
+        self.notify(RepositoryEvent::NoteDeleted(note_path.to_path_buf()));
+
+        //
+        // It is a good time to clean up trash
+        //
+
+        self.purge_trash()
+            .map(|_count| ())
+    }
+
+
+    /// Returns list of notes in trash. Original paths of entries are absolute.
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>> {
+        let workdir = self.get_workdir()?;
+        let trash = trash::Trash::load(self.storage.as_ref(), &helpers::get_trash_file())?;
+
+        Ok(trash.get_entries()
+            .iter()
+            .cloned()
+            .map(|entry| {
+                let original_path = workdir.join(entry.get_original_path());
+                entry.with_original_path(original_path)
+            })
+            .collect())
+    }
+
+
+    /// Restores a note from trash to its original location. Returns an
+    /// absolute path to the restored note.
+    /// 
+    /// Fails if another note already exists at the original location.
+    /// 
+    /// * `entry_id` - identifier of an entry in trash (see [`TrashEntry::get_id`])
+    pub fn restore_from_trash(&self, entry_id: &str) -> Result<PathBuf> {
+        let trash_file = helpers::get_trash_file();
+
+        let mut trash = trash::Trash::load(self.storage.as_ref(), &trash_file)?;
+        let entry = trash
+            .take(entry_id)
+            .ok_or(Error::from_string("no such entry in trash", ErrorCategory::Repo).with_kind(ErrorKind::NotFound))?;
+
+        let relative_path = entry.get_original_path();
+        if self.storage.exists(relative_path) {
+            return Err(Error::from_string("note already exists at original location", ErrorCategory::Repo)
+                .with_kind(ErrorKind::AlreadyExists)
+                .with_path(self.get_workdir()?.join(relative_path)));
+        }
+
+        //
+        // Move the note back and remove entry's folder
+        //
+
+        let trashed_path = entry.get_trashed_path();
+
+        self.storage.rename(&trashed_path, relative_path)?;
+
+        if let Some(entry_folder) = trashed_path.parent() {
+            self.storage.delete(entry_folder)?;
         }
 
         //
-        // And now add note itself
+        // Note gets its identifier back
         //
-        
-        helpers::commit_files(&self.internal_repo, &self.config, [relative_path].iter(),
-            Some(&format!("chore: {:?} added", relative_path)))
+
+        let mut note_id = String::new();
+        let ids_file = self.update_note_ids(|ids| {
+            note_id = match entry.get_note_id() {
+                Some(note_id) => {
+                    ids.insert(note_id, relative_path);
+                    note_id.to_owned()
+                },
+                None => ids.assign(relative_path)
+            };
+        })?;
+
+        trash.save(self.storage.as_ref(), &trash_file)?;
+
+        let operation = Operation::new(OperationKind::Restore)
+            .with_change(ChangeAction::Added, relative_path, Some(&note_id));
+
+        self.commit_changes(&[relative_path, &ids_file, &trash_file], &[&trashed_path], &operation, None)?;
+
+        let note_path = self.get_workdir()?.join(relative_path);
+        self.notify(RepositoryEvent::NoteCreated(note_path.clone()));
+
+        Ok(note_path)
+    }
+
+
+    /// Permanently removes all notes from trash.
+    pub fn empty_trash(&self) -> Result<()> {
+        self.remove_from_trash(|_entry| false)
+            .map(|_count| ())
+    }
+
+
+    /// Permanently removes notes, that are in trash longer than configured
+    /// number of days (see [`Repository::set_trash_purge_days`]). Returns
+    /// number of removed notes.
+    pub fn purge_trash(&self) -> Result<usize> {
+        let days = match self.config.query_trash_purge_days() {
+            Some(days) => days,
+            None => return Ok(0)
+        };
+
+        let max_age = Duration::from_secs(days * SECONDS_PER_DAY);
+        let now = SystemTime::now();
+
+        self.remove_from_trash(|entry| {
+            now.duration_since(entry.get_deleted_at())
+                .map_or(true, |age| age <= max_age)
+        })
     }
 
 
-    /// Adds a folder to repository (internal implementation). 
+    /// Sets number of days after which notes are purged from trash
+    /// automatically.
     /// 
-    /// Used for optimization: sometimes workdir is already known, so we can 
-    /// skip its acquisition.
+    /// * `days` - number of days (`None` disables automatic purge)
+    pub fn set_trash_purge_days(&mut self, days: Option<u64>) {
+        self.config.set_trash_purge_days(days);
+        self.notify(RepositoryEvent::ConfigChanged);
+    }
+
+
+    /// Moves (or renames) a note inside of repository. Note's identifier
+    /// remains the same.
     /// 
-    /// * `relative_path` - relative to working directory path to the folder
-    fn add_folder_internal(&self, relative_path: &Path) -> Result<()> {
-        if relative_path.as_os_str().is_empty() {
+    /// * `note_path` - absolute path to a note to move
+    /// * `new_note_path` - absolute destination path (MUST NOT exist)
+    pub fn move_note(&self, note_path: &Path, new_note_path: &Path) -> Result<()> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+        let new_relative_path = self.get_relative_path(new_note_path)?;
+
+        if self.storage.exists(new_relative_path) {
+            return Err(Error::from_string("invalid absolute destination path", ErrorCategory::Os)
+                .with_kind(ErrorKind::AlreadyExists)
+                .with_path(new_note_path));
+        }
+
+        //
+        // Move the file itself. Destination folder may be absent,
+        // but storage creates it if necessary
+        //
+
+        self.storage.rename(relative_path, new_relative_path)?;
+
+        //
+        // Now identifier follows the note. Notes added not with
+        // the library may have no identifier, so it is assigned here
+        //
+
+        let mut note_id = String::new();
+        let ids_file = self.update_note_ids(|ids| {
+            ids.rename(relative_path, new_relative_path);
+            note_id = ids.assign(new_relative_path);
+        })?;
+
+        let operation = Operation::new(OperationKind::Move)
+            .with_change(ChangeAction::Moved { from: relative_path.to_path_buf() }, new_relative_path, Some(&note_id));
+
+        self.commit_changes(&[new_relative_path, &ids_file], &[relative_path], &operation, None)?;
+
+        self.notify(RepositoryEvent::NoteMoved {
+            from: note_path.to_path_buf(),
+            to: new_note_path.to_path_buf()
+        });
+
+        Ok(())
+    }
+
+
+    /// Looks for a note by its stable identifier. Returns an absolute path
+    /// to the note or `None` if there is no note with such identifier.
+    /// 
+    /// * `id` - identifier of a note
+    pub fn find_by_id(&self, id: &str) -> Result<Option<PathBuf>> {
+        let workdir = self.get_workdir()?;
+        let ids = ids::NoteIds::load(self.storage.as_ref(), &helpers::get_ids_file())?;
+
+        Ok(ids.find(id)
+            .map(|relative_path| workdir.join(relative_path)))
+    }
+
+
+    /// Obtains a stable identifier of a note. Returns `None` if the note
+    /// has no identifier (e.g. it was not added to repository).
+    /// 
+    /// * `note_path` - absolute path to a note
+    pub fn get_note_id(&self, note_path: &Path) -> Result<Option<String>> {
+        let relative_path = self.get_relative_path(note_path)?;
+        let ids = ids::NoteIds::load(self.storage.as_ref(), &helpers::get_ids_file())?;
+
+        Ok(ids.id_of(relative_path)
+            .map(str::to_owned))
+    }
+
+
+    /// Removes identifiers of notes, that no longer exist in working
+    /// directory, together with their metadata. Useful after merging 
+    /// changes made elsewhere.
+    pub fn reconcile_note_ids(&self) -> Result<()> {
+        let ids_file = helpers::get_ids_file();
+        let metadata_file = helpers::get_metadata_file();
+
+        let mut ids = ids::NoteIds::load(self.storage.as_ref(), &ids_file)?;
+        let ids_changed = ids.retain(|relative_path| {
+            self.storage.exists(relative_path) && !self.storage.is_folder(relative_path)
+        });
+
+        //
+        // Metadata of trashed notes is kept, because they
+        // get their identifiers back at restoring
+        //
+
+        let trash = trash::Trash::load(self.storage.as_ref(), &helpers::get_trash_file())?;
+
+        let mut metadata = metadata::Metadata::load(self.storage.as_ref(), &metadata_file)?;
+        let metadata_changed = metadata.retain(|id| {
+            ids.find(id).is_some() || trash.get_entries().iter().any(|entry| entry.get_note_id() == Some(id))
+        });
+
+        if !ids_changed && !metadata_changed {
             //
-            // Adding repository's root is a normal case but we don't 
-            // need to do anything
+            // Nothing has changed, so there is nothing to commit
             //
 
             return Ok(());
         }
 
-        // TODO: add to configuration
+        ids.save(self.storage.as_ref(), &ids_file)?;
+        metadata.save(self.storage.as_ref(), &metadata_file)?;
+
+        self.commit_changes(&[&ids_file, &metadata_file], &[], &Operation::new(OperationKind::Reconcile), None)
+    }
+
+
+    /// Obtains metadata of a note (tags, creation and modification times).
+    /// Notes without metadata get an empty one.
+    /// 
+    /// * `note_path` - absolute path to a note
+    pub fn get_note_metadata(&self, note_path: &Path) -> Result<NoteMetadata> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+        let ids = ids::NoteIds::load(self.storage.as_ref(), &helpers::get_ids_file())?;
+
+        match ids.id_of(relative_path) {
+            Some(id) => Ok(metadata::Metadata::load(self.storage.as_ref(), &helpers::get_metadata_file())?.get(id)),
+            None => Ok(NoteMetadata::default())
+        }
+    }
+
+
+    /// Sets metadata of a note. Metadata is bound to note's stable
+    /// identifier, so it follows the note when it is moved.
+    /// 
+    /// * `note_path` - absolute path to a note
+    /// * `note_metadata` - new metadata of the note
+    pub fn set_note_metadata(&self, note_path: &Path, note_metadata: &NoteMetadata) -> Result<()> {
+        let relative_path = self.get_note_relative_path(note_path)?;
 
+        let mut id = String::new();
+        let ids_file = self.update_note_ids(|ids| {
+            id = ids.assign(relative_path);
+        })?;
+
+        let metadata_file = self.update_metadata(|metadata| {
+            metadata.set(&id, note_metadata);
+        })?;
+
+        let operation = Operation::new(OperationKind::Metadata)
+            .with_change(ChangeAction::Modified, relative_path, Some(&id));
+
+        self.commit_changes(&[&ids_file, &metadata_file], &[], &operation, None)?;
+
+        self.notify(RepositoryEvent::NoteUpdated(note_path.to_path_buf()));
         Ok(())
     }
-}
 
 
-impl Drop for Repository {
-    fn drop(&mut self) {
-        let config_file = helpers::get_config_file(&self.internal_repo)
-            .expect("cannot get repository's config file at destruction");
+    /// Adds an attachment (e.g. image or PDF) to repository and returns
+    /// a Markdown link to it, that may be inserted into a note.
+    /// 
+    /// Attachments are stored in a content-addressed folder, so the same
+    /// file is stored only once regardless of number of notes using it.
+    /// 
+    /// * `note_path` - absolute path to a note, that will contain the link
+    /// * `file_path` - path to a file to attach
+    pub fn add_attachment(&self, note_path: &Path, file_path: &Path) -> Result<String> {
+        if !file_path.exists() || !file_path.is_file() {
+            return Err(Error::from_string("invalid attachment path", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(file_path));
+        }
 
-        self.config
-            .save(&config_file)
-            .expect("cannot save configuration at destruction");
+        let note_relative_path = self.get_relative_path(note_path)?;
 
-        // TODO: stage and commit
+        let content = fs::read(file_path)?;
+        let attachment_name = attachments::get_attachment_name(&content, file_path);
+
+        //
+        // Store and commit the file only if it is not stored yet
+        //
+
+        let relative_path = Path::new(MM_ATTACHMENTS_FOLDER)
+            .join(&attachment_name);
+
+        if !self.storage.exists(&relative_path) {
+            self.storage.write(&relative_path, &content)?;
+            let operation = Operation::new(OperationKind::Attach)
+                .with_change(ChangeAction::Added, &relative_path, None);
+
+            self.commit_changes(&[&relative_path], &[], &operation, None)?;
+        }
+
+        let title = file_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+
+        Ok(attachments::compose_link(note_relative_path, &attachment_name, &title))
+    }
+
+
+    /// Collects notes, that reference each attachment. Returns a map from
+    /// absolute path of an attachment to absolute paths of notes. Attachments
+    /// without references are present with empty list of notes.
+    pub fn get_attachment_references(&self) -> Result<HashMap<PathBuf, Vec<PathBuf>>> {
+        let workdir = self.get_workdir()?;
+
+        Ok(attachments::collect_references(self.storage.as_ref(), |note| self.read_content(note))?
+            .into_iter()
+            .map(|(attachment, notes)| {
+                let notes = notes
+                    .iter()
+                    .map(|note| workdir.join(note))
+                    .collect();
+
+                (workdir.join(attachment), notes)
+            })
+            .collect())
+    }
+
+
+    /// Removes all attachments, that are not referenced by any note.
+    /// Returns absolute paths of removed attachments.
+    pub fn collect_attachments_garbage(&self) -> Result<Vec<PathBuf>> {
+        let mut garbage: Vec<PathBuf> = attachments::collect_references(self.storage.as_ref(), |note| self.read_content(note))?
+            .into_iter()
+            .filter(|(_attachment, notes)| notes.is_empty())
+            .map(|(attachment, _notes)| attachment)
+            .collect();
+
+        if garbage.is_empty() {
+            return Ok(garbage);
+        }
+
+        garbage.sort();
+
+        for attachment in &garbage {
+            self.storage.delete(attachment)?;
+        }
+
+        let removed: Vec<&Path> = garbage
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+
+        let operation = Operation::new(OperationKind::CleanUp)
+            .with_changes(removed.iter().map(|attachment| FileChange::new(ChangeAction::Removed, attachment, None)));
+
+        self.commit_changes(&[], &removed, &operation, None)?;
+
+        let workdir = self.get_workdir()?;
+        Ok(garbage
+            .iter()
+            .map(|attachment| workdir.join(attachment))
+            .collect())
+    }
+
+
+    /// Returns names of templates available in repository.
+    pub fn list_templates(&self) -> Result<Vec<String>> {
+        let templates_folder = helpers::get_templates_directory();
+        let templates = self.storage.list(&templates_folder)?;
+
+        Ok(templates
+            .iter()
+            .filter_map(|template| template.strip_prefix(&templates_folder).ok())
+            .map(|template| template.to_string_lossy().into_owned())
+            .collect())
+    }
+
+
+    /// Creates a new note from a template and adds it to repository.
+    /// 
+    /// Templates are stored in `templates` subfolder of repository's
+    /// configuration folder. The following variables are available in
+    /// templates besides custom ones:
+    /// 
+    /// - `{{date}}` - current date (e.g. `2023-01-31`)
+    /// - `{{title}}` - name of the note without extension
+    /// - `{{author}}` - name of the user from configuration
+    /// 
+    /// Custom variables override built-in ones. Unknown placeholders are
+    /// reported as errors.
+    /// 
+    /// * `template_name` - name of a template (see [`Repository::list_templates`])
+    /// * `note_path` - absolute path to a note to create (MUST NOT exist)
+    /// * `variables` - custom variables by their names
+    pub fn create_note_from_template(&self, template_name: &str, note_path: &Path, variables: &HashMap<String, String>) -> Result<()> {
+        let relative_path = self.get_relative_path(note_path)?;
+        if self.storage.exists(relative_path) {
+            return Err(Error::from_string("invalid absolute note path", ErrorCategory::Os)
+                .with_kind(ErrorKind::AlreadyExists)
+                .with_path(note_path));
+        }
+
+        helpers::check_template_name(template_name)?;
+
+        let template_path = helpers::get_templates_directory()
+            .join(template_name);
+
+        if !self.storage.exists(&template_path) || self.storage.is_folder(&template_path) {
+            return Err(Error::from_string(format!("template '{}' not found", template_name), ErrorCategory::Repo)
+                .with_kind(ErrorKind::NotFound));
+        }
+
+        //
+        // Compose all variables and render the template
+        //
+
+        let title = note_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut all_variables = HashMap::from([
+            ("date".to_owned(), Local::now().format(MM_TEMPLATE_DATE_FORMAT).to_string()),
+            ("title".to_owned(), title),
+            ("author".to_owned(), self.config.query_name()?.to_owned()),
+        ]);
+
+        all_variables.extend(variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+
+        let template = String::from_utf8(self.read_content(&template_path)?)
+            .map_err(|_| Error::from_string(format!("template '{}' is not a valid UTF-8 text", template_name), ErrorCategory::Repo))?;
+
+        let content = templates::render(&template, &all_variables)?;
+
+        //
+        // Now the note is ready to be created
+        //
+
+        self.write_content(relative_path, content.as_bytes())?;
+        self.add_note_internal(relative_path)
+    }
+
+
+    /// Opens a journal entry for a specific date. If the entry doesn't exist,
+    /// it is created (from configured template if any) and added to repository.
+    /// Returns an absolute path to the entry.
+    /// 
+    /// Entries are located in configured journal folder according to a
+    /// pattern (by default `journal/%Y/%m/%Y-%m-%d.md`).
+    /// 
+    /// Fails if journal settings are invalid (see
+    /// [`Repository::set_journal_folder`] and [`Repository::set_journal_pattern`]).
+    /// 
+    /// * `date` - date of the entry
+    pub fn open_journal_entry(&self, date: NaiveDate) -> Result<PathBuf> {
+        let relative_path = journal::get_entry_path(self.config.query_journal_folder(), self.config.query_journal_pattern(), date)?;
+
+        let entry_path = self.get_workdir()?
+            .join(&relative_path);
+
+        if self.storage.exists(&relative_path) {
+            return Ok(entry_path);
+        }
+
+        //
+        // The entry is absent, so let's create it. Template's date
+        // variable is replaced with entry's date
+        //
+
+        match self.config.query_journal_template() {
+            Some(template_name) => {
+                let variables = HashMap::from([
+                    ("date".to_owned(), date.format(MM_TEMPLATE_DATE_FORMAT).to_string()),
+                ]);
+
+                self.create_note_from_template(template_name, &entry_path, &variables)?;
+            },
+            None => {
+                self.write_content(&relative_path, &[])?;
+                self.add_note_internal(&relative_path)?;
+            }
+        }
+
+        Ok(entry_path)
+    }
+
+
+    /// Lists journal entries within a date range (both ends inclusive).
+    /// Entries are sorted by date.
+    /// 
+    /// * `from` - first date of the range
+    /// * `to` - last date of the range
+    pub fn list_journal_entries(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<(NaiveDate, PathBuf)>> {
+        let workdir = self.get_workdir()?;
+
+        journal::check_folder(self.config.query_journal_folder())?;
+        journal::check_pattern(self.config.query_journal_pattern())?;
+
+        let journal_folder = Path::new(self.config.query_journal_folder());
+
+        //
+        // Date of each entry is parsed back from its path, so
+        // unrelated files in journal folder are skipped
+        //
+
+        let pattern = self.config.query_journal_pattern();
+        let mut entries: Vec<(NaiveDate, PathBuf)> = self.storage.list(journal_folder)?
+            .into_iter()
+            .filter_map(|entry_path| {
+                let relative_path = entry_path
+                    .strip_prefix(journal_folder)
+                    .ok()?
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                NaiveDate::parse_from_str(&relative_path, pattern)
+                    .ok()
+                    .map(|date| (date, workdir.join(&entry_path)))
+            })
+            .filter(|(date, _entry_path)| from <= *date && *date <= to)
+            .collect();
+
+        entries.sort();
+        Ok(entries)
+    }
+
+
+    /// Sets a folder with journal entries. The folder must be located
+    /// inside of working directory and outside of configuration folder.
+    /// 
+    /// * `folder` - folder relative to working directory (`None` resets to default)
+    pub fn set_journal_folder(&mut self, folder: Option<&str>) -> Result<()> {
+        if let Some(folder) = folder {
+            journal::check_folder(folder)?;
+        }
+
+        self.config.set_journal_folder(folder);
+        self.notify(RepositoryEvent::ConfigChanged);
+
+        Ok(())
+    }
+
+
+    /// Sets a pattern of journal entries' paths (in `strftime` format, e.g.
+    /// `%Y/%m/%Y-%m-%d.md`). The pattern must contain only date specifiers
+    /// and produce paths inside of journal folder.
+    /// 
+    /// * `pattern` - pattern of paths (`None` resets to default)
+    pub fn set_journal_pattern(&mut self, pattern: Option<&str>) -> Result<()> {
+        if let Some(pattern) = pattern {
+            journal::check_pattern(pattern)?;
+        }
+
+        self.config.set_journal_pattern(pattern);
+        self.notify(RepositoryEvent::ConfigChanged);
+
+        Ok(())
+    }
+
+
+    /// Sets a template for new journal entries.
+    /// 
+    /// * `template` - name of a template (`None` means no template)
+    pub fn set_journal_template(&mut self, template: Option<&str>) -> Result<()> {
+        if let Some(template) = template {
+            helpers::check_template_name(template)?;
+        }
+
+        self.config.set_journal_template(template);
+        self.notify(RepositoryEvent::ConfigChanged);
+
+        Ok(())
+    }
+
+
+    /// Opens a note in an external editor and waits for the editor to exit.
+    /// If the note was changed, it is committed. Returns `true` if the note
+    /// was changed.
+    /// 
+    /// The editor is resolved from repository's local configuration first
+    /// (see [`Repository::set_editor`]), then `mm.editor` parameter of
+    /// user's git configuration, `$VISUAL` and `$EDITOR` environment
+    /// variables are checked. The editor works with a temporary copy of
+    /// the note, so the note remains untouched if the editor fails.
+    /// 
+    /// Encrypted notes are decrypted into the temporary copy and encrypted
+    /// back after editing. The copy is accessible by the owner only and is
+    /// wiped and removed as soon as the editor exits.
+    /// 
+    /// * `note_path` - absolute path to a note to edit
+    pub fn edit_note(&self, note_path: &Path) -> Result<bool> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+        let editor = editor::resolve_editor(self.config.query_editor())?;
+
+        //
+        // Temporary copy keeps note's name, because editors
+        // usually rely on extension (e.g. for highlighting).
+        // The copy is wiped and removed when dropped
+        //
+
+        let original = self.read_content(relative_path)?;
+        let edited = editor::TempCopy::create(&env::temp_dir(), Path::new(relative_path.file_name().unwrap_or_default()), &original)
+            .and_then(|copy| {
+                editor::launch_editor(&editor, copy.get_path())?;
+                copy.read()
+            });
+
+        let edited = edited?;
+        if edited == original {
+            return Ok(false);
+        }
+
+        self.write_content(relative_path, &edited)?;
+        self.add_note_internal(relative_path)?;
+
+        Ok(true)
+    }
+
+
+    /// Sets an editor command, that is used to edit notes. The command may
+    /// contain arguments (e.g. `code --wait`).
+    /// 
+    /// The command is saved in local configuration, that is never committed,
+    /// so repositories imported or merged from elsewhere cannot run their
+    /// own programs.
+    /// 
+    /// * `editor` - editor command (`None` means `$VISUAL` or `$EDITOR`)
+    pub fn set_editor(&mut self, editor: Option<&str>) {
+        self.config.set_editor(editor);
+        self.notify(RepositoryEvent::ConfigChanged);
+    }
+
+
+    /// Unlocks the repository, so encrypted notes may be read and written.
+    /// The key is derived from the passphrase with Argon2 and kept in memory
+    /// until the repository is locked (see [`Repository::lock`]). Key memory
+    /// is zeroized at locking.
+    /// 
+    /// The passphrase is verified against a check value stored in repository's
+    /// configuration folder, so a wrong passphrase fails immediately with
+    /// [`ErrorCategory::Passphrase`]. The first unlock of a repository sets
+    /// the passphrase.
+    /// 
+    /// * `passphrase` - passphrase to derive a key from
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        let check_file = helpers::get_key_check_file();
+
+        let key = match self.config.query_crypto_salt() {
+            Some(salt) => {
+                let key = crypto::derive_key(passphrase, &salt)?;
+                crypto::verify_check(&key, &self.storage.read(&check_file)?)?;
+
+                key
+            },
+            None => {
+                //
+                // The first passphrase for the repository, hence
+                // a new salt and a check value are stored
+                //
+
+                let salt = crypto::generate_salt();
+                let key = crypto::derive_key(passphrase, &salt)?;
+
+                self.config.set_crypto_salt(&salt);
+                self.storage.write(&check_file, &crypto::create_check(&key)?)?;
+
+                let config_file = self.save_config()?;
+                self.commit_changes(&[&config_file, &check_file], &[], &Operation::new(OperationKind::Config), Some("chore: encryption configured"))?;
+                self.notify(RepositoryEvent::ConfigChanged);
+
+                key
+            }
+        };
+
+        self.lock_session()?.unlock(key);
+        Ok(())
+    }
+
+
+    /// Locks the repository. The key is removed from memory, so encrypted
+    /// notes are not accessible until the repository is unlocked again.
+    pub fn lock(&self) -> Result<()> {
+        self.lock_session()?.lock();
+        Ok(())
+    }
+
+
+    /// Checks if the repository is locked. A repository is locked if it was
+    /// not unlocked or it was idle longer than configured timeout.
+    pub fn is_locked(&self) -> Result<bool> {
+        let timeout = self.get_idle_timeout();
+
+        Ok(!self.lock_session()?.is_unlocked(timeout))
+    }
+
+
+    /// Sets a time after which an idle unlocked repository is locked
+    /// automatically. Any access to encrypted notes resets the timer.
+    /// 
+    /// * `timeout` - idle timeout (`None` disables automatic locking)
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.config.set_idle_timeout(timeout.map(|timeout| timeout.as_secs()));
+        self.notify(RepositoryEvent::ConfigChanged);
+    }
+
+
+    /// Enables or disables encryption of all notes in repository. Enabling
+    /// encrypts all existing notes, so the repository MUST be unlocked (see
+    /// [`Repository::unlock`]). Disabling affects new notes only: already
+    /// encrypted notes remain encrypted (see [`Repository::decrypt_note`]).
+    /// 
+    /// While enabled, notes changed outside of the library are encrypted
+    /// before they are committed (see [`Repository::commit_pending`]), so
+    /// committing them fails if the repository is locked.
+    /// 
+    /// Previous revisions are not rewritten, so plain content of notes
+    /// committed before encryption was enabled remains in history.
+    /// 
+    /// * `enabled` - `true` to encrypt all notes
+    pub fn set_encryption(&mut self, enabled: bool) -> Result<()> {
+        if !enabled {
+            self.config.set_encrypt_all(false);
+
+            let config_file = self.save_config()?;
+            self.commit_changes(&[&config_file], &[], &Operation::new(OperationKind::Config), Some("chore: encryption of all notes disabled"))?;
+            self.notify(RepositoryEvent::ConfigChanged);
+
+            return Ok(());
+        }
+
+        let encrypted = self.with_key(|key| {
+            let mut encrypted = Vec::new();
+            for relative_path in self.list_notes()? {
+                let content = self.storage.read(&relative_path)?;
+                if crypto::is_encrypted(&content) {
+                    continue;
+                }
+
+                self.storage.write(&relative_path, &crypto::encrypt(key, &content)?)?;
+                encrypted.push(relative_path);
+            }
+
+            Ok(encrypted)
+        })?;
+
+        self.config.set_encrypt_all(true);
+
+        let config_file = self.save_config()?;
+        let mut changed: Vec<&Path> = encrypted
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+
+        changed.push(&config_file);
+
+        let operation = Operation::new(OperationKind::Encrypt)
+            .with_changes(encrypted.iter().map(|relative_path| FileChange::new(ChangeAction::Modified, relative_path, None)));
+
+        self.commit_changes(&changed, &[], &operation,
+            Some(&format!("chore: encryption of all notes enabled, {} notes encrypted", encrypted.len())))?;
+
+        let workdir = self.get_workdir()?;
+        for relative_path in &encrypted {
+            self.notify(RepositoryEvent::NoteUpdated(workdir.join(relative_path)));
+        }
+
+        self.notify(RepositoryEvent::ConfigChanged);
+        Ok(())
+    }
+
+
+    /// Encrypts a single note. Does nothing if the note is encrypted already.
+    /// 
+    /// * `note_path` - absolute path to a note to encrypt
+    pub fn encrypt_note(&self, note_path: &Path) -> Result<()> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+
+        let content = self.storage.read(relative_path)?;
+        if crypto::is_encrypted(&content) {
+            return Ok(());
+        }
+
+        let encrypted = self.with_key(|key| crypto::encrypt(key, &content))?;
+
+        self.storage.write(relative_path, &encrypted)?;
+        let operation = Operation::new(OperationKind::Encrypt)
+            .with_change(ChangeAction::Modified, relative_path, None);
+
+        self.commit_changes(&[relative_path], &[], &operation, None)?;
+
+        self.notify(RepositoryEvent::NoteUpdated(note_path.to_path_buf()));
+        Ok(())
+    }
+
+
+    /// Decrypts a single note, so it is stored as plain text. Does nothing
+    /// if the note is not encrypted.
+    /// 
+    /// * `note_path` - absolute path to a note to decrypt
+    pub fn decrypt_note(&self, note_path: &Path) -> Result<()> {
+        let relative_path = self.get_note_relative_path(note_path)?;
+
+        let content = self.storage.read(relative_path)?;
+        if !crypto::is_encrypted(&content) {
+            return Ok(());
+        }
+
+        let decrypted = self.with_key(|key| crypto::decrypt(key, &content))?;
+
+        self.storage.write(relative_path, &decrypted)?;
+        let operation = Operation::new(OperationKind::Decrypt)
+            .with_change(ChangeAction::Modified, relative_path, None);
+
+        self.commit_changes(&[relative_path], &[], &operation, None)?;
+
+        self.notify(RepositoryEvent::NoteUpdated(note_path.to_path_buf()));
+        Ok(())
+    }
+
+
+    /// Changes a passphrase (rotates a key). All encrypted notes including
+    /// ones in trash are encrypted again with a new key in a single commit.
+    /// The repository remains unlocked with the new key.
+    /// 
+    /// Previous revisions are not rewritten, so they remain encrypted with
+    /// the old key.
+    /// 
+    /// * `old_passphrase` - current passphrase
+    /// * `new_passphrase` - new passphrase
+    pub fn change_passphrase(&mut self, old_passphrase: &str, new_passphrase: &str) -> Result<()> {
+        let old_salt = self.config
+            .query_crypto_salt()
+            .ok_or(Error::from_string("encryption is not configured", ErrorCategory::Passphrase).with_kind(ErrorKind::NotFound))?;
+
+        let check_file = helpers::get_key_check_file();
+
+        let old_key = crypto::derive_key(old_passphrase, &old_salt)?;
+        crypto::verify_check(&old_key, &self.storage.read(&check_file)?)?;
+
+        let new_salt = crypto::generate_salt();
+        let new_key = crypto::derive_key(new_passphrase, &new_salt)?;
+
+        //
+        // Decrypt everything first, so corrupted content
+        // is detected before anything is changed
+        //
+
+        let mut files = self.list_notes()?;
+        files.extend(self.storage.list(&helpers::get_trash_directory())?);
+
+        let mut decrypted = Vec::new();
+        for relative_path in files {
+            let content = self.storage.read(&relative_path)?;
+            if crypto::is_encrypted(&content) {
+                let plaintext = crypto::decrypt(&old_key, &content)?;
+                decrypted.push((relative_path, plaintext));
+            }
+        }
+
+        for (relative_path, plaintext) in &decrypted {
+            self.storage.write(relative_path, &crypto::encrypt(&new_key, plaintext)?)?;
+        }
+
+        self.config.set_crypto_salt(&new_salt);
+        self.storage.write(&check_file, &crypto::create_check(&new_key)?)?;
+
+        let config_file = self.save_config()?;
+        let mut changed: Vec<&Path> = decrypted
+            .iter()
+            .map(|(relative_path, _plaintext)| relative_path.as_path())
+            .collect();
+
+        changed.push(&config_file);
+        changed.push(&check_file);
+
+        self.commit_changes(&changed, &[], &Operation::new(OperationKind::Config), Some("chore: passphrase changed"))?;
+        self.lock_session()?.unlock(new_key);
+
+        self.notify(RepositoryEvent::ConfigChanged);
+
+        Ok(())
+    }
+
+
+    /// Exports the repository into a static HTML site.
+    /// 
+    /// Markdown notes are rendered into pages preserving folder structure,
+    /// wiki links (`[[note]]` or `[[note|title]]`) are converted into relative
+    /// links, other files and attachments are copied as is. The site contains
+    /// an index page with a tree of folders and a page per tag (e.g. `#todo`).
+    /// 
+    /// Encrypted notes are exported decrypted, so the repository MUST be 
+    /// unlocked if it contains encrypted notes.
+    /// 
+    /// * `destination` - path to a folder for the site (MUST be outside of
+    ///   repository's working directory)
+    pub fn export_html(&self, destination: &Path) -> Result<()> {
+        self.export_html_with(destination, &|| Ok(()))
+    }
+
+
+    /// Exports the repository into a static HTML site (see
+    /// [`Repository::export_html`]) checking for cancellation between files.
+    /// 
+    /// * `destination` - path to a folder for the site
+    /// * `check_cancelled` - function, that fails if the export is cancelled
+    pub(super) fn export_html_with(&self, destination: &Path, check_cancelled: &dyn Fn() -> Result<()>) -> Result<()> {
+        if destination.starts_with(self.get_workdir()?) {
+            return Err(Error::from_string("export destination is inside of repository", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(destination));
+        }
+
+        let notes = self.list_notes()?;
+        let resolver = wiki::LinkResolver::new(&notes, export::is_markdown);
+
+        //
+        // Render notes first, other files are copied as is
+        //
+
+        let mut pages = Vec::new();
+        for relative_path in &notes {
+            check_cancelled()?;
+
+            let content = self.read_content(relative_path)?;
+
+            if export::is_markdown(relative_path) {
+                let page = export::render_note(relative_path, &String::from_utf8_lossy(&content), &resolver);
+                write_exported_file(destination, page.get_path(), page.get_html().as_bytes())?;
+
+                pages.push(page);
+            }
+            else {
+                write_exported_file(destination, &export::get_exported_path(relative_path), &content)?;
+            }
+        }
+
+        for attachment in self.storage.list(Path::new(MM_ATTACHMENTS_FOLDER))? {
+            check_cancelled()?;
+            write_exported_file(destination, &attachment, &self.storage.read(&attachment)?)?;
+        }
+
+        //
+        // And now generated pages: index and tags
+        //
+
+        write_exported_file(destination, Path::new(export::MM_EXPORT_INDEX_PAGE), export::render_index(&pages).as_bytes())?;
+
+        let mut tags: Vec<&str> = pages
+            .iter()
+            .flat_map(|page| page.get_tags())
+            .map(String::as_str)
+            .collect();
+
+        tags.sort();
+        tags.dedup();
+
+        for tag in tags {
+            check_cancelled()?;
+            write_exported_file(destination, &export::get_tag_page_path(tag), export::render_tag_page(tag, &pages).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Exports the repository into an archive: notes, attachments and 
+    /// configuration folder. Encrypted notes are archived encrypted. Lock
+    /// files and local configuration are not archived.
+    /// 
+    /// History may be included only for storages, that keep it (e.g.
+    /// [`crate::data::GitStorage`]). It is archived as a git bundle with
+    /// all branches, so version control's configuration and hooks are
+    /// never archived.
+    /// 
+    /// * `archive_path` - path to an archive to create
+    /// * `format` - format of the archive
+    /// * `with_history` - `true` to include full history
+    pub fn export_archive(&self, archive_path: &Path, format: ArchiveFormat, with_history: bool) -> Result<()> {
+        self.export_archive_with(archive_path, format, with_history, &|| Ok(()))
+    }
+
+
+    /// Exports the repository into an archive (see [`Repository::export_archive`])
+    /// checking for cancellation between files.
+    /// 
+    /// * `archive_path` - path to an archive to create
+    /// * `format` - format of the archive
+    /// * `with_history` - `true` to include full history
+    /// * `check_cancelled` - function, that fails if the export is cancelled
+    pub(super) fn export_archive_with(&self, archive_path: &Path, format: ArchiveFormat, with_history: bool, check_cancelled: &dyn Fn() -> Result<()>) -> Result<()> {
+        let mut files: Vec<PathBuf> = self.storage
+            .list(Path::new(""))?
+            .into_iter()
+            .filter(|relative_path| helpers::is_archived_path(relative_path))
+            .collect();
+
+        let bundle = match with_history {
+            true => Some(self.storage
+                .bundle_history()?
+                .ok_or(Error::from_string("history of this storage cannot be exported", ErrorCategory::Repo))?),
+            false => None
+        };
+
+        //
+        // Configuration may be not saved yet, but it must be
+        // actual in the archive, so it is taken from memory
+        // (saving is not allowed in shared mode)
+        //
+
+        let config_file = helpers::get_config_file();
+        files.retain(|relative_path| *relative_path != config_file);
+
+        let mut archive = export::ArchiveWriter::create(archive_path, format)?;
+        archive.add_file(&config_file, &self.config.to_vec()?)?;
+        for relative_path in &files {
+            check_cancelled()?;
+            archive.add_file(relative_path, &self.storage.read(relative_path)?)?;
+        }
+
+        if let Some(bundle) = bundle {
+            archive.add_file(&helpers::get_history_bundle_file(), &bundle)?;
+        }
+
+        archive.finish()
+    }
+
+
+    /// Creates a new repository under repositories folder from an archive
+    /// produced by [`Repository::export_archive`]. Format is detected
+    /// automatically.
+    /// 
+    /// Archives with version control's internal files, lock files, local
+    /// configuration or malformed trash, identifiers or metadata files are
+    /// rejected before anything is written. History is restored from 
+    /// archive's bundle (only objects and branches are taken). If the archive
+    /// has no history, all its files are committed at once. Trash is not
+    /// purged, and a partially imported repository is removed on failure.
+    /// 
+    /// * `archive_path` - path to an archive to import
+    /// * `repo_name` - a name of repository to create (MUST NOT exist)
+    pub fn import_archive(archive_path: &Path, repo_name: &str) -> Result<Self> {
+        let repo_path = Repository::get_new_repo_path(repo_name)?;
+
+        Repository::import_archive_to(archive_path, &repo_path, repo_name)
+    }
+
+
+    /// Creates a new repository in a folder from an archive (see 
+    /// [`Repository::import_archive`]).
+    /// 
+    /// * `archive_path` - path to an archive to import
+    /// * `repo_path` - path to a folder of repository to create (MUST NOT exist)
+    /// * `repo_name` - a name of repository to create
+    fn import_archive_to(archive_path: &Path, repo_path: &Path, repo_name: &str) -> Result<Self> {
+        let mut files = export::read_archive(archive_path)?;
+
+        let bundle_file = helpers::get_history_bundle_file();
+        let bundle = files
+            .iter()
+            .position(|(relative_path, _content)| *relative_path == bundle_file)
+            .map(|index| files.remove(index).1);
+
+        if let Some((relative_path, _content)) = files.iter().find(|(relative_path, _content)| !helpers::is_archived_path(relative_path)) {
+            return Err(Error::from_string("archive contains a forbidden file", ErrorCategory::IO)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(relative_path));
+        }
+
+        //
+        // Paths of trashed files and notes are composed from
+        // these files, so they are checked before unpacking
+        //
+
+        for (relative_path, content) in &files {
+            let checked = match relative_path {
+                path if *path == helpers::get_trash_file() => trash::Trash::check(content),
+                path if *path == helpers::get_ids_file() => ids::NoteIds::check(content),
+                path if *path == helpers::get_metadata_file() => metadata::Metadata::check(content),
+                _ => Ok(())
+            };
+
+            checked.map_err(|err| err.with_path(relative_path))?;
+        }
+
+        let repo = Repository::unpack_archive(&files, bundle.as_deref(), repo_path, repo_name);
+        if repo.is_err() && repo_path.exists() {
+            fs::remove_dir_all(repo_path)?;
+        }
+
+        repo
+    }
+
+
+    /// Unpacks files of an archive into a new repository and commits them
+    /// or restores their history.
+    /// 
+    /// * `files` - files of the archive (paths are relative to working directory)
+    /// * `bundle` - history bundle of the archive (if any)
+    /// * `repo_path` - path to a folder of repository to create
+    /// * `repo_name` - a name of repository to create
+    fn unpack_archive(files: &[(PathBuf, Vec<u8>)], bundle: Option<&[u8]>, repo_path: &Path, repo_name: &str) -> Result<Self> {
+        //
+        // Files are unpacked and history is restored before
+        // opening a repository, so no initial commit is made
+        //
+
+        for (relative_path, content) in files {
+            write_exported_file(repo_path, relative_path, content)?;
+        }
+
+        let lock = RepositoryLock::acquire(&repo_path.join(helpers::get_locks_directory()), LockMode::Exclusive)?;
+        let storage = GitStorage::open_or_create(repo_path)?;
+        if let Some(bundle) = bundle {
+            storage.unbundle_history(bundle)?;
+        }
+
+        let repo = Repository::open_storage_internal(storage, repo_name, cfg::Config::new, Some(lock))?;
+
+        if bundle.is_none() {
+            let files = repo.storage.list(Path::new(""))?;
+            let changed: Vec<&Path> = files
+                .iter()
+                .map(PathBuf::as_path)
+                .filter(|relative_path| helpers::is_archived_path(relative_path))
+                .collect();
+
+            let operation = Operation::new(OperationKind::Import)
+                .with_changes(changed
+                    .iter()
+                    .filter(|relative_path| helpers::is_note_path(relative_path))
+                    .map(|relative_path| FileChange::new(ChangeAction::Added, relative_path, None)));
+
+            repo.commit_changes(&changed, &[], &operation, Some(MM_IMPORT_COMMIT_MESSAGE))?;
+        }
+
+        Ok(repo)
+    }
+
+
+    /// Creates a new repository under repositories folder from a folder with
+    /// Markdown notes or an Obsidian vault. Returns the repository and a report
+    /// with imported notes as well as skipped or problematic files.
+    /// 
+    /// Markdown notes keep their locations, wiki links (`[[note]]`) are
+    /// converted into regular relative links. Other files become attachments
+    /// (see [`Repository::add_attachment`]) and links to them are updated.
+    /// Tags from front matter and text are stored as notes' metadata (see
+    /// [`Repository::get_note_metadata`]) together with files' times. Hidden 
+    /// files and folders are not imported.
+    /// 
+    /// Obsidian's daily notes settings are converted into journal settings,
+    /// templates folder is imported as repository's templates. Everything
+    /// is committed at once.
+    /// 
+    /// * `source` - path to a folder to import
+    /// * `repo_name` - a name of repository to create (MUST NOT exist)
+    pub fn import_folder(source: &Path, repo_name: &str) -> Result<(Self, ImportReport)> {
+        if !source.is_dir() {
+            return Err(Error::from_string("invalid source folder path", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(source));
+        }
+
+        let repo_path = Repository::get_new_repo_path(repo_name)?;
+        if repo_path.starts_with(source) {
+            return Err(Error::from_string("source folder contains repositories folder", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(source));
+        }
+
+        Repository::import_folder_with(source, || Repository::open_or_create(Some(repo_name)))
+    }
+
+
+    /// Creates a new repository from a folder with Markdown notes or an
+    /// Obsidian vault (see [`Repository::import_folder`]).
+    /// 
+    /// * `source` - path to a folder to import
+    /// * `create_repo` - function, that creates an empty repository to import into
+    fn import_folder_with<F>(source: &Path, create_repo: F) -> Result<(Self, ImportReport)>
+    where
+        F: FnOnce() -> Result<Self>
+    {
+        let mut report = ImportReport::default();
+        let settings = import::ObsidianSettings::load(source, &mut report);
+
+        let files: Vec<PathBuf> = misc::list_files_recursive(source, |path| !import::is_hidden(path))?
+            .iter()
+            .filter_map(|file| file.strip_prefix(source).ok())
+            .map(Path::to_path_buf)
+            .collect();
+
+        let resolver = wiki::LinkResolver::new(&files, export::is_markdown);
+
+        //
+        // Files, that are not notes, become attachments. Their
+        // paths are needed to update links in notes later
+        //
+
+        let mut attachment_paths = HashMap::new();
+        let mut imported_files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+
+        for relative_path in files.iter().filter(|relative_path| !export::is_markdown(relative_path)) {
+            let source_path = source.join(relative_path);
+            let content = match fs::read(&source_path) {
+                Ok(content) => content,
+                Err(err) => {
+                    report.skip(&source_path, err.to_string());
+                    continue;
+                }
+            };
+
+            let attachment_path = Path::new(MM_ATTACHMENTS_FOLDER)
+                .join(attachments::get_attachment_name(&content, relative_path));
+
+            if !imported_files.iter().any(|(path, _content)| *path == attachment_path) {
+                imported_files.push((attachment_path.clone(), content));
+                report.add_attachment();
+            }
+
+            attachment_paths.insert(relative_path.clone(), attachment_path);
+        }
+
+        //
+        // Now notes and templates
+        //
+
+        let templates_folder = settings.get_templates_folder();
+        let mut daily_template = None;
+
+        let mut notes = Vec::new();
+        for relative_path in files.iter().filter(|relative_path| export::is_markdown(relative_path)) {
+            let source_path = source.join(relative_path);
+            let markdown = match fs::read(&source_path).map(String::from_utf8) {
+                Ok(Ok(markdown)) => markdown,
+                Ok(Err(_)) => {
+                    report.skip(&source_path, "not a valid UTF-8 text");
+                    continue;
+                },
+                Err(err) => {
+                    report.skip(&source_path, err.to_string());
+                    continue;
+                }
+            };
+
+            if let Some(template_name) = templates_folder.and_then(|folder| relative_path.strip_prefix(folder).ok()) {
+                let variables = HashMap::from([
+                    ("date".to_owned(), String::new()),
+                    ("title".to_owned(), String::new()),
+                    ("author".to_owned(), String::new()),
+                ]);
+
+                if templates::render(&markdown, &variables).is_err() {
+                    report.warn(&source_path, "template contains unsupported placeholders");
+                }
+
+                if settings.get_daily_template().is_some_and(|template| template.with_extension("") == relative_path.with_extension("")) {
+                    daily_template = Some(template_name.to_string_lossy().into_owned());
+                }
+
+                imported_files.push((helpers::get_templates_directory().join(template_name), markdown.into_bytes()));
+                continue;
+            }
+
+            let content = import::rewrite_links(relative_path, Path::new(""), &markdown, &resolver, &attachment_paths,
+                |problem| report.warn(&source_path, problem));
+
+            let times = fs::metadata(&source_path).ok();
+            let note_metadata = NoteMetadata::new(
+                wiki::collect_note_tags(&markdown),
+                times.as_ref().and_then(|times| times.created().ok()),
+                times.as_ref().and_then(|times| times.modified().ok())
+            );
+
+            notes.push(import::ImportedNote::new(relative_path.clone(), content.into_bytes(), note_metadata));
+        }
+
+        if settings.get_daily_template().is_some() && daily_template.is_none() {
+            report.warn(source, "daily notes template is not located in templates folder");
+        }
+
+        if settings.get_daily_folder().is_some_and(|folder| journal::check_folder(folder).is_err()) {
+            report.warn(source, "daily notes folder is not located inside of the vault");
+        }
+
+        if settings.get_daily_pattern().is_some_and(|pattern| journal::check_pattern(pattern).is_err()) {
+            report.warn(source, "daily notes format is not supported");
+        }
+
+        //
+        // Everything is ready, so the repository may be created
+        //
+
+        let mut repo = create_repo()?;
+
+        if let Some(folder) = settings.get_daily_folder().filter(|folder| journal::check_folder(folder).is_ok()) {
+            repo.config.set_journal_folder(Some(folder));
+        }
+
+        if let Some(pattern) = settings.get_daily_pattern().filter(|pattern| journal::check_pattern(pattern).is_ok()) {
+            repo.config.set_journal_pattern(Some(pattern));
+        }
+
+        if let Some(template) = &daily_template {
+            repo.config.set_journal_template(Some(template));
+        }
+
+        let workdir = repo.get_workdir()?.to_path_buf();
+        for note in &notes {
+            report.add_imported(workdir.join(note.get_relative_path()));
+        }
+
+        let message = report.to_commit_message(&format!("chore: imported from {:?}", source));
+        repo.commit_import(&notes, &imported_files, &message, None)?;
+
+        Ok((repo, report))
+    }
+
+
+    /// Imports notes from an Evernote export file (`.enex`) into a folder of
+    /// the repository. Returns a report with imported notes and problems.
+    /// 
+    /// Notes are converted from ENML into Markdown and named after their
+    /// titles. Embedded resources (images, files) become attachments (see 
+    /// [`Repository::add_attachment`]), creation and modification dates as
+    /// well as tags are stored as notes' metadata (see 
+    /// [`Repository::get_note_metadata`]). Everything is committed at once.
+    /// 
+    /// * `enex_path` - path to an export file
+    /// * `folder_path` - absolute path to a folder to import notes into 
+    ///   (it is created if necessary)
+    pub fn import_enex(&self, enex_path: &Path, folder_path: &Path) -> Result<ImportReport> {
+        self.import_enex_with(enex_path, folder_path, &|| Ok(()))
+    }
+
+
+    /// Imports notes from an Evernote export file (see [`Repository::import_enex`])
+    /// checking for cancellation between notes. Nothing is imported if the
+    /// import is cancelled.
+    /// 
+    /// * `enex_path` - path to an export file
+    /// * `folder_path` - absolute path to a folder to import notes into
+    /// * `check_cancelled` - function, that fails if the import is cancelled
+    pub(super) fn import_enex_with(&self, enex_path: &Path, folder_path: &Path, check_cancelled: &dyn Fn() -> Result<()>) -> Result<ImportReport> {
+        let folder = self.get_import_folder(folder_path)?;
+
+        let xml = String::from_utf8(fs::read(enex_path)?)
+            .map_err(|_| Error::from_string("export file is not a valid UTF-8 text", ErrorCategory::IO))?;
+
+        let mut report = ImportReport::default();
+        let enex_notes = import::parse_enex(&xml, |title, problem| {
+            report.warn(enex_path, format!("note '{}': {}", title, problem))
+        })?;
+
+        let mut notes = Vec::with_capacity(enex_notes.len());
+        let mut imported_files: Vec<(PathBuf, Vec<u8>)> = Vec::new();
+
+        for enex_note in &enex_notes {
+            check_cancelled()?;
+
+            //
+            // Notes with equal titles get numbered names
+            //
+
+            let relative_path = self.get_import_note_path(folder, enex_note.get_title(), 
+                notes.iter().map(import::ImportedNote::get_relative_path));
+
+            let note_path = folder_path.join(relative_path.strip_prefix(folder)?);
+
+            let mut attachment_names = HashMap::new();
+            for resource in enex_note.get_resources() {
+                let attachment_name = attachments::get_attachment_name(resource.get_data(), Path::new(&resource.get_file_name()));
+                let attachment_path = Path::new(MM_ATTACHMENTS_FOLDER).join(&attachment_name);
+
+                if !imported_files.iter().any(|(path, _content)| *path == attachment_path) {
+                    imported_files.push((attachment_path, resource.get_data().to_vec()));
+                    report.add_attachment();
+                }
+
+                attachment_names.insert(resource.get_hash(), (attachment_name, resource));
+            }
+
+            let mut problems = Vec::new();
+            let markdown = import::convert_enml(enex_note.get_content(), 
+                |hash, _mime| {
+                    attachment_names.get(hash).map(|(attachment_name, resource)| {
+                        let file_name = resource.get_file_name();
+                        let title = Path::new(&file_name)
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+
+                        attachments::compose_link(&relative_path, attachment_name, &title)
+                    })
+                },
+                |problem| problems.push(problem));
+
+            for problem in problems {
+                report.warn(&note_path, problem);
+            }
+
+            let markdown = match markdown {
+                Ok(markdown) => markdown,
+                Err(err) => {
+                    report.skip(&note_path, err.to_string());
+                    continue;
+                }
+            };
+
+            let note_metadata = NoteMetadata::new(
+                enex_note.get_tags().to_vec(),
+                enex_note.get_created(),
+                enex_note.get_updated()
+            );
+
+            report.add_imported(note_path);
+            notes.push(import::ImportedNote::new(relative_path, markdown.into_bytes(), note_metadata));
+        }
+
+        check_cancelled()?;
+
+        let message = report.to_commit_message(&format!("chore: imported from {:?}", enex_path));
+        self.commit_import(&notes, &imported_files, &message, None)?;
+
+        Ok(report)
+    }
+
+
+    /// Imports notes from a Joplin export archive (`.jex`) into a folder of
+    /// the repository. Returns a report with imported notes and problems.
+    /// 
+    /// Notebooks become folders, notes are named after their titles. Resources
+    /// become attachments (see [`Repository::add_attachment`]) and Joplin's 
+    /// links (`:/<id>`) to notes and resources are converted into relative 
+    /// links. Creation and modification times as well as tags are stored as
+    /// notes' metadata (see [`Repository::get_note_metadata`]).
+    /// 
+    /// Each note is committed separately as of its modification time (from
+    /// the oldest note to the newest one) together with attachments it refers
+    /// to. The last commit contains the rest of attachments and a summary.
+    /// 
+    /// * `jex_path` - path to an export archive
+    /// * `folder_path` - absolute path to a folder to import notes into 
+    ///   (it is created if necessary)
+    pub fn import_jex(&self, jex_path: &Path, folder_path: &Path) -> Result<ImportReport> {
+        self.import_jex_with(jex_path, folder_path, &|| Ok(()))
+    }
+
+
+    /// Imports notes from a Joplin export archive (see [`Repository::import_jex`])
+    /// checking for cancellation between notes. Notes committed before
+    /// cancellation remain in the repository.
+    /// 
+    /// * `jex_path` - path to an export archive
+    /// * `folder_path` - absolute path to a folder to import notes into
+    /// * `check_cancelled` - function, that fails if the import is cancelled
+    pub(super) fn import_jex_with(&self, jex_path: &Path, folder_path: &Path, check_cancelled: &dyn Fn() -> Result<()>) -> Result<ImportReport> {
+        let folder = self.get_import_folder(folder_path)?;
+
+        let mut report = ImportReport::default();
+        let export = import::JexExport::read(jex_path, |problem| report.warn(jex_path, problem))?;
+
+        //
+        // Paths of all notes and attachments are needed to convert links
+        //
+
+        let mut note_paths: HashMap<&str, PathBuf> = HashMap::new();
+        for jex_note in export.get_notes() {
+            let note_folder = folder.join(export.get_folder_path(jex_note));
+            let relative_path = self.get_import_note_path(&note_folder, jex_note.get_title(), 
+                note_paths.values().map(PathBuf::as_path));
+
+            note_paths.insert(jex_note.get_id(), relative_path);
+        }
+
+        let attachment_paths: HashMap<&str, PathBuf> = export
+            .get_resources()
+            .iter()
+            .map(|(id, resource)| {
+                let attachment_name = attachments::get_attachment_name(resource.get_data(), Path::new(resource.get_file_name()));
+                (id.as_str(), Path::new(MM_ATTACHMENTS_FOLDER).join(attachment_name))
+            })
+            .collect();
+
+        let mut jex_notes: Vec<_> = export.get_notes().iter().collect();
+        jex_notes.sort_by_key(|jex_note| jex_note.get_updated().or(jex_note.get_created()));
+
+        let mut committed_attachments = Vec::new();
+        for jex_note in jex_notes {
+            check_cancelled()?;
+
+            let relative_path = &note_paths[jex_note.get_id()];
+            let note_path = folder_path.join(relative_path.strip_prefix(folder)?);
+
+            let mut referred = Vec::new();
+            let mut problems = Vec::new();
+            let markdown = import::rewrite_joplin_links(jex_note.get_body(), 
+                |id| {
+                    let target = note_paths
+                        .get(id)
+                        .or_else(|| attachment_paths.get(id).inspect(|_path| referred.push(id.to_owned())))?;
+
+                    Some(wiki::get_relative_link(relative_path, target))
+                },
+                |problem| problems.push(problem));
+
+            for problem in problems {
+                report.warn(&note_path, problem);
+            }
+
+            //
+            // Attachments are committed together with the first
+            // note, that refers to them
+            //
+
+            let mut files = Vec::new();
+            for id in referred {
+                let attachment_path = &attachment_paths[id.as_str()];
+                if !committed_attachments.contains(attachment_path) {
+                    files.push((attachment_path.clone(), export.get_resources()[&id].get_data().to_vec()));
+                    committed_attachments.push(attachment_path.clone());
+                    report.add_attachment();
+                }
+            }
+
+            let note_metadata = NoteMetadata::new(
+                jex_note.get_tags().to_vec(),
+                jex_note.get_created(),
+                jex_note.get_updated()
+            );
+
+            let note = import::ImportedNote::new(relative_path.clone(), markdown.into_bytes(), note_metadata);
+            let message = format!("chore: imported {:?}", jex_note.get_title());
+
+            self.commit_import(&[note], &files, &message, jex_note.get_updated().or(jex_note.get_created()))?;
+            report.add_imported(note_path);
+        }
+
+        //
+        // The rest of attachments is committed at once with a summary
+        //
+
+        let mut files = Vec::new();
+        for (id, attachment_path) in attachment_paths {
+            if !committed_attachments.contains(&attachment_path) {
+                files.push((attachment_path.clone(), export.get_resources()[id].get_data().to_vec()));
+                committed_attachments.push(attachment_path);
+                report.add_attachment();
+            }
+        }
+
+        let message = report.to_commit_message(&format!("chore: imported from {:?}", jex_path));
+        self.commit_import(&[], &files, &message, None)?;
+
+        Ok(report)
+    }
+
+
+    /// Converts an absolute path to a folder to import notes into into
+    /// a path relative to working directory. The folder MUST NOT be a
+    /// file or a service folder.
+    /// 
+    /// * `folder_path` - absolute path to a folder
+    fn get_import_folder<'a>(&self, folder_path: &'a Path) -> Result<&'a Path> {
+        let folder = self.get_relative_path(folder_path)?;
+        if !helpers::is_note_path(folder) || (self.storage.exists(folder) && !self.storage.is_folder(folder)) {
+            return Err(Error::from_string("invalid absolute folder path", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(folder_path));
+        }
+
+        Ok(folder)
+    }
+
+
+    /// Composes a path to an imported note from its title. Notes with 
+    /// equal titles get numbered names.
+    /// 
+    /// * `folder` - folder relative to working directory to place the note into
+    /// * `title` - title of the note
+    /// * `taken` - paths of other imported notes
+    fn get_import_note_path<'a, I>(&self, folder: &Path, title: &str, taken: I) -> PathBuf
+    where
+        I: Iterator<Item = &'a Path> + Clone
+    {
+        let name = import::get_note_file_name(title);
+
+        (1..)
+            .map(|number| match number {
+                1 => folder.join(format!("{}.md", name)),
+                number => folder.join(format!("{} ({}).md", name, number))
+            })
+            .find(|path| !self.storage.exists(path) && !taken.clone().any(|taken| taken == path))
+            .unwrap_or_default()
+    }
+
+
+    /// Returns a path for a new repository under repositories folder. Fails
+    /// if the repository already exists.
+    /// 
+    /// * `repo_name` - a name of repository
+    fn get_new_repo_path(repo_name: &str) -> Result<PathBuf> {
+        Repository::create_repos_folder()?;
+
+        let repo_path = helpers::get_repo_path(&Some(repo_name))
+            .ok_or(Error::from_string("cannot get repository path", ErrorCategory::Os))?;
+
+        if repo_path.exists() {
+            return Err(Error::from_string(format!("repository '{}' already exists", repo_name), ErrorCategory::Repo)
+                .with_kind(ErrorKind::AlreadyExists)
+                .with_path(repo_path));
+        }
+
+        Ok(repo_path)
+    }
+
+
+    /// Creates repositories folder if necessary.
+    fn create_repos_folder() -> Result<()> {
+        //
+        // App may be run for the first time or data may be erased, so
+        // we need to create the folder if necessary
+        //
+
+        if !helpers::is_repos_folder_present() {
+            helpers::get_repos_folder()
+                .ok_or(Error::from_string("cannot get repositories folder", ErrorCategory::Os))
+                .and_then(misc::create_folder_recursive)?;
+        }
+
+        Ok(())
+    }
+
+
+    /// Internal constructor, that wraps a storage and initializes it if 
+    /// necessary. Expired notes in trash are purged.
+    /// 
+    /// * `storage` - storage to use
+    /// * `repo_name` - a name of repository
+    /// * `default_config` - function, that creates a configuration for new storages
+    /// * `lock` - lock of the repository (if any)
+    fn from_storage_internal<S, F>(storage: S, repo_name: &str, default_config: F, lock: Option<RepositoryLock>) -> Result<Self>
+    where
+        S: Storage + 'static,
+        F: FnOnce() -> Result<cfg::Config>
+    {
+        let repo = Repository::open_storage_internal(storage, repo_name, default_config, lock)?;
+
+        //
+        // Expired notes in trash are purged at opening too,
+        // because a repository may be not modified for a long time
+        //
+
+        if repo.is_writable() {
+            repo.purge_trash()?;
+        }
+
+        Ok(repo)
+    }
+
+
+    /// Internal constructor, that wraps a storage and initializes it if 
+    /// necessary. Unlike [`Repository::from_storage_internal`], trash is
+    /// left intact.
+    /// 
+    /// * `storage` - storage to use
+    /// * `repo_name` - a name of repository
+    /// * `default_config` - function, that creates a configuration for new storages
+    /// * `lock` - lock of the repository (if any)
+    fn open_storage_internal<S, F>(storage: S, repo_name: &str, default_config: F, lock: Option<RepositoryLock>) -> Result<Self>
+    where
+        S: Storage + 'static,
+        F: FnOnce() -> Result<cfg::Config>
+    {
+        let config_file = helpers::get_config_file();
+        let local_config_file = helpers::get_local_config_file();
+
+        //
+        // Local configuration and lock files are excluded from
+        // version control before anything is committed
+        //
+
+        if lock.as_ref().is_none_or(|lock| lock.get_mode() != LockMode::Shared) {
+            storage.exclude(&[&local_config_file, &helpers::get_locks_directory()])?;
+        }
+
+        //
+        // Storage may be new, hence it needs a configuration file
+        //
+
+        let mut config = if storage.exists(&config_file) {
+            cfg::Config::from_slice(&storage.read(&config_file)?)?
+        }
+        else {
+            let config = default_config()?;
+
+            storage.create_folder(&helpers::get_config_directory())?;
+            storage.write(&config_file, &config.to_vec()?)?;
+            storage.commit(config.query_name()?, config.query_email()?, &[&config_file], &[], MM_INITIAL_COMMIT_MESSAGE, None)?;
+
+            config
+        };
+
+        if storage.exists(&local_config_file) {
+            config.load_local(&storage.read(&local_config_file)?)?;
+        }
+
+        Ok(Repository {
+            storage: Box::new(storage),
+            name: repo_name.to_owned(),
+            config,
+            session: Mutex::new(crypto::Session::new()),
+            lock,
+            observers: Mutex::new(events::Observers::default())
+        })
+    }
+
+
+    /// Permanently removes notes from trash. Returns number of removed notes.
+    /// 
+    /// * `keep` - function, that decides if an entry should stay in trash
+    fn remove_from_trash<F>(&self, keep: F) -> Result<usize>
+    where
+        F: FnMut(&TrashEntry) -> bool
+    {
+        let trash_file = helpers::get_trash_file();
+
+        let mut trash = trash::Trash::load(self.storage.as_ref(), &trash_file)?;
+        let removed = trash.take_unless(keep);
+
+        if removed.is_empty() {
+            return Ok(0);
+        }
+
+        //
+        // Metadata of removed notes is not needed anymore
+        //
+
+        let metadata_file = self.update_metadata(|metadata| {
+            metadata.retain(|id| !removed.iter().any(|entry| entry.get_note_id() == Some(id)));
+        })?;
+
+        //
+        // Entry's folder may be already removed (e.g. by hand),
+        // then there is nothing to remove
+        //
+
+        let mut trashed_paths = Vec::with_capacity(removed.len());
+        for entry in &removed {
+            let trashed_path = entry.get_trashed_path();
+            if let Some(entry_folder) = trashed_path.parent().filter(|folder| self.storage.exists(folder)) {
+                self.storage.delete(entry_folder)?;
+            }
+
+            trashed_paths.push(trashed_path);
+        }
+
+        trash.save(self.storage.as_ref(), &trash_file)?;
+
+        let removed_paths: Vec<&Path> = trashed_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+
+        let operation = Operation::new(OperationKind::Purge)
+            .with_changes(removed.iter().map(|entry| FileChange::new(ChangeAction::Removed, entry.get_original_path(), entry.get_note_id())));
+
+        self.commit_changes(&[&trash_file, &metadata_file], &removed_paths, &operation, None)?;
+
+        Ok(removed.len())
+    }
+
+
+    /// Adds a note to repository (internal implementation).
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    fn add_note_internal(&self, relative_path: &Path) -> Result<()> {
+        //
+        // Let's add folder first (skip step for notes in repository's root)
+        //
+
+        if let Some(parent) = relative_path.parent() {
+            self.add_folder_internal(parent)?;
+        }
+
+        //
+        // Note may be written outside of the library, so it is
+        // encrypted here to never commit plain content
+        //
+
+        self.encrypt_if_required(relative_path)?;
+
+        //
+        // And now add note itself with its identifier
+        //
+
+        let mut is_new = false;
+        let mut note_id = String::new();
+        let ids_file = self.update_note_ids(|ids| {
+            is_new = ids.id_of(relative_path).is_none();
+            note_id = ids.assign(relative_path);
+        })?;
+
+        let operation = match is_new {
+            true => Operation::new(OperationKind::Add).with_change(ChangeAction::Added, relative_path, Some(&note_id)),
+            false => Operation::new(OperationKind::Update).with_change(ChangeAction::Modified, relative_path, Some(&note_id))
+        };
+
+        self.commit_changes(&[relative_path, &ids_file], &[], &operation, None)?;
+
+        let note_path = self.get_workdir()?.join(relative_path);
+        self.notify(match is_new {
+            true => RepositoryEvent::NoteCreated(note_path),
+            false => RepositoryEvent::NoteUpdated(note_path)
+        });
+
+        Ok(())
+    }
+
+
+    /// Writes imported notes and files, assigns identifiers and metadata
+    /// to the notes and commits everything at once together with
+    /// configuration.
+    /// 
+    /// * `notes` - notes to write
+    /// * `files` - other files (e.g. attachments) by their relative paths
+    /// * `message` - commit message
+    /// * `time` - time of the commit (`None` means current time)
+    fn commit_import(&self, notes: &[import::ImportedNote], files: &[(PathBuf, Vec<u8>)], message: &str, time: Option<SystemTime>) -> Result<()> {
+        for (relative_path, content) in files {
+            self.storage.write(relative_path, content)?;
+        }
+
+        for note in notes {
+            self.write_content(note.get_relative_path(), note.get_content())?;
+        }
+
+        let mut note_ids = Vec::with_capacity(notes.len());
+        let ids_file = self.update_note_ids(|ids| {
+            note_ids.extend(notes.iter().map(|note| ids.assign(note.get_relative_path())));
+        })?;
+
+        let metadata_file = self.update_metadata(|metadata| {
+            for (note, id) in notes.iter().zip(&note_ids) {
+                metadata.set(id, note.get_metadata());
+            }
+        })?;
+
+        let config_file = self.save_config()?;
+
+        let mut changed: Vec<&Path> = files
+            .iter()
+            .map(|(relative_path, _content)| relative_path.as_path())
+            .chain(notes.iter().map(import::ImportedNote::get_relative_path))
+            .collect();
+
+        changed.extend([ids_file.as_path(), metadata_file.as_path(), config_file.as_path()]);
+
+        let operation = Operation::new(OperationKind::Import)
+            .with_changes(notes
+                .iter()
+                .zip(&note_ids)
+                .map(|(note, id)| FileChange::new(ChangeAction::Added, note.get_relative_path(), Some(id))));
+
+        self.commit_changes_at(&changed, &[], &operation, Some(message), time)?;
+
+        let workdir = self.get_workdir()?;
+        for note in notes {
+            self.notify(RepositoryEvent::NoteCreated(workdir.join(note.get_relative_path())));
+        }
+
+        Ok(())
+    }
+
+
+    /// Commits changes of watched files, that were made outside of the library
+    /// (see [`super::RepositoryWatcher`]). Renames are detected the same way
+    /// as by [`Repository::commit_pending`], so moved notes keep their
+    /// identifiers and are reported as moved. Returns number of committed
+    /// notes.
+    /// 
+    /// * `paths` - absolute paths to changed files or folders
+    #[cfg(feature = "watcher")]
+    pub(super) fn commit_watched_changes(&self, paths: &[PathBuf]) -> Result<usize> {
+        //
+        // Only renames of watched paths are taken, other pending
+        // changes are left for their own events
+        //
+
+        let mut paths = paths.to_vec();
+        let mut renamed = Vec::new();
+        for entry in self.status()? {
+            if let StatusKind::Renamed { from } = entry.get_kind() {
+                let to = entry.get_path();
+                if paths.iter().any(|path| from.starts_with(path) || to.starts_with(path)) {
+                    renamed.push((from.clone(), to.to_path_buf()));
+                    paths.extend([from.clone(), to.to_path_buf()]);
+                }
+            }
+        }
+
+        self.commit_external_changes(&paths, &renamed, None)
+    }
+
+
+    /// Commits changes of notes, that were made outside of the library (e.g.
+    /// by external editors). Paths outside of working directory, internal
+    /// files and unchanged files are skipped. Moved notes keep their
+    /// identifiers. Returns number of committed notes.
+    /// 
+    /// * `paths` - absolute paths to changed notes or folders
+    /// * `renamed` - absolute previous and new paths of moved notes
+    /// * `message` - commit message (`None` means generated one)
+    pub(super) fn commit_external_changes(&self, paths: &[PathBuf], renamed: &[(PathBuf, PathBuf)], message: Option<&str>) -> Result<usize> {
+        //
+        // A note is considered moved only if it is absent at its
+        // previous location. Otherwise both paths are handled as
+        // separate changes
+        //
+
+        let mut moved = Vec::new();
+        for (from, to) in renamed {
+            if let (Some(from), Some(to)) = (self.get_external_path(from), self.get_external_path(to)) {
+                if !self.storage.exists(from) && self.storage.exists(to) && !self.storage.is_folder(to) {
+                    moved.push((from.to_path_buf(), to.to_path_buf()));
+                }
+            }
+        }
+
+        //
+        // Folders are expanded, because notes may be moved
+        // into working directory together with their folder
+        //
+
+        let mut candidates = Vec::new();
+        for path in paths {
+            let relative_path = match self.get_external_path(path) {
+                Some(relative_path) => relative_path,
+                None => continue
+            };
+
+            if self.storage.is_folder(relative_path) {
+                candidates.extend(self.storage.list(relative_path)?
+                    .into_iter()
+                    .filter(|relative_path| helpers::is_note_path(relative_path)));
+            }
+            else {
+                candidates.push(relative_path.to_path_buf());
+            }
+        }
+
+        candidates.retain(|candidate| !moved.iter().any(|(from, to)| candidate == from || candidate == to));
+        candidates.sort();
+        candidates.dedup();
+
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        for relative_path in candidates {
+            if !self.storage.is_changed(&relative_path)? {
+                continue;
+            }
+
+            //
+            // Candidates are sorted, so a removed folder precedes
+            // its content, that is removed together with it
+            //
+
+            match self.storage.exists(&relative_path) {
+                true => changed.push(relative_path),
+                false if removed.last().is_some_and(|folder: &PathBuf| relative_path.starts_with(folder)) => {},
+                false => removed.push(relative_path)
+            }
+        }
+
+        if changed.is_empty() && removed.is_empty() && moved.is_empty() {
+            return Ok(0);
+        }
+
+        for relative_path in changed.iter().chain(moved.iter().map(|(_from, to)| to)) {
+            if helpers::is_note_path(relative_path) {
+                self.encrypt_if_required(relative_path)?;
+            }
+        }
+
+        //
+        // Removed path may be a folder, so removed notes are
+        // taken from identifiers map. Notes without identifiers
+        // are reported by their paths
+        //
+
+        let mut changes = Vec::new();
+        let ids_file = self.update_note_ids(|ids| {
+            for relative_path in &changed {
+                let action = match ids.id_of(relative_path) {
+                    Some(_id) => ChangeAction::Modified,
+                    None => ChangeAction::Added
+                };
+
+                changes.push(FileChange::new(action, relative_path, Some(&ids.assign(relative_path))));
+            }
+
+            for (from, to) in &moved {
+                ids.rename(from, to);
+                changes.push(FileChange::new(ChangeAction::Moved { from: from.clone() }, to, Some(&ids.assign(to))));
+            }
+
+            for relative_path in &removed {
+                match ids.remove_under(relative_path) {
+                    notes if notes.is_empty() => changes.push(FileChange::new(ChangeAction::Removed, relative_path, None)),
+                    notes => changes.extend(notes
+                        .iter()
+                        .map(|(relative_path, id)| FileChange::new(ChangeAction::Removed, relative_path, Some(id))))
+                }
+            }
+        })?;
+
+        let summary = match (message, changes.len()) {
+            (Some(message), _) => Some(message.to_owned()),
+            (None, 1) => None,
+            (None, count) => Some(format!("chore: {} notes changed externally", count))
+        };
+
+        let mut changed_paths: Vec<&Path> = changed
+            .iter()
+            .chain(moved.iter().map(|(_from, to)| to))
+            .map(PathBuf::as_path)
+            .collect();
+
+        changed_paths.push(&ids_file);
+
+        let removed_paths: Vec<&Path> = removed
+            .iter()
+            .chain(moved.iter().map(|(from, _to)| from))
+            .map(PathBuf::as_path)
+            .collect();
+
+        let operation = Operation::new(OperationKind::External)
+            .with_changes(changes);
+
+        self.commit_changes(&changed_paths, &removed_paths, &operation, summary.as_deref())?;
+
+        self.notify_changes(operation.get_changes())?;
+
+        Ok(operation.get_changes().len())
+    }
+
+
+    /// Applies changes of an undo or redo operation to working directory
+    /// and commits them. Notes, that are added back, are taken from trash
+    /// (or from history if they are not in trash anymore).
+    /// 
+    /// * `operation` - operation to apply (paths MUST be relative to the
+    ///   repository's working directory)
+    /// * `revision_id` - revision with contents of added and modified notes
+    fn apply_operation(&self, operation: &Operation, revision_id: Option<&str>) -> Result<()> {
+        self.check_writable()?;
+
+        //
+        // All changes are checked first, so working
+        // directory is never changed partially
+        //
+
+        let pending: Vec<PathBuf> = self.storage.status()?
+            .into_iter()
+            .flat_map(|entry| match entry.get_kind() {
+                StatusKind::Renamed { from } => vec![from.clone(), entry.get_path().to_path_buf()],
+                _ => vec![entry.get_path().to_path_buf()]
+            })
+            .collect();
+
+        for change in operation.get_changes() {
+            self.check_change(change, &pending)?;
+        }
+
+        let ids_file = helpers::get_ids_file();
+        let trash_file = helpers::get_trash_file();
+
+        let mut ids = ids::NoteIds::load(self.storage.as_ref(), &ids_file)?;
+        let mut trash = trash::Trash::load(self.storage.as_ref(), &trash_file)?;
+
+        let mut changed = vec![ids_file.clone(), trash_file.clone()];
+        let mut removed = Vec::new();
+
+        for change in operation.get_changes() {
+            let relative_path = change.get_path();
+
+            match change.get_action() {
+                ChangeAction::Removed => {
+                    let trashed_path = trash
+                        .push(relative_path, ids.remove(relative_path))
+                        .get_trashed_path();
+
+                    self.storage.rename(relative_path, &trashed_path)?;
+
+                    changed.push(trashed_path);
+                    removed.push(relative_path.to_path_buf());
+                    continue;
+                },
+                ChangeAction::Moved { from } => {
+                    self.storage.rename(from, relative_path)?;
+                    ids.rename(from, relative_path);
+
+                    removed.push(from.clone());
+                },
+                ChangeAction::Added => {
+                    let entry_id = trash
+                        .find_note(relative_path, change.get_note_id())
+                        .map(|entry| entry.get_id().to_owned());
+
+                    match entry_id.and_then(|entry_id| trash.take(&entry_id)) {
+                        Some(entry) => {
+                            let trashed_path = entry.get_trashed_path();
+
+                            self.storage.rename(&trashed_path, relative_path)?;
+                            if let Some(entry_folder) = trashed_path.parent() {
+                                self.storage.delete(entry_folder)?;
+                            }
+
+                            removed.push(trashed_path);
+                        },
+                        None => {
+                            //
+                            // A removed folder is restored with all its
+                            // files, that had no identifiers at removal
+                            //
+
+                            let restored = self.restore_content(relative_path, revision_id)?;
+                            if restored.iter().any(|path| path != relative_path) {
+                                changed.extend(restored);
+                                continue;
+                            }
+                        }
+                    }
+                },
+                ChangeAction::Modified => {
+                    self.restore_content(relative_path, revision_id)?;
+                }
+            }
+
+            match change.get_note_id() {
+                Some(note_id) => ids.insert(note_id, relative_path),
+                None => {
+                    ids.assign(relative_path);
+                }
+            }
+
+            changed.push(relative_path.to_path_buf());
+        }
+
+        ids.save(self.storage.as_ref(), &ids_file)?;
+        trash.save(self.storage.as_ref(), &trash_file)?;
+
+        let changed_paths: Vec<&Path> = changed
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+
+        let removed_paths: Vec<&Path> = removed
+            .iter()
+            .map(PathBuf::as_path)
+            .collect();
+
+        self.commit_changes(&changed_paths, &removed_paths, operation, None)?;
+        self.notify_changes(operation.get_changes())
+    }
+
+
+    /// Checks if a change of an undo or redo operation may be applied to
+    /// working directory.
+    /// 
+    /// * `change` - change to check (paths are relative)
+    /// * `pending` - relative paths of files with uncommitted changes
+    fn check_change(&self, change: &FileChange, pending: &[PathBuf]) -> Result<()> {
+        let relative_path = change.get_path();
+        let (source, destination) = match change.get_action() {
+            ChangeAction::Added => (None, Some(relative_path)),
+            ChangeAction::Modified | ChangeAction::Removed => (Some(relative_path), None),
+            ChangeAction::Moved { from } => (Some(from.as_path()), Some(relative_path))
+        };
+
+        let workdir = self.get_workdir()?;
+
+        if let Some(source) = source {
+            if !self.storage.exists(source) {
+                return Err(Error::from_string("note does not exist anymore", ErrorCategory::Repo)
+                    .with_kind(ErrorKind::NotFound)
+                    .with_path(workdir.join(source)));
+            }
+
+            if pending.iter().any(|path| path == source) {
+                return Err(Error::from_string("note has uncommitted changes", ErrorCategory::Repo)
+                    .with_kind(ErrorKind::Conflict)
+                    .with_path(workdir.join(source)));
+            }
+        }
+
+        if let Some(destination) = destination {
+            if self.storage.exists(destination) {
+                return Err(Error::from_string("another note exists at the path", ErrorCategory::Repo)
+                    .with_kind(ErrorKind::AlreadyExists)
+                    .with_path(workdir.join(destination)));
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /// Writes content of a note or a folder with all its files as of
+    /// a revision. Returns relative paths of restored files.
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    ///   or the folder
+    /// * `revision_id` - revision to take content from
+    fn restore_content(&self, relative_path: &Path, revision_id: Option<&str>) -> Result<Vec<PathBuf>> {
+        let not_found = |path: &Path| Error::from_string("content of note not found in history", ErrorCategory::Repo)
+            .with_kind(ErrorKind::NotFound)
+            .with_path(self.storage.get_root().join(path));
+
+        let revision_id = revision_id.ok_or_else(|| not_found(relative_path))?;
+        let files = self.storage.list_revision(relative_path, revision_id)?;
+        if files.is_empty() {
+            return Err(not_found(relative_path));
+        }
+
+        for file in &files {
+            let content = self.storage
+                .read_revision(file, revision_id)?
+                .ok_or_else(|| not_found(file))?;
+
+            self.storage.write(file, &content)?;
+        }
+
+        Ok(files)
+    }
+
+
+    /// Delivers events about changes of notes made by an operation.
+    /// 
+    /// * `changes` - changes of notes (paths are relative)
+    fn notify_changes(&self, changes: &[FileChange]) -> Result<()> {
+        let workdir = self.get_workdir()?;
+
+        for change in changes {
+            let path = workdir.join(change.get_path());
+            self.notify(match change.get_action() {
+                ChangeAction::Added => RepositoryEvent::NoteCreated(path),
+                ChangeAction::Moved { from } => RepositoryEvent::NoteMoved {
+                    from: workdir.join(from),
+                    to: path
+                },
+                ChangeAction::Removed => RepositoryEvent::NoteDeleted(path),
+                ChangeAction::Modified => RepositoryEvent::NoteUpdated(path)
+            });
+        }
+
+        Ok(())
+    }
+
+
+    /// Converts an absolute path of an externally changed file into a path
+    /// relative to working directory. Returns `None` for paths outside of
+    /// working directory and for internal files.
+    /// 
+    /// * `path` - absolute path to a file
+    fn get_external_path<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        let relative_path = path
+            .strip_prefix(self.storage.get_root())
+            .ok()
+            .filter(|relative_path| !relative_path.as_os_str().is_empty())?;
+
+        let is_internal = !helpers::is_note_path(relative_path) || self.storage
+            .get_history_folder()
+            .is_some_and(|folder| relative_path.starts_with(folder));
+
+        (!is_internal).then_some(relative_path)
+    }
+
+
+    /// Reads content of a file and decrypts it if necessary.
+    /// 
+    /// * `relative_path` - relative to working directory path to a file
+    fn read_content(&self, relative_path: &Path) -> Result<Vec<u8>> {
+        let content = self.storage.read(relative_path)?;
+        if !crypto::is_encrypted(&content) {
+            return Ok(content);
+        }
+
+        self.with_key(|key| crypto::decrypt(key, &content))
+    }
+
+
+    /// Writes content of a file. Content is encrypted if the file is 
+    /// encrypted already or encryption of all notes is enabled.
+    /// 
+    /// * `relative_path` - relative to working directory path to a file
+    /// * `content` - plain content of the file
+    fn write_content(&self, relative_path: &Path, content: &[u8]) -> Result<()> {
+        self.check_writable()?;
+
+        let encrypt = self.config.query_encrypt_all() || self.storage
+            .read(relative_path)
+            .is_ok_and(|current| crypto::is_encrypted(&current));
+
+        if encrypt {
+            self.storage.write(relative_path, &self.with_key(|key| crypto::encrypt(key, content))?)
+        }
+        else {
+            self.storage.write(relative_path, content)
+        }
+    }
+
+
+    /// Encrypts a file in place if encryption of all notes is enabled and
+    /// the file is not encrypted yet. Fails if the repository is locked in
+    /// this case, so plain content is never committed.
+    /// 
+    /// * `relative_path` - relative to working directory path to a file
+    fn encrypt_if_required(&self, relative_path: &Path) -> Result<()> {
+        if !self.config.query_encrypt_all() {
+            return Ok(());
+        }
+
+        let content = self.storage.read(relative_path)?;
+        if crypto::is_encrypted(&content) {
+            return Ok(());
+        }
+
+        self.storage.write(relative_path, &self.with_key(|key| crypto::encrypt(key, &content))?)
+    }
+
+
+    /// Locks unlock session's state.
+    fn lock_session(&self) -> Result<MutexGuard<'_, crypto::Session>> {
+        self.session
+            .lock()
+            .map_err(|_| Error::from_string("unlock session is poisoned", ErrorCategory::Repo))
+    }
+
+
+    /// Locks subscribers to repository's events.
+    fn lock_observers(&self) -> Result<MutexGuard<'_, events::Observers>> {
+        self.observers
+            .lock()
+            .map_err(|_| Error::from_string("subscribers of repository are poisoned", ErrorCategory::Repo))
+    }
+
+
+    /// Delivers an event to subscribers. The change is made already, so
+    /// poisoned subscribers don't fail an operation.
+    /// 
+    /// * `event` - event to deliver
+    fn notify(&self, event: RepositoryEvent) {
+        let observers = self.observers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .snapshot();
+
+        for id in events::deliver(&observers, &event) {
+            self.observers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(id);
+        }
+    }
+
+
+    /// Calls a function with a key for encrypted notes. Fails if the 
+    /// repository is locked.
+    /// 
+    /// * `action` - function, that uses the key
+    fn with_key<F, R>(&self, action: F) -> Result<R>
+    where
+        F: FnOnce(&crypto::Key) -> Result<R>
+    {
+        let timeout = self.get_idle_timeout();
+        let mut session = self.lock_session()?;
+
+        action(session.get_key(timeout)?)
+    }
+
+
+    /// Obtains configured idle timeout of unlock session.
+    fn get_idle_timeout(&self) -> Option<Duration> {
+        self.config
+            .query_idle_timeout()
+            .map(Duration::from_secs)
+    }
+
+
+    /// Returns paths of all notes relative to working directory.
+    fn list_notes(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.storage.list(Path::new(""))?
+            .into_iter()
+            .filter(|relative_path| helpers::is_note_path(relative_path))
+            .collect())
+    }
+
+
+    /// Saves configuration into storage. Returns path to configuration 
+    /// file relative to working directory (ready to be committed).
+    fn save_config(&self) -> Result<PathBuf> {
+        let config_file = helpers::get_config_file();
+        self.storage.write(&config_file, &self.config.to_vec()?)?;
+        self.storage.write(&helpers::get_local_config_file(), &self.config.local_to_vec()?)?;
+
+        Ok(config_file)
+    }
+
+
+    /// Records changes of files in storage. The operation is recorded
+    /// in commit message, so it may be parsed back from history
+    /// (see [`Repository::get_activity`]).
+    /// 
+    /// * `changed` - list of added or modified files (paths MUST be
+    ///   relative to the repository's working directory)
+    /// * `removed` - list of removed files (paths MUST be relative
+    ///   to the repository's working directory)
+    /// * `operation` - operation, that made the changes
+    /// * `summary` - optional subject of commit message (default one
+    ///   is composed from the operation)
+    fn commit_changes(&self, changed: &[&Path], removed: &[&Path], operation: &Operation, summary: Option<&str>) -> Result<()> {
+        self.commit_changes_at(changed, removed, operation, summary, None)
+    }
+
+
+    /// Records changes of files in storage as of a specific time.
+    /// 
+    /// * `changed` - list of added or modified files (paths MUST be
+    ///   relative to the repository's working directory)
+    /// * `removed` - list of removed files (paths MUST be relative
+    ///   to the repository's working directory)
+    /// * `operation` - operation, that made the changes
+    /// * `summary` - optional subject of commit message (default one
+    ///   is composed from the operation)
+    /// * `time` - time of the commit (`None` means current time)
+    fn commit_changes_at(&self, changed: &[&Path], removed: &[&Path], operation: &Operation, summary: Option<&str>, time: Option<SystemTime>) -> Result<()> {
+        self.check_writable()?;
+
+        let message = operation.compose_message(summary);
+        self.storage.commit(self.config.query_name()?, self.config.query_email()?, changed, removed, &message, time)
+    }
+
+
+    /// Loads note identifiers map, modifies it and saves back. Returns
+    /// path to identifiers file relative to working directory (ready
+    /// to be committed).
+    /// 
+    /// * `modifier` - function, that modifies identifiers map
+    fn update_note_ids<F>(&self, modifier: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&mut ids::NoteIds)
+    {
+        let ids_file = helpers::get_ids_file();
+
+        let mut ids = ids::NoteIds::load(self.storage.as_ref(), &ids_file)?;
+        modifier(&mut ids);
+        ids.save(self.storage.as_ref(), &ids_file)?;
+
+        Ok(ids_file)
+    }
+
+
+    /// Loads notes metadata map, modifies it and saves back. Returns
+    /// path to metadata file relative to working directory (ready
+    /// to be committed).
+    /// 
+    /// * `modifier` - function, that modifies metadata map
+    fn update_metadata<F>(&self, modifier: F) -> Result<PathBuf>
+    where
+        F: FnOnce(&mut metadata::Metadata)
+    {
+        let metadata_file = helpers::get_metadata_file();
+
+        let mut metadata = metadata::Metadata::load(self.storage.as_ref(), &metadata_file)?;
+        modifier(&mut metadata);
+        metadata.save(self.storage.as_ref(), &metadata_file)?;
+
+        Ok(metadata_file)
+    }
+
+
+    /// Checks if the repository may be changed, i.e. it is not opened
+    /// in shared mode.
+    fn is_writable(&self) -> bool {
+        self.get_lock_mode() != Some(LockMode::Shared)
+    }
+
+
+    /// Fails if the repository may not be changed (see [`Repository::is_writable`]).
+    fn check_writable(&self) -> Result<()> {
+        if !self.is_writable() {
+            return Err(Error::from_string("repository is opened in shared mode", ErrorCategory::Repo)
+                .with_kind(ErrorKind::Locked));
+        }
+
+        Ok(())
+    }
+
+
+    /// Converts an absolute path into a path relative to working directory.
+    /// 
+    /// * `path` - absolute path inside of working directory
+    fn get_relative_path<'a>(&self, path: &'a Path) -> Result<&'a Path> {
+        if !path.is_absolute() {
+            return Err(Error::from_string("path is not absolute", ErrorCategory::Os)
+                .with_kind(ErrorKind::InvalidPath)
+                .with_path(path));
+        }
+
+        let workdir = self.get_workdir()?;
+
+        path.strip_prefix(workdir)
+            .map_err(|err| Error::from(err).with_path(path))
+    }
+
+
+    /// Converts an absolute path to an existing note into a path relative
+    /// to working directory.
+    /// 
+    /// * `note_path` - absolute path to a note
+    fn get_note_relative_path<'a>(&self, note_path: &'a Path) -> Result<&'a Path> {
+        let relative_path = self.get_relative_path(note_path)?;
+
+        if !self.storage.exists(relative_path) || self.storage.is_folder(relative_path) {
+            return Err(Error::from_string("invalid absolute note path", ErrorCategory::Os)
+                .with_kind(ErrorKind::NotFound)
+                .with_path(note_path));
+        }
+
+        Ok(relative_path)
+    }
+
+
+    /// Adds a folder to repository (internal implementation).
+    /// 
+    /// Used for optimization: sometimes workdir is already known, so we can
+    /// skip its acquisition.
+    /// 
+    /// * `relative_path` - relative to working directory path to the folder
+    fn add_folder_internal(&self, relative_path: &Path) -> Result<()> {
+        if relative_path.as_os_str().is_empty() {
+            //
+            // Adding repository's root is a normal case but we don't
+            // need to do anything
+            //
+
+            return Ok(());
+        }
+
+        // TODO: add to configuration
+
+        Ok(())
+    }
+}
+
+
+/// Writes a file of an export creating parent folders if necessary.
+/// 
+/// * `destination` - root folder of the export
+/// * `relative_path` - path to a file relative to the root folder
+/// * `content` - content of the file
+fn write_exported_file(destination: &Path, relative_path: &Path, content: &[u8]) -> Result<()> {
+    let path = destination.join(relative_path);
+    if let Some(parent) = path.parent() {
+        misc::create_folder_recursive(parent)?;
+    }
+
+    fs::write(path, content)
+        .map_err(Error::from)
+}
+
+
+impl Drop for Repository {
+    fn drop(&mut self) {
+        if !self.is_writable() {
+            return;
+        }
+
+        let config_file = helpers::get_config_file();
+        let config = self.config
+            .to_vec()
+            .expect("cannot serialize configuration at destruction");
+
+        self.storage
+            .write(&config_file, &config)
+            .expect("cannot save configuration at destruction");
+
+        let local_config = self.config
+            .local_to_vec()
+            .expect("cannot serialize local configuration at destruction");
+
+        self.storage
+            .write(&helpers::get_local_config_file(), &local_config)
+            .expect("cannot save local configuration at destruction");
+
+        // TODO: stage and commit
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json as sj;
+
+
+    /// Creates an in-memory repository with encryption of all notes enabled.
+    fn create_encrypted_repo() -> Repository {
+        let mut repo = Repository::in_memory("encrypted", "Tester", "tester@example.com").unwrap();
+        repo.unlock("passphrase").unwrap();
+        repo.set_encryption(true).unwrap();
+
+        repo
+    }
+
+
+    #[test]
+    fn added_note_is_encrypted() {
+        let repo = create_encrypted_repo();
+        let relative_path = Path::new("note.md");
+
+        repo.storage.write(relative_path, b"secret").unwrap();
+        repo.add_note(&repo.get_workdir().unwrap().join(relative_path)).unwrap();
+
+        assert!(crypto::is_encrypted(&repo.storage.read(relative_path).unwrap()));
+        assert_eq!(repo.read_content(relative_path).unwrap(), b"secret");
+    }
+
+
+    #[test]
+    fn external_change_is_encrypted() {
+        let repo = create_encrypted_repo();
+        let relative_path = Path::new("external.md");
+
+        repo.storage.write(relative_path, b"secret").unwrap();
+        assert_eq!(repo.commit_external_changes(&[repo.get_workdir().unwrap().join(relative_path)], &[], None).unwrap(), 1);
+
+        assert!(crypto::is_encrypted(&repo.storage.read(relative_path).unwrap()));
+    }
+
+
+    #[test]
+    fn locked_repository_does_not_commit_plain_note() {
+        let repo = create_encrypted_repo();
+        let relative_path = Path::new("note.md");
+
+        repo.lock().unwrap();
+        repo.storage.write(relative_path, b"secret").unwrap();
+
+        assert!(repo.add_note(&repo.get_workdir().unwrap().join(relative_path)).is_err());
+        assert!(repo.storage.history(Some(relative_path)).unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn unreferenced_attachments_are_collected() {
+        let repo = Repository::in_memory("attachments", "Tester", "tester@example.com").unwrap();
+        let note = repo.get_workdir().unwrap().join("folder/note.md");
+
+        let file = env::temp_dir().join(format!("mm-attachment-test-{}.png", uuid::Uuid::new_v4()));
+        fs::write(&file, b"image").unwrap();
+
+        let link = repo.add_attachment(&note, &file).unwrap();
+        let history_length = repo.get_history(None).unwrap().len();
+        assert_eq!(repo.add_attachment(&note, &file).unwrap(), link);
+        assert_eq!(repo.get_history(None).unwrap().len(), history_length);
+        assert!(link.starts_with("![mm-attachment-test-") && link.contains("](../attachments/"));
+
+        repo.write_note(&note, link.as_bytes()).unwrap();
+        assert!(repo.collect_attachments_garbage().unwrap().is_empty());
+
+        repo.write_note(&note, b"no links").unwrap();
+        let garbage = repo.collect_attachments_garbage().unwrap();
+        assert_eq!(garbage.len(), 1);
+        assert!(repo.get_attachment_references().unwrap().is_empty());
+
+        fs::remove_file(file).unwrap();
+    }
+
+
+    #[test]
+    fn missing_trash_folder_is_purged() {
+        let repo = Repository::in_memory("trash", "Tester", "tester@example.com").unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        for name in ["a.md", "b.md"] {
+            repo.write_note(&workdir.join(name), b"# Note").unwrap();
+            repo.remove_note(&workdir.join(name)).unwrap();
+        }
+
+        let entries = repo.list_trash().unwrap();
+        let trashed_path = trash::Trash::load(repo.storage.as_ref(), &helpers::get_trash_file()).unwrap().get_entries()[0].get_trashed_path();
+        repo.storage.delete(trashed_path.parent().unwrap()).unwrap();
+
+        repo.empty_trash().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(repo.list_trash().unwrap().is_empty());
+        assert!(repo.storage.list(&helpers::get_trash_directory()).unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn invalid_journal_settings_are_rejected() {
+        let mut repo = Repository::in_memory("journal", "Tester", "tester@example.com").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+
+        assert!(repo.set_journal_folder(Some("../..")).is_err());
+        assert!(repo.set_journal_pattern(Some("%Q.md")).is_err());
+        assert!(repo.set_journal_pattern(Some("../%Y.md")).is_err());
+        assert!(repo.set_journal_template(Some("../mm_ids.json")).is_err());
+
+        repo.set_journal_folder(Some("daily")).unwrap();
+        repo.set_journal_pattern(Some("%Y-%m-%d.md")).unwrap();
+        assert_eq!(repo.open_journal_entry(date).unwrap(), repo.get_workdir().unwrap().join("daily/2024-01-02.md"));
+
+        //
+        // Configuration may be committed elsewhere, so
+        // loaded settings are checked before use
+        //
+
+        repo.config.set_journal_folder(Some("/tmp"));
+        assert_eq!(repo.open_journal_entry(date).unwrap_err().get_kind(), ErrorKind::InvalidPath);
+        assert!(repo.list_journal_entries(date, date).is_err());
+
+        repo.config.set_journal_folder(None);
+        repo.config.set_journal_pattern(Some("%Q/%Y.md"));
+        assert_eq!(repo.open_journal_entry(date).unwrap_err().get_kind(), ErrorKind::InvalidPath);
+
+        repo.config.set_journal_pattern(None);
+        repo.config.set_journal_template(Some("../../outside.md"));
+        assert_eq!(repo.open_journal_entry(date).unwrap_err().get_kind(), ErrorKind::InvalidPath);
+    }
+
+
+    #[test]
+    fn notes_are_kept_in_plain_directory() {
+        let root = env::temp_dir().join(format!("mm-directory-test-{}", uuid::Uuid::new_v4()));
+        let storage = crate::data::DirectoryStorage::open_or_create(&root).unwrap();
+        let repo = Repository::from_storage_internal(storage, "directory", || cfg::Config::with_user("Tester", "tester@example.com"), None).unwrap();
+
+        let (note, moved) = (root.join("note.md"), root.join("folder/moved.md"));
+        repo.write_note(&note, b"# Note").unwrap();
+        let id = repo.get_note_id(&note).unwrap();
+
+        repo.move_note(&note, &moved).unwrap();
+        assert_eq!(fs::read(&moved).unwrap(), b"# Note");
+        assert_eq!(repo.get_note_id(&moved).unwrap(), id);
+        assert!(repo.get_history(None).unwrap().is_empty());
+        assert!(repo.undo().unwrap().is_none());
+
+        drop(repo);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+
+    #[test]
+    fn callback_may_change_subscriptions() {
+        let repo = Arc::new(Repository::in_memory("events", "Tester", "tester@example.com").unwrap());
+        let receiver = repo.subscribe_channel().unwrap();
+
+        let weak = Arc::downgrade(&repo);
+        let id = repo.subscribe(move |_event| {
+            if let Some(repo) = weak.upgrade() {
+                let id = repo.subscribe(|_event| {}).unwrap();
+                assert!(repo.unsubscribe(id).unwrap());
+            }
+        }).unwrap();
+
+        repo.write_note(&repo.get_workdir().unwrap().join("note.md"), b"content").unwrap();
+        assert!(repo.unsubscribe(id).unwrap());
+        assert!(matches!(receiver.try_recv(), Ok(RepositoryEvent::NoteCreated(_))));
+    }
+
+
+    #[test]
+    #[cfg(feature = "watcher")]
+    fn watched_rename_keeps_identifier() {
+        let workdir = env::temp_dir().join(format!("mm-watch-test-{}", uuid::Uuid::new_v4()));
+        misc::create_folder_recursive(&workdir).unwrap();
+
+        let storage = GitStorage::open_or_create(&workdir).unwrap();
+        let repo = Repository::from_storage_internal(storage, "watched", || cfg::Config::with_user("Tester", "tester@example.com"), None).unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+
+        let (from, to) = (workdir.join("from.md"), workdir.join("to.md"));
+        repo.write_note(&from, b"content").unwrap();
+        let id = repo.get_note_id(&from).unwrap();
+
+        let receiver = repo.subscribe_channel().unwrap();
+        fs::rename(&from, &to).unwrap();
+        assert_eq!(repo.commit_watched_changes(&[from.clone(), to.clone()]).unwrap(), 1);
+
+        assert_eq!(receiver.try_recv().unwrap(), RepositoryEvent::NoteMoved { from, to: to.clone() });
+        assert_eq!(repo.get_note_id(&to).unwrap(), id);
+
+        fs::remove_dir_all(workdir).unwrap();
+    }
+
+
+    #[test]
+    fn undo_restores_removed_folder() {
+        let repo = Repository::in_memory("undo", "Tester", "tester@example.com").unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+
+        //
+        // Files committed elsewhere have no identifiers, so
+        // their removal is recorded for the folder
+        //
+
+        let file = Path::new("folder/file.txt");
+        repo.storage.write(file, b"content").unwrap();
+        repo.storage.commit("Tester", "tester@example.com", &[file], &[], "chore: made elsewhere", None).unwrap();
+
+        repo.storage.delete(Path::new("folder")).unwrap();
+        assert_eq!(repo.commit_external_changes(&[workdir.join("folder")], &[], None).unwrap(), 1);
+
+        let undone = repo.undo().unwrap().unwrap();
+        assert_eq!(undone.get_changes()[0].get_action(), &ChangeAction::Removed);
+        assert_eq!(repo.storage.read(file).unwrap(), b"content");
+    }
+
+
+    #[test]
+    fn exported_notes_do_not_replace_generated_pages() {
+        let repo = Repository::in_memory("export", "Tester", "tester@example.com").unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        repo.write_note(&workdir.join("index.md"), b"# My index #todo").unwrap();
+        repo.write_note(&workdir.join("_tags/todo.md"), b"# My todo").unwrap();
+
+        let destination = env::temp_dir().join(format!("mm-export-test-{}", uuid::Uuid::new_v4()));
+        repo.export_html(&destination).unwrap();
+
+        let read = |path: &str| fs::read_to_string(destination.join(path)).unwrap();
+        assert!(read("index.html").contains("<h1>Notes</h1>"));
+        assert!(read("_tags/todo.html").contains("<h1>#todo</h1>"));
+        assert!(read("index-note.html").contains("My index"));
+        assert!(read("_tags-note/todo.html").contains("My todo"));
+
+        fs::remove_dir_all(destination).unwrap();
+    }
+
+
+    #[test]
+    fn cancelled_export_stops_between_files() {
+        let repo = Repository::in_memory("export", "Tester", "tester@example.com").unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        for name in ["a.md", "b.md", "c.md"] {
+            repo.write_note(&workdir.join(name), b"# Note").unwrap();
+        }
+
+        let destination = env::temp_dir().join(format!("mm-export-test-{}", uuid::Uuid::new_v4()));
+        let checks = std::cell::Cell::new(0);
+        let result = repo.export_html_with(&destination, &|| {
+            checks.set(checks.get() + 1);
+            match checks.get() {
+                1 => Ok(()),
+                _ => Err(Error::from_string("cancelled", ErrorCategory::Repo))
+            }
+        });
+
+        assert!(result.is_err());
+        assert!(destination.join("a.html").exists());
+        assert!(!destination.join("b.html").exists());
+
+        fs::remove_dir_all(destination).unwrap();
+    }
+
+
+    /// Creates a zip archive with files and a configuration, that allows
+    /// to commit them. Returns a path to the archive.
+    /// 
+    /// * `files` - paths relative to working directory and contents of files
+    fn create_archive(files: &[(PathBuf, &[u8])]) -> PathBuf {
+        let mut config = cfg::Config::with_user("Tester", "tester@example.com").unwrap();
+        config.set_trash_purge_days(Some(1));
+
+        let archive_path = env::temp_dir().join(format!("mm-archive-test-{}.zip", uuid::Uuid::new_v4()));
+        let mut archive = export::ArchiveWriter::create(&archive_path, ArchiveFormat::Zip).unwrap();
+        archive.add_file(&helpers::get_config_file(), &config.to_vec().unwrap()).unwrap();
+        for (relative_path, content) in files {
+            archive.add_file(relative_path, content).unwrap();
+        }
+
+        archive.finish().unwrap();
+        archive_path
+    }
+
+
+    #[test]
+    fn crafted_archive_is_rejected() {
+        let entry_id = uuid::Uuid::new_v4().to_string();
+        let crafted = [
+            (helpers::get_trash_file(), sj::json!([{ "id": "../../..", "path": "note.md", "deleted": 0 }])),
+            (helpers::get_trash_file(), sj::json!([{ "id": entry_id, "path": "../../note.md", "deleted": 0 }])),
+            (helpers::get_trash_file(), sj::json!([{ "id": entry_id, "path": ".mm/mm_config.json", "deleted": 0 }])),
+            (helpers::get_trash_file(), sj::json!({ "id": entry_id })),
+            (helpers::get_ids_file(), sj::json!({ "id": "/etc/passwd" })),
+            (helpers::get_ids_file(), sj::json!({ "id": 1 })),
+            (helpers::get_metadata_file(), sj::json!({ "id": "tags" })),
+        ];
+
+        for (relative_path, content) in crafted {
+            let archive_path = create_archive(&[
+                (PathBuf::from("note.md"), b"# Note"),
+                (relative_path.clone(), &sj::to_vec(&content).unwrap()),
+            ]);
+
+            let repo_path = env::temp_dir().join(format!("mm-import-test-{}", uuid::Uuid::new_v4()));
+            let err = match Repository::import_archive_to(&archive_path, &repo_path, "imported") {
+                Ok(_repo) => panic!("crafted archive is imported: {}", content),
+                Err(err) => err
+            };
+
+            assert_eq!(err.get_path(), Some(relative_path.as_path()), "{}", content);
+            assert!(!repo_path.exists(), "{}", content);
+
+            fs::remove_file(archive_path).unwrap();
+        }
+    }
+
+
+    #[test]
+    fn failed_import_is_removed() {
+        let archive_path = create_archive(&[
+            (PathBuf::from("note.md"), b"# Note"),
+            (helpers::get_config_file(), b"not a configuration"),
+        ]);
+
+        let repo_path = env::temp_dir().join(format!("mm-import-test-{}", uuid::Uuid::new_v4()));
+
+        assert!(Repository::import_archive_to(&archive_path, &repo_path, "imported").is_err());
+        assert!(!repo_path.exists());
+
+        fs::remove_file(archive_path).unwrap();
+    }
+
+
+    #[test]
+    fn imported_trash_is_not_purged() {
+        let entry_id = uuid::Uuid::new_v4().to_string();
+        let trash = sj::json!([{ "id": entry_id, "path": "old.md", "deleted": 0 }]);
+        let archive_path = create_archive(&[
+            (PathBuf::from("note.md"), b"# Note"),
+            (helpers::get_trash_directory().join(&entry_id).join("old.md"), b"# Old"),
+            (helpers::get_trash_file(), &sj::to_vec(&trash).unwrap()),
+        ]);
+
+        let repo_path = env::temp_dir().join(format!("mm-import-test-{}", uuid::Uuid::new_v4()));
+        let repo = Repository::import_archive_to(&archive_path, &repo_path, "imported").unwrap();
+
+        let entries = repo.list_trash().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].get_original_path(), repo_path.join("old.md"));
+        assert_eq!(repo.read_note(&repo_path.join("note.md")).unwrap(), b"# Note");
+
+        drop(repo);
+        fs::remove_dir_all(repo_path).unwrap();
+        fs::remove_file(archive_path).unwrap();
+    }
+
+
+    /// Creates a folder with files to import. Returns a path to the folder.
+    /// 
+    /// * `files` - paths relative to the folder and contents of files
+    fn create_import_folder(files: &[(&str, &[u8])]) -> PathBuf {
+        let source = env::temp_dir().join(format!("mm-import-test-{}", uuid::Uuid::new_v4()));
+        for (relative_path, content) in files {
+            write_exported_file(&source, Path::new(relative_path), content).unwrap();
+        }
+
+        source
+    }
+
+
+    #[test]
+    fn folder_is_imported() {
+        let source = create_import_folder(&[
+            ("note.md", b"# Note #idea\n\n[[other]] ![[image.png]] [image](image.png)"),
+            ("sub/other.md", b"# Other\n\n[[note#Part]]"),
+            ("image.png", b"image"),
+            ("broken.md", b"\xff\xfe"),
+            (".hidden/secret.md", b"# Secret"),
+        ]);
+
+        let (repo, report) = Repository::import_folder_with(&source, || Repository::in_memory("imported", "Tester", "tester@example.com")).unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+
+        let mut imported = report.get_imported().to_vec();
+        imported.sort();
+        assert_eq!(imported, vec![workdir.join("note.md"), workdir.join("sub/other.md")]);
+        assert_eq!(report.get_attachments_count(), 1);
+        assert_eq!(report.get_skipped().len(), 1);
+        assert_eq!(report.get_skipped()[0].0, source.join("broken.md"));
+
+        let attachment = Path::new(MM_ATTACHMENTS_FOLDER).join(attachments::get_attachment_name(b"image", Path::new("image.png")));
+        assert_eq!(repo.storage.read(&attachment).unwrap(), b"image");
+
+        let note = String::from_utf8(repo.read_note(&workdir.join("note.md")).unwrap()).unwrap();
+        assert!(note.contains("(<sub/other.md>)"), "{}", note);
+        assert_eq!(note.matches(&format!("(<{}>)", wiki::normalize(&attachment))).count(), 2, "{}", note);
+
+        let other = String::from_utf8(repo.read_note(&workdir.join("sub/other.md")).unwrap()).unwrap();
+        assert!(other.contains("(<../note.md#part>)"), "{}", other);
+
+        assert_eq!(repo.get_note_metadata(&workdir.join("note.md")).unwrap().get_tags(), ["idea"]);
+        assert!(!repo.storage.exists(Path::new(".hidden/secret.md")));
+
+        fs::remove_dir_all(source).unwrap();
+    }
+
+
+    #[test]
+    fn obsidian_vault_is_imported() {
+        let source = create_import_folder(&[
+            (".obsidian/daily-notes.json", br#"{ "folder": "daily", "format": "YYYY/MM-DD", "template": "templates/day" }"#),
+            (".obsidian/templates.json", br#"{ "folder": "templates" }"#),
+            ("templates/day.md", b"# {{date}}"),
+            ("daily/2024/01-02.md", b"# Entry"),
+        ]);
+
+        let (repo, report) = Repository::import_folder_with(&source, || Repository::in_memory("vault", "Tester", "tester@example.com")).unwrap();
+
+        assert!(report.get_warnings().is_empty(), "{:?}", report.get_warnings());
+        assert_eq!(repo.config.query_journal_folder(), "daily");
+        assert_eq!(repo.config.query_journal_pattern(), "%Y/%m-%d.md");
+        assert_eq!(repo.config.query_journal_template(), Some("day.md"));
+        assert_eq!(repo.list_templates().unwrap(), vec!["day.md"]);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let entry_path = repo.get_workdir().unwrap().join("daily/2024/01-02.md");
+        assert_eq!(repo.list_journal_entries(date, date).unwrap(), vec![(date, entry_path)]);
+
+        fs::remove_dir_all(source).unwrap();
+    }
+
+
+    #[test]
+    fn unsupported_vault_settings_are_reported() {
+        let source = create_import_folder(&[
+            (".obsidian/daily-notes.json", br#"{ "folder": "../outside", "format": "YYYY-MM-DD HH:mm" }"#),
+            ("note.md", b"# Note"),
+        ]);
+
+        let (repo, report) = Repository::import_folder_with(&source, || Repository::in_memory("vault", "Tester", "tester@example.com")).unwrap();
+        let warnings: Vec<&str> = report.get_warnings()
+            .iter()
+            .map(|(_path, problem)| problem.as_str())
+            .collect();
+
+        assert!(warnings.contains(&"daily notes folder is not located inside of the vault"), "{:?}", warnings);
+        assert!(warnings.iter().any(|problem| problem.starts_with("daily notes format")), "{:?}", warnings);
+        assert_eq!(repo.config.query_journal_folder(), cfg::Config::with_user("Tester", "tester@example.com").unwrap().query_journal_folder());
+
+        fs::remove_dir_all(source).unwrap();
+    }
+
+
+    /// Writes a Joplin export archive with items. Returns a path to
+    /// the archive.
+    /// 
+    /// * `items` - identifiers and serialized items
+    /// * `resources` - identifiers and contents of resources
+    fn create_jex(items: &[(&str, String)], resources: &[(&str, &[u8])]) -> PathBuf {
+        let jex_path = env::temp_dir().join(format!("mm-jex-test-{}.jex", uuid::Uuid::new_v4()));
+        let mut builder = tar::Builder::new(fs::File::create(&jex_path).unwrap());
+
+        let files = items
+            .iter()
+            .map(|(id, item)| (format!("{}.md", id), item.as_bytes()))
+            .chain(resources.iter().map(|(id, content)| (format!("resources/{}", id), *content)));
+
+        for (file_path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            builder.append_data(&mut header, file_path, content).unwrap();
+        }
+
+        builder.finish().unwrap();
+        jex_path
+    }
+
+
+    #[test]
+    fn jex_notes_are_committed_as_of_their_times() {
+        let folder_id = "00000000000000000000000000000001";
+        let old_id = "00000000000000000000000000000010";
+        let new_id = "00000000000000000000000000000011";
+        let resource_id = "00000000000000000000000000000020";
+
+        let jex_path = create_jex(&[
+            (new_id, format!("New\n\n[old](:/{}) ![image](:/{})\n\nid: {}\nparent_id: {}\nupdated_time: 2023-02-01T00:00:00.000Z\ntype_: 1", old_id, resource_id, new_id, folder_id)),
+            (folder_id, format!("Work\n\nid: {}\ntype_: 2", folder_id)),
+            (resource_id, format!("image.png\n\nid: {}\nfile_extension: png\ntype_: 4", resource_id)),
+            (old_id, format!("Old\n\nText\n\nid: {}\nparent_id: {}\ncreated_time: 2023-01-01T00:00:00.000Z\ntype_: 1", old_id, folder_id)),
+        ], &[(resource_id, b"image")]);
+
+        let repo = Repository::in_memory("jex", "Tester", "tester@example.com").unwrap();
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        let report = repo.import_jex(&jex_path, &workdir.join("joplin")).unwrap();
+
+        assert_eq!(report.get_imported(), [workdir.join("joplin/Work/Old.md"), workdir.join("joplin/Work/New.md")]);
+        assert_eq!(report.get_attachments_count(), 1);
+
+        let attachment = Path::new(MM_ATTACHMENTS_FOLDER).join(attachments::get_attachment_name(b"image", Path::new(&format!("{}.png", resource_id))));
+        let new_note = String::from_utf8(repo.read_note(&workdir.join("joplin/Work/New.md")).unwrap()).unwrap();
+        assert_eq!(new_note, format!("[old](../../joplin/Work/Old.md) ![image](../../{})", wiki::normalize(&attachment)));
+
+        //
+        // Notes are committed from the oldest one, the summary is the last
+        //
+
+        let history = repo.get_history(None).unwrap();
+        let day = |days| SystemTime::UNIX_EPOCH + Duration::from_secs(1672531200 + days * SECONDS_PER_DAY);
+
+        assert_eq!(history[2].get_message().lines().next(), Some("chore: imported \"Old\""));
+        assert_eq!(history[2].get_time(), day(0));
+        assert_eq!(history[1].get_message().lines().next(), Some("chore: imported \"New\""));
+        assert_eq!(history[1].get_time(), day(31));
+        assert!(history[0].get_message().starts_with("chore: imported from"));
+        assert_eq!(repo.storage.history(Some(&attachment)).unwrap()[0].get_time(), day(31));
+
+        fs::remove_file(jex_path).unwrap();
+    }
+
+
+    /// Creates a repository with git storage in a new temporary folder.
+    /// 
+    /// * `repo_name` - a name of repository
+    fn create_git_repo(repo_name: &str) -> Repository {
+        let workdir = env::temp_dir().join(format!("mm-{}-test-{}", repo_name, uuid::Uuid::new_v4()));
+        misc::create_folder_recursive(&workdir).unwrap();
+
+        let storage = GitStorage::open_or_create(&workdir).unwrap();
+        Repository::from_storage_internal(storage, repo_name, || cfg::Config::with_user("Tester", "tester@example.com"), None).unwrap()
+    }
+
+
+    /// Writes notes through the repository and changes them outside of it:
+    /// `modified.md` is modified, `removed.md` is removed, `from.md` is
+    /// moved to `to.md` and `folder/added.md` is created. Internal files
+    /// and attachments are changed too.
+    /// 
+    /// * `repo` - repository to change
+    fn change_externally(repo: &Repository) {
+        let workdir = repo.get_workdir().unwrap();
+        for name in ["modified.md", "removed.md", "from.md"] {
+            repo.write_note(&workdir.join(name), format!("# Note {}\n\nSome text to detect renames", name).as_bytes()).unwrap();
+        }
+
+        fs::write(workdir.join("modified.md"), b"# Modified").unwrap();
+        fs::remove_file(workdir.join("removed.md")).unwrap();
+        fs::rename(workdir.join("from.md"), workdir.join("to.md")).unwrap();
+        write_exported_file(workdir, Path::new("folder/added.md"), b"# Added").unwrap();
+        write_exported_file(workdir, Path::new(".mm/internal.json"), b"{}").unwrap();
+        write_exported_file(workdir, Path::new("attachments/image.png"), b"image").unwrap();
+    }
+
+
+    #[test]
+    fn status_reports_uncommitted_notes() {
+        let repo = create_git_repo("status");
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        assert!(repo.status().unwrap().is_empty());
+
+        change_externally(&repo);
+
+        let status: Vec<(PathBuf, StatusKind)> = repo.status().unwrap()
+            .into_iter()
+            .map(|entry| (entry.get_path().to_path_buf(), entry.get_kind().clone()))
+            .collect();
+
+        assert_eq!(status, vec![
+            (workdir.join("folder/added.md"), StatusKind::Added),
+            (workdir.join("modified.md"), StatusKind::Modified),
+            (workdir.join("removed.md"), StatusKind::Deleted),
+            (workdir.join("to.md"), StatusKind::Renamed { from: workdir.join("from.md") }),
+        ]);
+
+        fs::remove_dir_all(workdir).unwrap();
+    }
+
+
+    #[test]
+    fn status_reports_conflicts() {
+        let repo = create_git_repo("conflict");
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        let note_path = workdir.join("note.md");
+        repo.write_note(&note_path, b"base").unwrap();
+
+        //
+        // Another branch changes the same note, so merging it
+        // leaves the note conflicted
+        //
+
+        let git = git2::Repository::open(&workdir).unwrap();
+        let base = git.head().unwrap().peel_to_commit().unwrap();
+        let mut builder = git.treebuilder(Some(&base.tree().unwrap())).unwrap();
+        builder.insert("note.md", git.blob(b"theirs").unwrap(), 0o100644).unwrap();
+
+        let tree = git.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Tester", "tester@example.com").unwrap();
+        let theirs = git.commit(None, &signature, &signature, "theirs", &tree, &[&base]).unwrap();
+
+        repo.write_note(&note_path, b"ours").unwrap();
+        git.merge(&[&git.find_annotated_commit(theirs).unwrap()], None, None).unwrap();
+
+        let status = repo.status().unwrap();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].get_path(), note_path);
+        assert_eq!(*status[0].get_kind(), StatusKind::Conflicted);
+
+        let err = repo.commit_pending(None).unwrap_err();
+        assert_eq!(err.get_kind(), ErrorKind::Conflict);
+        assert_eq!(err.get_path(), Some(note_path.as_path()));
+
+        fs::remove_dir_all(workdir).unwrap();
+    }
+
+
+    #[test]
+    fn pending_changes_are_committed() {
+        let repo = create_git_repo("pending");
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        change_externally(&repo);
+
+        let moved_id = repo.get_note_id(&workdir.join("from.md")).unwrap();
+        let removed_id = repo.get_note_id(&workdir.join("removed.md")).unwrap().unwrap();
+
+        assert_eq!(repo.commit_pending(None).unwrap(), 4);
+        assert!(repo.status().unwrap().is_empty());
+        assert_eq!(repo.commit_pending(None).unwrap(), 0);
+
+        //
+        // Moved note keeps its identifier, removed one loses it,
+        // internal files and attachments are left as they are
+        //
+
+        assert_eq!(repo.get_note_id(&workdir.join("to.md")).unwrap(), moved_id);
+        assert!(repo.get_note_id(&workdir.join("folder/added.md")).unwrap().is_some());
+        assert_eq!(repo.find_by_id(&removed_id).unwrap(), None);
+        assert!(repo.storage.is_changed(Path::new(".mm/internal.json")).unwrap());
+        assert!(repo.storage.is_changed(Path::new("attachments/image.png")).unwrap());
+
+        let history = repo.get_history(None).unwrap();
+        assert_eq!(history[0].get_message().lines().next(), Some("chore: 4 notes changed externally"));
+
+        fs::remove_dir_all(workdir).unwrap();
+    }
+
+
+    #[test]
+    fn pending_changes_are_committed_with_message() {
+        let repo = create_git_repo("pending");
+        let workdir = repo.get_workdir().unwrap().to_path_buf();
+        let note_path = workdir.join("note.md");
+        repo.write_note(&note_path, b"# Note").unwrap();
+
+        fs::write(workdir.join(".gitignore"), b"*.tmp\n").unwrap();
+        repo.storage.commit("Tester", "tester@example.com", &[Path::new(".gitignore")], &[], "chore: ignore", None).unwrap();
+
+        fs::write(&note_path, b"# Edited").unwrap();
+        fs::write(workdir.join("draft.tmp"), b"draft").unwrap();
+
+        assert_eq!(repo.commit_pending(Some("docs: edited note")).unwrap(), 1);
+        assert!(repo.status().unwrap().is_empty());
+
+        let history = repo.get_history(None).unwrap();
+        assert_eq!(history[0].get_message().lines().next(), Some("docs: edited note"));
+        assert_eq!(repo.read_note(&note_path).unwrap(), b"# Edited");
+
+        fs::remove_dir_all(workdir).unwrap();
     }
 }
@@ -0,0 +1,152 @@
+use std::sync::{Arc, RwLock};
+
+use crate::error::{Result, Error, ErrorCategory};
+use super::repository::Repository;
+use super::view::RepositoryView;
+
+
+/// A handle to a repository, that may be shared between threads.
+/// 
+/// Cloning the handle is cheap: all clones refer to the same repository.
+/// Read operations (see [`SharedRepository::read`]) run concurrently,
+/// while changes (see [`SharedRepository::write`]) wait until all other
+/// operations are finished and run one at a time.
+#[derive(Clone)]
+pub struct SharedRepository {
+    /// Repository behind a readers-writer lock
+    internal: Arc<RwLock<Repository>>,
+}
+
+
+impl SharedRepository {
+    /// Wraps a repository into a shared handle.
+    /// 
+    /// * `repo` - repository to share
+    pub fn new(repo: Repository) -> Self {
+        SharedRepository {
+            internal: Arc::new(RwLock::new(repo))
+        }
+    }
+
+
+    /// Runs a read operation. Other read operations may run at the
+    /// same time.
+    /// 
+    /// The operation gets a read-only view of the repository, so changes
+    /// (e.g. [`Repository::write_note`]) are possible only with
+    /// [`SharedRepository::write`].
+    /// 
+    /// * `operation` - function, that reads from the repository
+    pub fn read<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(RepositoryView<'_>) -> Result<T>
+    {
+        let repo = self.internal
+            .read()
+            .map_err(|_| Error::from_string("shared repository is poisoned", ErrorCategory::Repo))?;
+
+        operation(RepositoryView::new(&repo))
+    }
+
+
+    /// Runs an operation, that changes the repository. Operations are
+    /// queued and run exclusively.
+    /// 
+    /// * `operation` - function, that changes the repository
+    pub fn write<F, T>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce(&mut Repository) -> Result<T>
+    {
+        let mut repo = self.internal
+            .write()
+            .map_err(|_| Error::from_string("shared repository is poisoned", ErrorCategory::Repo))?;
+
+        operation(&mut repo)
+    }
+}
+
+
+impl From<Repository> for SharedRepository {
+    fn from(repo: Repository) -> Self {
+        SharedRepository::new(repo)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::thread;
+
+
+    /// Number of threads of each kind
+    const THREADS_COUNT: usize = 4;
+
+    /// Number of notes written by each writer
+    const NOTES_COUNT: usize = 5;
+
+
+    #[test]
+    fn readers_and_writers_run_in_parallel() {
+        let shared = SharedRepository::new(Repository::in_memory("shared", "Tester", "tester@example.com").unwrap());
+        let workdir = shared.read(|view| view.get_workdir().map(Path::to_path_buf)).unwrap();
+
+        let note_path = workdir.join("common.md");
+        shared.write(|repo| repo.write_note(&note_path, b"0")).unwrap();
+
+        thread::scope(|scope| {
+            for writer in 0..THREADS_COUNT {
+                let shared = shared.clone();
+                let workdir = &workdir;
+                let note_path = &note_path;
+
+                scope.spawn(move || {
+                    for note in 0..NOTES_COUNT {
+                        shared.write(|repo| {
+                            repo.write_note(&workdir.join(format!("{}-{}.md", writer, note)), b"# Note")?;
+                            repo.write_note(note_path, format!("{}", writer * NOTES_COUNT + note + 1).as_bytes())
+                        }).unwrap();
+                    }
+                });
+            }
+
+            for _reader in 0..THREADS_COUNT {
+                let shared = shared.clone();
+                let note_path = &note_path;
+
+                scope.spawn(move || {
+                    for _read in 0..NOTES_COUNT {
+                        //
+                        // Each read sees the note and its history in
+                        // the same state, writers can't interfere
+                        //
+
+                        shared.read(|view| {
+                            let content = String::from_utf8(view.read_note(note_path)?).unwrap();
+                            let history = view.get_history(Some(note_path))?;
+                            let writes = content.parse::<usize>().unwrap();
+
+                            assert!(writes <= THREADS_COUNT * NOTES_COUNT);
+                            assert_eq!(view.read_note(note_path)?, content.as_bytes());
+                            assert_eq!(view.get_history(Some(note_path))?.len(), history.len());
+
+                            Ok(())
+                        }).unwrap();
+                    }
+                });
+            }
+        });
+
+        shared.read(|view| {
+            for writer in 0..THREADS_COUNT {
+                for note in 0..NOTES_COUNT {
+                    assert_eq!(view.read_note(&workdir.join(format!("{}-{}.md", writer, note)))?, b"# Note");
+                }
+            }
+
+            assert_eq!(view.get_history(Some(&note_path))?.len(), THREADS_COUNT * NOTES_COUNT + 1);
+            Ok(())
+        }).unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::error::{Result, Error, ErrorCategory};
+
+
+/// Opening sequence of a placeholder
+const PLACEHOLDER_OPEN: &str = "{{";
+
+/// Closing sequence of a placeholder
+const PLACEHOLDER_CLOSE: &str = "}}";
+
+
+/// Substitutes all placeholders in a template with values of variables.
+/// 
+/// Placeholders look like `{{name}}` (spaces around the name are allowed).
+/// Unknown placeholders and unterminated ones are reported as errors, so
+/// a note is never created from a half-filled template.
+/// 
+/// * `template` - content of a template
+/// * `variables` - values of variables by their names
+pub(super) fn render(template: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(PLACEHOLDER_OPEN) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start + PLACEHOLDER_OPEN.len()..];
+
+        let end = rest
+            .find(PLACEHOLDER_CLOSE)
+            .ok_or(Error::from_string("unterminated placeholder in template", ErrorCategory::Repo))?;
+
+        let name = rest[..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or(Error::from_string(format!("unknown template variable '{}'", name), ErrorCategory::Repo))?;
+
+        result.push_str(value);
+        rest = &rest[end + PLACEHOLDER_CLOSE.len()..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Creates variables from pairs of names and values.
+    fn create_variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+
+    #[test]
+    fn placeholders_are_substituted() {
+        let variables = create_variables(&[("title", "Plan"), ("date", "2024-01-02")]);
+
+        assert_eq!(render("# {{title}}\n{{ date }} {{title}}\n", &variables).unwrap(), "# Plan\n2024-01-02 Plan\n");
+        assert_eq!(render("No placeholders }}", &variables).unwrap(), "No placeholders }}");
+    }
+
+
+    #[test]
+    fn values_are_not_rendered_again() {
+        let variables = create_variables(&[("title", "{{date}}")]);
+
+        assert_eq!(render("{{title}}", &variables).unwrap(), "{{date}}");
+    }
+
+
+    #[test]
+    fn invalid_placeholders_are_rejected() {
+        let variables = create_variables(&[("title", "Plan")]);
+
+        assert!(render("{{unknown}}", &variables).is_err());
+        assert!(render("{{title", &variables).is_err());
+        assert!(render("{{}}", &variables).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json as sj;
+use uuid::Uuid;
+
+use crate::data::Storage;
+use crate::error::{Result, Error, ErrorCategory};
+use super::helpers;
+
+
+/// Key of trash entry identifier
+const ID_KEY: &str = "id";
+
+/// Key of original path of a trashed note
+const PATH_KEY: &str = "path";
+
+/// Key of a stable identifier of a trashed note
+const NOTE_ID_KEY: &str = "note";
+
+/// Key of deletion time (seconds since UNIX epoch)
+const DELETED_KEY: &str = "deleted";
+
+
+/// Describes a note, that was moved to trash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrashEntry {
+    /// Identifier of the entry in trash
+    id: String,
+
+    /// Path, that the note had before removal
+    original_path: PathBuf,
+
+    /// Stable identifier of the note (if it had one)
+    note_id: Option<String>,
+
+    /// Time of removal
+    deleted_at: SystemTime,
+}
+
+
+impl TrashEntry {
+    /// Obtains an identifier of the entry in trash.
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+
+    /// Obtains a path, that the note had before removal.
+    pub fn get_original_path(&self) -> &Path {
+        &self.original_path
+    }
+
+
+    /// Obtains a time of removal.
+    pub fn get_deleted_at(&self) -> SystemTime {
+        self.deleted_at
+    }
+
+
+    /// Obtains a stable identifier of the note (if it had one).
+    pub fn get_note_id(&self) -> Option<&str> {
+        self.note_id.as_deref()
+    }
+
+
+    /// Returns a path to trashed file relative to working directory.
+    pub(super) fn get_trashed_path(&self) -> PathBuf {
+        let file_name = self.original_path
+            .file_name()
+            .unwrap_or(self.original_path.as_os_str());
+
+        helpers::get_trash_directory()
+            .join(&self.id)
+            .join(file_name)
+    }
+
+
+    /// Returns the same entry with different original path.
+    /// 
+    /// * `original_path` - new original path
+    pub(super) fn with_original_path(mut self, original_path: PathBuf) -> Self {
+        self.original_path = original_path;
+        self
+    }
+
+
+    /// Parses an entry from JSON. Entries, whose identifier is not a UUID
+    /// or whose path may lead outside of working directory, are rejected,
+    /// because paths of trashed files are composed from them.
+    fn from_json(value: &sj::Value) -> Option<Self> {
+        let id = value.get(ID_KEY)?.as_str()?;
+        if Uuid::parse_str(id).ok()?.to_string() != id {
+            return None;
+        }
+
+        let original_path = PathBuf::from(value.get(PATH_KEY)?.as_str()?);
+        if !helpers::is_plain_relative_path(&original_path) || !helpers::is_note_path(&original_path) {
+            return None;
+        }
+
+        Some(TrashEntry {
+            id: id.to_owned(),
+            original_path,
+            note_id: value.get(NOTE_ID_KEY).and_then(sj::Value::as_str).map(str::to_owned),
+            deleted_at: UNIX_EPOCH + Duration::from_secs(value.get(DELETED_KEY)?.as_u64()?),
+        })
+    }
+
+
+    /// Converts an entry to JSON.
+    fn to_json(&self) -> sj::Value {
+        let deleted = self.deleted_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        let path = self.original_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        sj::json!({
+            ID_KEY: self.id,
+            PATH_KEY: path,
+            NOTE_ID_KEY: self.note_id,
+            DELETED_KEY: deleted,
+        })
+    }
+}
+
+
+/// Index of notes in trash.
+/// 
+/// Trashed notes are stored in repository's configuration folder, each in
+/// its own subfolder named with an entry identifier. Index is stored as
+/// a JSON array of entries.
+pub(super) struct Trash {
+    /// List of entries
+    entries: Vec<TrashEntry>,
+}
+
+
+impl Trash {
+    /// Loads trash index from a file. Absent file is treated as an
+    /// empty trash.
+    /// 
+    /// * `storage` - storage to read the file from
+    /// * `trash_file` - relative path to a file to read index from
+    pub(super) fn load(storage: &dyn Storage, trash_file: &Path) -> Result<Self> {
+        if !storage.exists(trash_file) {
+            return Ok(Trash { entries: Vec::new() });
+        }
+
+        let file_content = storage.read(trash_file)?;
+        let raw: sj::Value = sj::from_slice(file_content.as_slice())?;
+
+        raw.as_array()
+            .map(|entries| entries.iter().filter_map(TrashEntry::from_json).collect())
+            .map(|entries| Trash { entries })
+            .ok_or(Error::from_string("trash index is not a JSON array", ErrorCategory::Repo))
+    }
+
+
+    /// Checks if a trash index is well-formed, i.e. all its entries may be
+    /// loaded. Unlike [`Trash::load`], that skips invalid entries, the
+    /// whole index is rejected (e.g. when it comes from an archive).
+    /// 
+    /// * `content` - content of an index file
+    pub(super) fn check(content: &[u8]) -> Result<()> {
+        let raw: sj::Value = sj::from_slice(content)?;
+
+        let is_valid = raw
+            .as_array()
+            .is_some_and(|entries| entries.iter().all(|entry| TrashEntry::from_json(entry).is_some()));
+
+        match is_valid {
+            true => Ok(()),
+            false => Err(Error::from_string("trash index contains invalid entries", ErrorCategory::Repo))
+        }
+    }
+
+
+    /// Saves trash index into a file.
+    /// 
+    /// * `storage` - storage to write the file to
+    /// * `trash_file` - relative path to a file to write index to
+    pub(super) fn save(&self, storage: &dyn Storage, trash_file: &Path) -> Result<()> {
+        let raw = sj::Value::Array(self.entries.iter().map(TrashEntry::to_json).collect());
+        storage.write(trash_file, &sj::to_vec_pretty(&raw)?)
+    }
+
+
+    /// Creates a new entry for a note being removed right now.
+    /// 
+    /// * `relative_path` - relative to working directory path to the note
+    /// * `note_id` - stable identifier of the note (if any)
+    pub(super) fn push(&mut self, relative_path: &Path, note_id: Option<String>) -> &TrashEntry {
+        self.entries.push(TrashEntry {
+            id: Uuid::new_v4().to_string(),
+            original_path: relative_path.to_path_buf(),
+            note_id,
+            deleted_at: SystemTime::now(),
+        });
+
+        self.entries.last()
+            .expect("trash entry was just pushed")
+    }
+
+
+    /// Returns all entries in trash.
+    pub(super) fn get_entries(&self) -> &[TrashEntry] {
+        &self.entries
+    }
+
+
+    /// Looks for the most recently removed note with an original path
+    /// and an identifier.
+    /// 
+    /// * `relative_path` - original path of the note
+    /// * `note_id` - stable identifier of the note (if any)
+    pub(super) fn find_note(&self, relative_path: &Path, note_id: Option<&str>) -> Option<&TrashEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.original_path == relative_path && entry.get_note_id() == note_id)
+    }
+
+
+    /// Removes an entry from trash index and returns it.
+    /// 
+    /// * `id` - identifier of the entry
+    pub(super) fn take(&mut self, id: &str) -> Option<TrashEntry> {
+        let position = self.entries
+            .iter()
+            .position(|entry| entry.id == id)?;
+
+        Some(self.entries.remove(position))
+    }
+
+
+    /// Removes all entries, that are not accepted by a predicate, and
+    /// returns them.
+    /// 
+    /// * `predicate` - function, that decides if an entry should be kept
+    pub(super) fn take_unless<F>(&mut self, mut predicate: F) -> Vec<TrashEntry>
+    where
+        F: FnMut(&TrashEntry) -> bool
+    {
+        let (kept, taken) = self.entries
+            .drain(..)
+            .partition(|entry| predicate(entry));
+
+        self.entries = kept;
+        taken
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::MemoryStorage;
+
+
+    /// Loads trash index with a single entry.
+    fn load_entry(id: &str, path: &str) -> Vec<TrashEntry> {
+        let storage = MemoryStorage::new();
+        let trash_file = helpers::get_trash_file();
+
+        let raw = sj::json!([{ ID_KEY: id, PATH_KEY: path, DELETED_KEY: 0 }]);
+        storage.write(&trash_file, &sj::to_vec(&raw).unwrap()).unwrap();
+
+        Trash::load(&storage, &trash_file).unwrap().entries
+    }
+
+
+    #[test]
+    fn entry_survives_index() {
+        let storage = MemoryStorage::new();
+        let trash_file = helpers::get_trash_file();
+
+        let mut trash = Trash { entries: Vec::new() };
+        trash.push(Path::new("folder/note.md"), Some(String::from("note")));
+        trash.save(&storage, &trash_file).unwrap();
+
+        let loaded = Trash::load(&storage, &trash_file).unwrap();
+        let entry = &loaded.get_entries()[0];
+        assert_eq!(entry.get_original_path(), Path::new("folder/note.md"));
+        assert_eq!(entry.get_note_id(), Some("note"));
+        assert_eq!(entry.get_trashed_path(), helpers::get_trash_directory().join(entry.get_id()).join("note.md"));
+    }
+
+
+    #[test]
+    fn unsafe_entries_are_rejected() {
+        let id = Uuid::new_v4().to_string();
+        assert_eq!(load_entry(&id, "folder/note.md").len(), 1);
+
+        for unsafe_id in ["../../..", "", "entry", &id.to_uppercase(), &format!("{{{}}}", id)] {
+            assert!(load_entry(unsafe_id, "note.md").is_empty(), "{}", unsafe_id);
+        }
+
+        for unsafe_path in ["", "../note.md", "/etc/passwd", "folder/../../note.md", ".mm/mm_ids.json", "attachments/file.png"] {
+            assert!(load_entry(&id, unsafe_path).is_empty(), "{}", unsafe_path);
+        }
+    }
+}
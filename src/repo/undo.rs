@@ -0,0 +1,223 @@
+use crate::data::Revision;
+use super::operation::{Operation, OperationKind};
+
+
+/// An operation, that may be undone or redone.
+pub(super) struct UndoStep {
+    /// Identifier of a revision, that recorded the operation
+    revision_id: String,
+
+    /// Identifier of the revision preceding the operation (if any)
+    previous_id: Option<String>,
+
+    /// The operation itself (paths are relative to working directory)
+    operation: Operation,
+}
+
+
+impl UndoStep {
+    /// Obtains an identifier of a revision, that recorded the operation.
+    pub(super) fn get_revision_id(&self) -> &str {
+        &self.revision_id
+    }
+
+
+    /// Obtains an identifier of the revision preceding the operation.
+    pub(super) fn get_previous_id(&self) -> Option<&str> {
+        self.previous_id.as_deref()
+    }
+
+
+    /// Obtains the operation.
+    pub(super) fn get_operation(&self) -> &Operation {
+        &self.operation
+    }
+}
+
+
+/// Undo and redo stacks of a repository.
+/// 
+/// Stacks are not stored anywhere, but restored from operations recorded
+/// in history, so they survive reopening of a repository. Operations, that
+/// cannot be undone, and commits made outside of the library clear both
+/// stacks, because changes before them cannot be reverted safely.
+#[derive(Default)]
+pub(super) struct UndoStacks {
+    /// Operations, that may be undone (the newest last)
+    undo: Vec<UndoStep>,
+
+    /// Undone operations, that may be redone (the most recently undone last)
+    redo: Vec<UndoStep>,
+}
+
+
+impl UndoStacks {
+    /// Restores stacks from history.
+    /// 
+    /// * `revisions` - history of the whole repository (the newest first)
+    pub(super) fn from_history(revisions: &[Revision]) -> Self {
+        let mut stacks = UndoStacks::default();
+
+        for revision in revisions.iter().rev() {
+            let operation = match Operation::parse(revision.get_message()) {
+                Some(operation) => operation,
+                None => {
+                    stacks.clear();
+                    continue;
+                }
+            };
+
+            match operation.get_kind() {
+                OperationKind::Undo => stacks.move_top(operation.get_target(), true),
+                OperationKind::Redo => stacks.move_top(operation.get_target(), false),
+                kind if kind.is_reversible() => {
+                    stacks.redo.clear();
+                    stacks.undo.push(UndoStep {
+                        revision_id: revision.get_id().to_owned(),
+                        previous_id: revision.get_parent_id().map(str::to_owned),
+                        operation
+                    });
+                },
+                _ => stacks.clear()
+            }
+        }
+
+        stacks
+    }
+
+
+    /// Obtains an operation, that is undone next (if any).
+    pub(super) fn get_undo(&self) -> Option<&UndoStep> {
+        self.undo.last()
+    }
+
+
+    /// Obtains an operation, that is redone next (if any).
+    pub(super) fn get_redo(&self) -> Option<&UndoStep> {
+        self.redo.last()
+    }
+
+
+    /// Moves the top operation from one stack to another. Stacks, that
+    /// do not match the history, are cleared.
+    /// 
+    /// * `target` - identifier of a revision, that is expected on top
+    /// * `undo` - `true` to move from undo stack to redo one, `false`
+    ///   to move back
+    fn move_top(&mut self, target: Option<&str>, undo: bool) {
+        let (from, to) = match undo {
+            true => (&mut self.undo, &mut self.redo),
+            false => (&mut self.redo, &mut self.undo)
+        };
+
+        match from.pop() {
+            Some(step) if target == Some(step.get_revision_id()) => to.push(step),
+            _ => self.clear()
+        }
+    }
+
+
+    /// Clears both stacks.
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::repo::operation::ChangeAction;
+
+
+    /// Creates a revision, that records an operation.
+    fn create_revision(id: &str, parent_id: Option<&str>, operation: &Operation) -> Revision {
+        let revision = Revision::new(id, operation.compose_message(None), "Tester", SystemTime::now());
+        match parent_id {
+            Some(parent_id) => revision.with_parent_id(parent_id),
+            None => revision
+        }
+    }
+
+
+    /// Creates an operation, that changes a note.
+    fn create_update(path: &str) -> Operation {
+        Operation::new(OperationKind::Update).with_change(ChangeAction::Modified, Path::new(path), Some("id"))
+    }
+
+
+    #[test]
+    fn steps_refer_to_actual_parents() {
+        //
+        // History is listed in topological order, so the revision
+        // listed after "c" is not necessarily its parent
+        //
+
+        let revisions = vec![
+            create_revision("c", Some("a"), &create_update("c.md")),
+            create_revision("b", Some("a"), &create_update("b.md")),
+            create_revision("a", None, &create_update("a.md")),
+        ];
+
+        let stacks = UndoStacks::from_history(&revisions);
+        let step = stacks.get_undo().unwrap();
+
+        assert_eq!(step.get_revision_id(), "c");
+        assert_eq!(step.get_previous_id(), Some("a"));
+    }
+
+
+    #[test]
+    fn undo_and_redo_move_steps_between_stacks() {
+        let revisions = vec![
+            create_revision("u", Some("b"), &Operation::new(OperationKind::Undo).with_target("b")),
+            create_revision("b", Some("a"), &create_update("b.md")),
+            create_revision("a", None, &create_update("a.md")),
+        ];
+
+        let stacks = UndoStacks::from_history(&revisions);
+        assert_eq!(stacks.get_undo().unwrap().get_revision_id(), "a");
+        assert_eq!(stacks.get_redo().unwrap().get_revision_id(), "b");
+
+        let mut revisions = revisions;
+        revisions.insert(0, create_revision("r", Some("u"), &Operation::new(OperationKind::Redo).with_target("b")));
+
+        let stacks = UndoStacks::from_history(&revisions);
+        assert_eq!(stacks.get_undo().unwrap().get_revision_id(), "b");
+        assert!(stacks.get_redo().is_none());
+    }
+
+
+    #[test]
+    fn foreign_revision_clears_stacks() {
+        let revisions = vec![
+            create_revision("c", Some("b"), &create_update("c.md")),
+            Revision::new("b", "fix: made elsewhere", "Tester", SystemTime::now()).with_parent_id("a"),
+            create_revision("a", None, &create_update("a.md")),
+        ];
+
+        let mut stacks = UndoStacks::from_history(&revisions);
+        assert_eq!(stacks.get_undo().unwrap().get_revision_id(), "c");
+
+        stacks.undo.pop();
+        assert!(stacks.get_undo().is_none());
+    }
+
+
+    #[test]
+    fn mismatched_undo_clears_stacks() {
+        let revisions = vec![
+            create_revision("u", Some("b"), &Operation::new(OperationKind::Undo).with_target("a")),
+            create_revision("b", Some("a"), &create_update("b.md")),
+            create_revision("a", None, &create_update("a.md")),
+        ];
+
+        let stacks = UndoStacks::from_history(&revisions);
+        assert!(stacks.get_undo().is_none());
+        assert!(stacks.get_redo().is_none());
+    }
+}
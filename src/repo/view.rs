@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::error::Result;
+use crate::data::{Revision, StatusEntry};
+use super::repository::Repository;
+use super::trash::{TrashEntry};
+use super::metadata::{NoteMetadata};
+use super::lock::{LockMode};
+use super::operation::{Activity};
+use super::ArchiveFormat;
+
+
+/// A read-only view of a repository.
+/// 
+/// Many methods of [`Repository`] take `&self`, but change files of the
+/// repository (e.g. [`Repository::write_note`]). The view exposes only
+/// methods, that change nothing inside of the repository, so it may be
+/// used by concurrent readers (see [`super::SharedRepository::read`]).
+#[derive(Clone, Copy)]
+pub struct RepositoryView<'a> {
+    /// Viewed repository
+    repo: &'a Repository,
+}
+
+
+impl<'a> RepositoryView<'a> {
+    /// Creates a view of a repository.
+    /// 
+    /// * `repo` - repository to view
+    pub fn new(repo: &'a Repository) -> Self {
+        RepositoryView {
+            repo
+        }
+    }
+
+
+    /// Obtains a working directory (see [`Repository::get_workdir`]).
+    pub fn get_workdir(&self) -> Result<&'a Path> {
+        self.repo.get_workdir()
+    }
+
+
+    /// Obtains a name (see [`Repository::get_name`]).
+    pub fn get_name(&self) -> &'a str {
+        self.repo.get_name()
+    }
+
+
+    /// Obtains a lock mode (see [`Repository::get_lock_mode`]).
+    pub fn get_lock_mode(&self) -> Option<LockMode> {
+        self.repo.get_lock_mode()
+    }
+
+
+    /// Reads content of a note (see [`Repository::read_note`]).
+    /// 
+    /// * `note_path` - absolute path to a note to read
+    pub fn read_note(&self, note_path: &Path) -> Result<Vec<u8>> {
+        self.repo.read_note(note_path)
+    }
+
+
+    /// Returns history of revisions (see [`Repository::get_history`]).
+    /// 
+    /// * `note_path` - absolute path to a note to get history of (`None`
+    ///   means history of the whole repository)
+    pub fn get_history(&self, note_path: Option<&Path>) -> Result<Vec<Revision>> {
+        self.repo.get_history(note_path)
+    }
+
+
+    /// Returns operations made through the library
+    /// (see [`Repository::get_activity`]).
+    /// 
+    /// * `note_path` - absolute path to a note to get activity of (`None`
+    ///   means activity of the whole repository)
+    pub fn get_activity(&self, note_path: Option<&Path>) -> Result<Vec<Activity>> {
+        self.repo.get_activity(note_path)
+    }
+
+
+    /// Returns uncommitted changes (see [`Repository::status`]).
+    pub fn status(&self) -> Result<Vec<StatusEntry>> {
+        self.repo.status()
+    }
+
+
+    /// Returns list of notes in trash (see [`Repository::list_trash`]).
+    pub fn list_trash(&self) -> Result<Vec<TrashEntry>> {
+        self.repo.list_trash()
+    }
+
+
+    /// Looks for a note by its identifier (see [`Repository::find_by_id`]).
+    /// 
+    /// * `id` - identifier of a note
+    pub fn find_by_id(&self, id: &str) -> Result<Option<PathBuf>> {
+        self.repo.find_by_id(id)
+    }
+
+
+    /// Obtains an identifier of a note (see [`Repository::get_note_id`]).
+    /// 
+    /// * `note_path` - absolute path to a note
+    pub fn get_note_id(&self, note_path: &Path) -> Result<Option<String>> {
+        self.repo.get_note_id(note_path)
+    }
+
+
+    /// Obtains metadata of a note (see [`Repository::get_note_metadata`]).
+    /// 
+    /// * `note_path` - absolute path to a note
+    pub fn get_note_metadata(&self, note_path: &Path) -> Result<NoteMetadata> {
+        self.repo.get_note_metadata(note_path)
+    }
+
+
+    /// Collects notes, that reference each attachment
+    /// (see [`Repository::get_attachment_references`]).
+    pub fn get_attachment_references(&self) -> Result<HashMap<PathBuf, Vec<PathBuf>>> {
+        self.repo.get_attachment_references()
+    }
+
+
+    /// Returns names of templates (see [`Repository::list_templates`]).
+    pub fn list_templates(&self) -> Result<Vec<String>> {
+        self.repo.list_templates()
+    }
+
+
+    /// Lists journal entries within a date range
+    /// (see [`Repository::list_journal_entries`]).
+    /// 
+    /// * `from` - first date of the range
+    /// * `to` - last date of the range
+    pub fn list_journal_entries(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<(NaiveDate, PathBuf)>> {
+        self.repo.list_journal_entries(from, to)
+    }
+
+
+    /// Checks if the repository is locked (see [`Repository::is_locked`]).
+    pub fn is_locked(&self) -> Result<bool> {
+        self.repo.is_locked()
+    }
+
+
+    /// Exports the repository into a static HTML site
+    /// (see [`Repository::export_html`]).
+    /// 
+    /// * `destination` - path to a folder for the site
+    pub fn export_html(&self, destination: &Path) -> Result<()> {
+        self.repo.export_html(destination)
+    }
+
+
+    /// Exports the repository into an archive
+    /// (see [`Repository::export_archive`]).
+    /// 
+    /// * `archive_path` - path to an archive to create
+    /// * `format` - format of the archive
+    /// * `with_history` - `true` to include full history
+    pub fn export_archive(&self, archive_path: &Path, format: ArchiveFormat, with_history: bool) -> Result<()> {
+        self.repo.export_archive(archive_path, format, with_history)
+    }
+
+
+    /// Exports the repository into a static HTML site checking for
+    /// cancellation (see [`Repository::export_html`]).
+    /// 
+    /// * `destination` - path to a folder for the site
+    /// * `check_cancelled` - function, that fails if the export is cancelled
+    #[cfg(feature = "async")]
+    pub(super) fn export_html_with(&self, destination: &Path, check_cancelled: &dyn Fn() -> Result<()>) -> Result<()> {
+        self.repo.export_html_with(destination, check_cancelled)
+    }
+
+
+    /// Exports the repository into an archive checking for cancellation
+    /// (see [`Repository::export_archive`]).
+    /// 
+    /// * `archive_path` - path to an archive to create
+    /// * `format` - format of the archive
+    /// * `with_history` - `true` to include full history
+    /// * `check_cancelled` - function, that fails if the export is cancelled
+    #[cfg(feature = "async")]
+    pub(super) fn export_archive_with(&self, archive_path: &Path, format: ArchiveFormat, with_history: bool, check_cancelled: &dyn Fn() -> Result<()>) -> Result<()> {
+        self.repo.export_archive_with(archive_path, format, with_history, check_cancelled)
+    }
+}
+
+
+impl<'a> From<&'a Repository> for RepositoryView<'a> {
+    fn from(repo: &'a Repository) -> Self {
+        RepositoryView::new(repo)
+    }
+}
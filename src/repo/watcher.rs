@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use notify_debouncer_mini::{new_debouncer, Debouncer, DebounceEventResult};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+
+use crate::error::{Result, Error};
+use super::shared::SharedRepository;
+
+
+/// Watcher, that commits changes of notes made outside of the library
+/// (e.g. by external editors) in repository's working directory.
+/// 
+/// Changes are debounced, so a burst of changes (e.g. an editor saving
+/// a file in several steps) results in a single commit. Internal folders
+/// (`.git`, `.mm`) are ignored. Moved notes are detected and keep their
+/// identifiers. Committed changes are reported as the same events as
+/// changes made through the library (see [`crate::repo::RepositoryEvent`]).
+/// 
+/// Watching stops when the watcher is dropped.
+pub struct RepositoryWatcher {
+    /// Debounced filesystem watcher (it is kept only to watch until drop)
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+
+impl RepositoryWatcher {
+    /// Starts watching a repository.
+    /// 
+    /// * `repo` - repository to watch
+    /// * `delay` - time without changes of a file, after which the file
+    ///   is committed
+    /// * `on_error` - function, that reports errors of watching and
+    ///   committing (it is called on watcher's thread)
+    pub fn start<E>(repo: SharedRepository, delay: Duration, mut on_error: E) -> Result<Self>
+    where
+        E: FnMut(Error) + Send + 'static
+    {
+        let workdir = repo.read(|repo| Ok(repo.get_workdir()?.to_path_buf()))?;
+
+        let mut debouncer = new_debouncer(delay, move |result: DebounceEventResult| {
+            let paths: Vec<_> = match result {
+                Ok(events) => events
+                    .into_iter()
+                    .map(|event| event.path)
+                    .collect(),
+                Err(error) => return on_error(Error::from(error))
+            };
+
+            if let Err(error) = repo.write(|repo| repo.commit_watched_changes(&paths)) {
+                on_error(error);
+            }
+        })?;
+
+        debouncer
+            .watcher()
+            .watch(&workdir, RecursiveMode::Recursive)?;
+
+        Ok(RepositoryWatcher {
+            _debouncer: debouncer
+        })
+    }
+}
@@ -0,0 +1,21 @@
+#[allow(clippy::module_inception)]
+mod wiki;
+
+
+pub(crate) use self::wiki::{
+    WikiLink,
+    LinkResolver,
+    replace_wiki_links,
+    collect_note_tags,
+    split_front_matter,
+    resolve_relative_link,
+    get_relative_link,
+    normalize,
+};
+
+
+/// Delimiter of YAML front matter
+const MM_FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Keys of front matter, that contain tags
+const MM_FRONT_MATTER_TAGS_KEYS: [&str; 2] = ["tags", "tag"];
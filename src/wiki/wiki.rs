@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf, Component};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use super::{MM_FRONT_MATTER_DELIMITER, MM_FRONT_MATTER_TAGS_KEYS};
+
+
+/// A wiki link, e.g. `[[target#heading|title]]` or an embedding 
+/// `![[target]]`.
+pub(crate) struct WikiLink<'a> {
+    /// Target of the link (a note or a file)
+    target: &'a str,
+
+    /// Optional heading inside of the target
+    heading: Option<&'a str>,
+
+    /// Optional title of the link
+    title: Option<&'a str>,
+
+    /// `true` if the target is embedded rather than linked
+    embed: bool,
+
+    /// Original text of the link
+    text: &'a str,
+}
+
+
+impl<'a> WikiLink<'a> {
+    /// Obtains a target of the link.
+    pub(crate) fn get_target(&self) -> &'a str {
+        self.target
+    }
+
+
+    /// Obtains a heading inside of the target (if any).
+    pub(crate) fn get_heading(&self) -> Option<&'a str> {
+        self.heading
+    }
+
+
+    /// Obtains a title of the link. Target is used if the link has 
+    /// no explicit title.
+    pub(crate) fn get_title(&self) -> &'a str {
+        self.title.unwrap_or(self.target)
+    }
+
+
+    /// Checks if the target is embedded rather than linked.
+    pub(crate) fn is_embed(&self) -> bool {
+        self.embed
+    }
+
+
+    /// Obtains original text of the link.
+    pub(crate) fn get_text(&self) -> &'a str {
+        self.text
+    }
+}
+
+
+/// Resolves targets of wiki links into files.
+/// 
+/// A target may be a path to a file relative to repository's root or just
+/// a name of a file, like in Obsidian. Extension of Markdown notes may be
+/// omitted. Matching is case-insensitive.
+pub(crate) struct LinkResolver {
+    /// Files by their relative paths
+    by_path: HashMap<String, PathBuf>,
+
+    /// Files by their names
+    by_name: HashMap<String, PathBuf>,
+}
+
+
+impl LinkResolver {
+    /// Creates a resolver for a set of files.
+    /// 
+    /// * `files` - paths to files relative to repository's root
+    /// * `is_note` - function, that checks if a file is a Markdown note
+    pub(crate) fn new<F>(files: &[PathBuf], is_note: F) -> Self
+    where
+        F: Fn(&Path) -> bool
+    {
+        let mut by_path = HashMap::new();
+        let mut by_name = HashMap::new();
+
+        //
+        // Shorter paths are inserted first, so a name refers to the
+        // closest to root file like in Obsidian
+        //
+
+        let mut files: Vec<&PathBuf> = files.iter().collect();
+        files.sort_by_key(|file| (file.components().count(), file.as_path()));
+
+        for file in files {
+            let mut keys = vec![(normalize(file), file.file_name())];
+            if is_note(file) {
+                keys.push((normalize(&file.with_extension("")), file.file_stem()));
+            }
+
+            for (path, name) in keys {
+                by_path
+                    .entry(path.to_lowercase())
+                    .or_insert_with(|| file.clone());
+
+                if let Some(name) = name {
+                    by_name
+                        .entry(name.to_string_lossy().to_lowercase())
+                        .or_insert_with(|| file.clone());
+                }
+            }
+        }
+
+        LinkResolver {
+            by_path,
+            by_name
+        }
+    }
+
+
+    /// Resolves a target of a wiki link. Returns `None` if there is
+    /// no such file.
+    /// 
+    /// * `target` - target of a link
+    pub(crate) fn resolve(&self, target: &str) -> Option<&Path> {
+        let target = normalize(Path::new(target.trim()))
+            .to_lowercase();
+
+        self.by_path
+            .get(&target)
+            .or_else(|| self.by_name.get(&target))
+            .map(PathBuf::as_path)
+    }
+}
+
+
+/// Replaces wiki links (`[[target]]`, `[[target#heading|title]]` and 
+/// `![[target]]`) in Markdown text. Fenced code blocks are left intact.
+/// 
+/// * `markdown` - Markdown text
+/// * `replace` - function, that returns a replacement for a link
+pub(crate) fn replace_wiki_links<F>(markdown: &str, mut replace: F) -> String
+where
+    F: FnMut(&WikiLink) -> String
+{
+    let mut result = String::with_capacity(markdown.len());
+    let mut in_fence = false;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        if in_fence {
+            result.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let end = match rest[start..].find("]]") {
+                Some(end) => start + end,
+                None => break
+            };
+
+            let embed = rest[..start].ends_with('!');
+            let text_start = if embed { start - 1 } else { start };
+
+            result.push_str(&rest[..text_start]);
+
+            let content = &rest[start + 2..end];
+            let (target, title) = match content.split_once('|') {
+                Some((target, title)) => (target, Some(title.trim())),
+                None => (content, None)
+            };
+
+            let (target, heading) = match target.split_once('#') {
+                Some((target, heading)) => (target, Some(heading.trim())),
+                None => (target, None)
+            };
+
+            let link = WikiLink {
+                target: target.trim(),
+                heading,
+                title,
+                embed,
+                text: &rest[text_start..end + 2]
+            };
+
+            result.push_str(&replace(&link));
+            rest = &rest[end + 2..];
+        }
+
+        result.push_str(rest);
+    }
+
+    result
+}
+
+
+/// Collects tags (e.g. `#project` or `#area/work`) from a text. Tags must
+/// be preceded by a whitespace (or start the text) and contain at least
+/// one non-digit character.
+/// 
+/// * `text` - text to collect tags from
+/// * `tags` - collection of tags to extend
+fn collect_tags(text: &str, tags: &mut Vec<String>) {
+    let is_tag_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-' || c == '/';
+
+    let mut previous = None;
+    for (position, c) in text.char_indices() {
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            let tag: String = text[position + 1..]
+                .chars()
+                .take_while(|c| is_tag_char(*c))
+                .collect();
+
+            let tag = tag.trim_end_matches('/');
+            if tag.chars().any(|c| !c.is_ascii_digit()) {
+                tags.push(tag.to_owned());
+            }
+        }
+
+        previous = Some(c);
+    }
+}
+
+
+/// Collects tags of a note: ones listed in YAML front matter (`tags` or
+/// `tag` key) and inline ones outside of code. Result is sorted and has
+/// no duplicates.
+/// 
+/// * `markdown` - content of a note
+pub(crate) fn collect_note_tags(markdown: &str) -> Vec<String> {
+    let (front_matter, body) = split_front_matter(markdown);
+
+    let mut tags = front_matter
+        .map(collect_front_matter_tags)
+        .unwrap_or_default();
+
+    let mut in_code_block = false;
+    for event in Parser::new_ext(body, Options::all()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(text) if !in_code_block => collect_tags(&text, &mut tags),
+            _ => {}
+        }
+    }
+
+    tags.sort();
+    tags.dedup();
+
+    tags
+}
+
+
+/// Resolves a relative destination of a regular Markdown link against
+/// repository's root. Returns the target path and a fragment (with `#`).
+/// External, absolute and anchor-only links as well as links leading
+/// outside of the root are not resolved.
+/// 
+/// * `relative_path` - path to a note with the link relative to repository's root
+/// * `destination` - destination of the link
+pub(crate) fn resolve_relative_link<'a>(relative_path: &Path, destination: &'a str) -> Option<(PathBuf, &'a str)> {
+    if destination.contains("://") || destination.starts_with('/') || destination.starts_with('#') {
+        return None;
+    }
+
+    let (path, fragment) = match destination.find('#') {
+        Some(position) => destination.split_at(position),
+        None => (destination, "")
+    };
+
+    let path = path.replace("%20", " ");
+
+    let mut target = PathBuf::new();
+    for component in relative_path.parent().unwrap_or(Path::new("")).join(path).components() {
+        match component {
+            Component::ParentDir => {
+                if !target.pop() {
+                    return None;
+                }
+            },
+            Component::CurDir => {},
+            component => target.push(component)
+        }
+    }
+
+    Some((target, fragment))
+}
+
+
+/// Composes a relative link from one file to another. Both paths are
+/// relative to the same root (e.g. repository's root or site's root).
+/// 
+/// * `from` - path to a file with the link
+/// * `to` - path to a target of the link
+pub(crate) fn get_relative_link(from: &Path, to: &Path) -> String {
+    let depth = from
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+
+    format!("{}{}", "../".repeat(depth), normalize(to))
+}
+
+
+/// Splits YAML front matter (if any) from note's body.
+/// 
+/// * `markdown` - content of a note
+pub(crate) fn split_front_matter(markdown: &str) -> (Option<&str>, &str) {
+    let mut lines = markdown.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some(MM_FRONT_MATTER_DELIMITER) {
+        return (None, markdown);
+    }
+
+    let start = markdown.find('\n').map_or(markdown.len(), |position| position + 1);
+
+    let mut position = start;
+    for line in lines {
+        if line.trim_end() == MM_FRONT_MATTER_DELIMITER {
+            return (Some(&markdown[start..position]), &markdown[position + line.len()..]);
+        }
+
+        position += line.len();
+    }
+
+    (None, markdown)
+}
+
+
+/// Converts a path into a string with forward slashes.
+/// 
+/// * `path` - path to convert
+pub(crate) fn normalize(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+
+/// Collects tags from YAML front matter. Supports inline lists 
+/// (`tags: [a, b]`), plain values (`tags: a, b` or `tags: a b`) and
+/// block lists (`- a` lines after `tags:`).
+/// 
+/// * `front_matter` - text of front matter without delimiters
+fn collect_front_matter_tags(front_matter: &str) -> Vec<String> {
+    let clean = |tag: &str| -> Option<String> {
+        let tag = tag
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .trim_start_matches('#');
+
+        (!tag.is_empty()).then(|| tag.to_owned())
+    };
+
+    let mut tags = Vec::new();
+    let mut in_list = false;
+
+    for line in front_matter.lines() {
+        if in_list {
+            match line.trim_start().strip_prefix('-') {
+                Some(item) => {
+                    tags.extend(clean(item));
+                    continue;
+                },
+                None => in_list = false
+            }
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue
+        };
+
+        if !MM_FRONT_MATTER_TAGS_KEYS.contains(&key.trim()) {
+            continue;
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            in_list = true;
+            continue;
+        }
+
+        let value = value
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+
+        tags.extend(value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(clean));
+    }
+
+    tags
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Checks if a file is a Markdown note.
+    fn is_note(path: &Path) -> bool {
+        path.extension().is_some_and(|extension| extension == "md")
+    }
+
+
+    #[test]
+    fn wiki_links_are_parsed() {
+        let mut links = Vec::new();
+        let result = replace_wiki_links("See [[Note#Intro|the note]] and ![[image.png]].\n", |link| {
+            links.push((
+                link.get_target().to_owned(),
+                link.get_heading().map(str::to_owned),
+                link.get_title().to_owned(),
+                link.is_embed(),
+                link.get_text().to_owned()
+            ));
+
+            String::from("<link>")
+        });
+
+        assert_eq!(result, "See <link> and <link>.\n");
+        assert_eq!(links, vec![
+            (String::from("Note"), Some(String::from("Intro")), String::from("the note"), false, String::from("[[Note#Intro|the note]]")),
+            (String::from("image.png"), None, String::from("image.png"), true, String::from("![[image.png]]")),
+        ]);
+    }
+
+
+    #[test]
+    fn fenced_and_unclosed_links_are_kept() {
+        let markdown = "```\n[[Code]]\n```\n[[Unclosed\n";
+        let result = replace_wiki_links(markdown, |_| String::from("<link>"));
+
+        assert_eq!(result, markdown);
+    }
+
+
+    #[test]
+    fn targets_are_resolved_by_path_and_name() {
+        let files = vec![
+            PathBuf::from("folder/Deep.md"),
+            PathBuf::from("Deep.md"),
+            PathBuf::from("attachments/image.png"),
+        ];
+        let resolver = LinkResolver::new(&files, is_note);
+
+        assert_eq!(resolver.resolve("deep"), Some(Path::new("Deep.md")));
+        assert_eq!(resolver.resolve("folder/deep"), Some(Path::new("folder/Deep.md")));
+        assert_eq!(resolver.resolve("Folder/Deep.md"), Some(Path::new("folder/Deep.md")));
+        assert_eq!(resolver.resolve("image.png"), Some(Path::new("attachments/image.png")));
+        assert_eq!(resolver.resolve("image"), None);
+        assert_eq!(resolver.resolve("missing"), None);
+    }
+
+
+    #[test]
+    fn tags_are_collected_outside_of_code() {
+        let markdown = "---\ntags: [one, \"#two\"]\ntag:\n  - three\ntitle: x\n---\n#four and #area/five/ but not #123 or a#six\n\n```\n#seven\n```\n";
+
+        assert_eq!(collect_note_tags(markdown), vec!["area/five", "four", "one", "three", "two"]);
+    }
+
+
+    #[test]
+    fn front_matter_is_split() {
+        assert_eq!(split_front_matter("---\ntags: a\n---\n# Note\n"), (Some("tags: a\n"), "# Note\n"));
+        assert_eq!(split_front_matter("---\ntags: a\n# Note\n"), (None, "---\ntags: a\n# Note\n"));
+        assert_eq!(split_front_matter("# Note\n---\n"), (None, "# Note\n---\n"));
+    }
+
+
+    #[test]
+    fn relative_links_stay_inside_of_root() {
+        let note = Path::new("folder/note.md");
+
+        assert_eq!(resolve_relative_link(note, "../other%20note.md#part"), Some((PathBuf::from("other note.md"), "#part")));
+        assert_eq!(resolve_relative_link(note, "./image.png"), Some((PathBuf::from("folder/image.png"), "")));
+        assert_eq!(resolve_relative_link(note, "../../outside.md"), None);
+        assert_eq!(resolve_relative_link(note, "https://example.com"), None);
+        assert_eq!(resolve_relative_link(note, "#heading"), None);
+    }
+
+
+    #[test]
+    fn relative_links_climb_to_root() {
+        assert_eq!(get_relative_link(Path::new("note.md"), Path::new("folder/other.md")), "folder/other.md");
+        assert_eq!(get_relative_link(Path::new("a/b/note.md"), Path::new("attachments/image.png")), "../../attachments/image.png");
+        assert_eq!(get_relative_link(Path::new("a/note.md"), Path::new("a/other note.md")), "../a/other note.md");
+    }
+}
@@ -0,0 +1,87 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::os::unix::fs::PermissionsExt;
+
+use libmm::repo::{Repository, OperationKind};
+use libmm::error::ErrorCategory;
+
+
+/// Writes an executable shell script, that acts as an editor.
+fn write_editor(name: &str, body: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("mm-editor-test-{}", std::process::id()));
+    fs::create_dir_all(&folder).unwrap();
+
+    let script = folder.join(name);
+    fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o700)).unwrap();
+
+    script
+}
+
+
+/// Creates an in-memory repository with a note and an editor.
+fn create_repo(editor: &Path) -> (Repository, PathBuf) {
+    let mut repo = Repository::in_memory("editor", "Tester", "tester@example.com").unwrap();
+    let note = repo.get_workdir().unwrap().join("note.md");
+
+    repo.write_note(&note, b"original").unwrap();
+    repo.set_editor(Some(editor.to_str().unwrap()));
+
+    (repo, note)
+}
+
+
+#[test]
+fn edited_note_is_committed() {
+    let editor = write_editor("replace.sh", "printf 'edited by script' > \"$1\"");
+    let (repo, note) = create_repo(&editor);
+
+    assert!(repo.edit_note(&note).unwrap());
+    assert_eq!(repo.read_note(&note).unwrap(), b"edited by script");
+
+    let activity = repo.get_activity(Some(&note)).unwrap();
+    assert_eq!(activity.len(), 2);
+    assert_eq!(activity[0].get_operation().get_kind(), OperationKind::Update);
+}
+
+
+#[test]
+fn unchanged_note_is_not_committed() {
+    let editor = write_editor("noop.sh", "true");
+    let (repo, note) = create_repo(&editor);
+
+    assert!(!repo.edit_note(&note).unwrap());
+    assert_eq!(repo.get_activity(Some(&note)).unwrap().len(), 1);
+}
+
+
+#[test]
+fn failed_editor_keeps_note() {
+    let editor = write_editor("fail.sh", "printf 'garbage' > \"$1\"; exit 1");
+    let (repo, note) = create_repo(&editor);
+
+    let error = repo.edit_note(&note).unwrap_err();
+    assert_eq!(error.get_category(), ErrorCategory::Editor);
+    assert_eq!(repo.read_note(&note).unwrap(), b"original");
+}
+
+
+#[test]
+fn temporary_copy_is_private_and_removed() {
+    let report = std::env::temp_dir().join(format!("mm-editor-report-{}", std::process::id()));
+    let editor = write_editor("report.sh", &format!(
+        "ls -ld \"$(dirname \"$1\")\" > '{0}'; ls -l \"$1\" >> '{0}'; echo \"$1\" >> '{0}'",
+        report.display()));
+
+    let (repo, note) = create_repo(&editor);
+    assert!(!repo.edit_note(&note).unwrap());
+
+    let report = fs::read_to_string(&report).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert!(lines[0].starts_with("drwx------"));
+    assert!(lines[1].starts_with("-rw-------"));
+    assert!(!Path::new(lines[2]).exists());
+    assert!(!Path::new(lines[2]).parent().unwrap().exists());
+}